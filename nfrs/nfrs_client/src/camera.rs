@@ -0,0 +1,333 @@
+use bevy::prelude::*;
+use bevy::render::camera::{CameraUpdateSystem, Viewport};
+use bevy::render::view::RenderLayers;
+use bevy::transform::TransformSystem;
+use bevy::window::PrimaryWindow;
+use nfrs_shared::{CarVelocity, TrackLayout, MAX_LOCAL_PLAYERS};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::controls::{Action, ActionState};
+use crate::impact::CameraShake;
+use crate::settings::Settings;
+use crate::spectator::Spectating;
+use crate::{AppState, LocalCar, LocalPlayer};
+
+// Zoom of the chase cameras with the car at rest
+const CHASE_SCALE: f32 = 0.03;
+// Extra zoom out reached at FULL_ZOOM_SPEED
+const SPEED_ZOOM: f32 = 0.015;
+const FULL_ZOOM_SPEED: f32 = 25.0;
+// Seconds of travel the chase camera looks ahead of the car
+const LOOK_AHEAD: f32 = 0.4;
+// How far behind the car the camera looks while looking back
+const LOOK_BACK_DISTANCE: f32 = 12.0;
+// How quickly the camera catches up, per second
+const FOLLOW_SMOOTHING: f32 = 5.0;
+const ROTATION_SMOOTHING: f32 = 4.0;
+const ZOOM_SMOOTHING: f32 = 2.0;
+// Empty space around the track in the overview
+const OVERVIEW_MARGIN: f32 = 1.05;
+
+/// Where the active camera controller wants the camera; applied at the end of every
+/// frame with the screen shake on top
+#[derive(Component)]
+pub struct CameraRig {
+    /// Local player this camera follows; only split-screen has more than the first
+    pub seat: u8,
+    pub position: Vec2,
+    /// Radians; the view turns with it
+    pub rotation: f32,
+    /// Orthographic scale, in world units per pixel
+    pub scale: f32,
+}
+
+impl Default for CameraRig {
+    fn default() -> Self {
+        Self {
+            seat: 0,
+            position: Vec2::ZERO,
+            rotation: 0.0,
+            scale: 0.05,
+        }
+    }
+}
+
+/// How the camera follows the local car; C cycles through them
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriverCamera {
+    /// Smoothly follows the car, looking ahead of it and zooming out with speed
+    #[default]
+    Chase,
+    /// Like `Chase`, but turns so the car always points up
+    RotateWithCar,
+    /// The whole track at once
+    Overview,
+}
+
+impl DriverCamera {
+    pub fn label(self) -> &'static str {
+        match self {
+            DriverCamera::Chase => "Chase",
+            DriverCamera::RotateWithCar => "Rotate with car",
+            DriverCamera::Overview => "Track overview",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            DriverCamera::Chase => DriverCamera::RotateWithCar,
+            DriverCamera::RotateWithCar => DriverCamera::Overview,
+            DriverCamera::Overview => DriverCamera::Chase,
+        }
+    }
+}
+
+/// Draws the menus and HUD over the whole window in split-screen, where the player
+/// cameras only cover part of it
+#[derive(Component)]
+struct HudCamera;
+
+/// Render layer seen only by one local player's camera, on top of the shared world
+pub fn player_layer(seat: u8) -> RenderLayers {
+    RenderLayers::layer(1 + seat as usize)
+}
+
+/// Part of the window showing a local player, as fractions of it: one above the other
+/// for two players, corners for three and four
+pub fn player_viewport(seat: u8, players: u8) -> Rect {
+    let size = match players {
+        0 | 1 => return Rect::new(0.0, 0.0, 1.0, 1.0),
+        2 => Vec2::new(1.0, 0.5),
+        _ => Vec2::splat(0.5),
+    };
+    let corner = match (players, seat) {
+        (2, seat) => Vec2::new(0.0, seat as f32 * 0.5),
+        (_, seat) => Vec2::new((seat % 2) as f32, (seat / 2) as f32) * 0.5,
+    };
+    Rect::from_corners(corner, corner + size)
+}
+
+/// Local players on this screen during the race
+pub fn local_players(settings: &Settings, spectating: bool) -> u8 {
+    if spectating {
+        1
+    } else {
+        settings.local_players
+    }
+}
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_camera);
+        app.add_systems(OnEnter(AppState::Game), spawn_split_screen_cameras);
+        app.add_systems(OnExit(AppState::Game), reset_viewport);
+        app.add_systems(
+            Update,
+            (cycle_driver_camera, follow_local_car)
+                .chain()
+                .run_if(in_state(AppState::Game).and(not(resource_exists::<Spectating>))),
+        );
+        app.add_systems(
+            PostUpdate,
+            set_viewports
+                .run_if(in_state(AppState::Game))
+                .before(CameraUpdateSystem),
+        );
+        app.add_systems(
+            PostUpdate,
+            apply_camera_rig
+                .before(TransformSystem::TransformPropagate)
+                .before(CameraUpdateSystem),
+        );
+    }
+}
+
+fn player_camera(rig: CameraRig) -> impl Bundle {
+    (
+        Camera2d,
+        Camera {
+            order: rig.seat as isize,
+            ..default()
+        },
+        Projection::Orthographic(OrthographicProjection {
+            scale: rig.scale,
+            ..OrthographicProjection::default_2d()
+        }),
+        RenderLayers::layer(0).union(&player_layer(rig.seat)),
+        rig,
+    )
+}
+
+fn setup_camera(mut commands: Commands) {
+    commands.spawn(player_camera(CameraRig::default()));
+}
+
+/// One camera per extra local player, and one for the HUD on top
+fn spawn_split_screen_cameras(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    spectating: Option<Res<Spectating>>,
+) {
+    let players = local_players(&settings, spectating.is_some());
+    if players <= 1 {
+        return;
+    }
+    info!("Split-screen for {} players", players);
+    for seat in 1..players {
+        commands.spawn((
+            player_camera(CameraRig { seat, ..default() }),
+            StateScoped(AppState::Game),
+        ));
+    }
+    commands.spawn((
+        Camera2d,
+        Camera {
+            order: MAX_LOCAL_PLAYERS as isize,
+            clear_color: ClearColorConfig::None,
+            ..default()
+        },
+        // A layer nothing is on, so it only draws the UI
+        RenderLayers::layer(1 + MAX_LOCAL_PLAYERS as usize),
+        IsDefaultUiCamera,
+        HudCamera,
+        StateScoped(AppState::Game),
+    ));
+}
+
+/// Keep each player's camera on its part of the window as it is resized
+fn set_viewports(
+    settings: Res<Settings>,
+    spectating: Option<Res<Spectating>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(&mut Camera, &CameraRig)>,
+) {
+    let players = local_players(&settings, spectating.is_some());
+    let Ok(window) = windows.single() else {
+        return;
+    };
+    let size = window.physical_size().as_vec2();
+    for (mut camera, rig) in cameras.iter_mut() {
+        let viewport = (players > 1).then(|| {
+            let area = player_viewport(rig.seat, players);
+            let min = (area.min * size).as_uvec2();
+            Viewport {
+                physical_position: min,
+                physical_size: ((area.max * size).as_uvec2() - min).max(UVec2::ONE),
+                ..default()
+            }
+        });
+        let area = |viewport: &Option<Viewport>| {
+            viewport
+                .as_ref()
+                .map(|viewport| (viewport.physical_position, viewport.physical_size))
+        };
+        if area(&camera.viewport) != area(&viewport) {
+            camera.viewport = viewport;
+        }
+    }
+}
+
+fn reset_viewport(mut cameras: Query<&mut Camera, With<CameraRig>>) {
+    for mut camera in cameras.iter_mut() {
+        camera.viewport = None;
+    }
+}
+
+/// Center and scale that fit the whole track in a view of this many logical pixels
+pub fn overview(track: &TrackLayout, size: Vec2) -> (Vec2, f32) {
+    let bounds = track.bounds();
+    let scale = (bounds.width() / size.x).max(bounds.height() / size.y);
+    (bounds.center(), scale * OVERVIEW_MARGIN)
+}
+
+fn cycle_driver_camera(keys: Res<ButtonInput<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keys.just_pressed(KeyCode::KeyC) {
+        settings.camera = settings.camera.next();
+        info!("Camera: {:?}", settings.camera);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn follow_local_car(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    actions: Res<ActionState>,
+    local: Res<LocalPlayer>,
+    cars: Query<(&Transform, Option<&CarVelocity>, &LocalCar)>,
+    mut rigs: Query<(&mut CameraRig, &Camera)>,
+) {
+    let dt = time.delta_secs();
+    let follow = 1.0 - (-FOLLOW_SMOOTHING * dt).exp();
+    let turn = 1.0 - (-ROTATION_SMOOTHING * dt).exp();
+    let zoom = 1.0 - (-ZOOM_SMOOTHING * dt).exp();
+
+    for (mut rig, camera) in rigs.iter_mut() {
+        if settings.camera == DriverCamera::Overview {
+            let (Some(track), Some(size)) = (
+                TrackLayout::by_name(&local.track),
+                camera.logical_viewport_size(),
+            ) else {
+                continue;
+            };
+            let (center, scale) = overview(&track, size);
+            rig.position = rig.position.lerp(center, follow);
+            rig.rotation = turn_towards(rig.rotation, 0.0, turn);
+            rig.scale += (scale - rig.scale) * zoom;
+            continue;
+        }
+
+        // Waiting for the join to be accepted, or for the car to be replicated
+        let Some((transform, velocity, _)) = cars.iter().find(|(.., car)| car.seat == rig.seat)
+        else {
+            continue;
+        };
+        let velocity = velocity.map_or(Vec2::ZERO, |velocity| velocity.0);
+        let looking_back = actions.player(rig.seat).pressed(Action::LookBack);
+        let target = if looking_back {
+            let forward = (transform.rotation * Vec3::Y).truncate();
+            transform.translation.truncate() - forward * LOOK_BACK_DISTANCE
+        } else {
+            transform.translation.truncate() + velocity * LOOK_AHEAD
+        };
+        rig.position = rig.position.lerp(target, follow);
+
+        let mut heading = if settings.camera == DriverCamera::RotateWithCar {
+            transform.rotation.to_euler(EulerRot::ZYX).0
+        } else {
+            0.0
+        };
+        // Turned around, so what is behind the car is at the top of the screen
+        if looking_back && settings.camera == DriverCamera::RotateWithCar {
+            heading += std::f32::consts::PI;
+        }
+        rig.rotation = turn_towards(rig.rotation, heading, turn);
+
+        let speed = (velocity.length() / FULL_ZOOM_SPEED).min(1.0);
+        rig.scale += (CHASE_SCALE + SPEED_ZOOM * speed - rig.scale) * zoom;
+    }
+}
+
+/// Move `t` of the way from one angle to another, the short way round
+fn turn_towards(from: f32, to: f32, t: f32) -> f32 {
+    let difference =
+        (to - from + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+    from + difference * t
+}
+
+fn apply_camera_rig(
+    shake: Res<CameraShake>,
+    mut cameras: Query<(&CameraRig, &mut Transform, &mut Projection)>,
+) {
+    for (rig, mut transform, mut projection) in cameras.iter_mut() {
+        transform.translation = (rig.position + shake.offset()).extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(rig.rotation);
+        if let Projection::Orthographic(ortho) = projection.as_mut() {
+            if ortho.scale != rig.scale {
+                ortho.scale = rig.scale;
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use bevy::input::keyboard::KeyboardInput;
+use bevy::prelude::*;
+use lightyear::prelude::*;
+use nfrs_shared::{ChatLine, ChatSend, InputChannel, MAX_CHAT_LEN};
+use std::collections::VecDeque;
+use tracing::info;
+
+use crate::controls::{Action, ActionState, KeyboardCapture};
+use crate::pause::pause_menu_closed;
+use crate::AppState;
+
+// Lines shown at once
+const SHOWN_LINES: usize = 8;
+// Seconds a line stays on screen, and how long it then takes to fade out
+const LINE_LIFETIME: f32 = 10.0;
+const FADE_TIME: f32 = 2.0;
+const PANEL_WIDTH: f32 = 380.0;
+const PLAYER_COLOR: Color = Color::WHITE;
+const SYSTEM_COLOR: Color = Color::srgb(1.0, 0.85, 0.3);
+
+/// Recent chat lines, and what the player is typing
+#[derive(Resource, Default)]
+pub struct Chat {
+    /// Lines with the time they arrived, oldest first
+    lines: VecDeque<(ChatLine, f32)>,
+    /// Being typed while the chat is open
+    typing: Option<String>,
+}
+
+#[derive(Component)]
+struct ChatPanel;
+
+/// A line of the chat, with the time it arrived
+#[derive(Component)]
+struct ChatLineText(f32);
+
+pub struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Chat>();
+        app.add_systems(OnEnter(AppState::Game), setup_chat);
+        app.add_systems(
+            Update,
+            (receive_chat, type_chat.run_if(pause_menu_closed), draw_chat)
+                .chain()
+                .run_if(in_state(AppState::Game)),
+        );
+        app.add_systems(OnExit(AppState::Game), close_chat);
+    }
+}
+
+fn setup_chat(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            width: Val::Px(PANEL_WIDTH),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(2.0),
+            ..default()
+        },
+        ChatPanel,
+        StateScoped(AppState::Game),
+    ));
+}
+
+fn close_chat(mut chat: ResMut<Chat>, mut capture: ResMut<KeyboardCapture>) {
+    *chat = Chat::default();
+    capture.active = false;
+}
+
+fn receive_chat(
+    time: Res<Time>,
+    mut receivers: Query<&mut MessageReceiver<ChatLine>>,
+    mut chat: ResMut<Chat>,
+) {
+    for mut receiver in receivers.iter_mut() {
+        for line in receiver.receive() {
+            match &line.from {
+                Some(from) => info!("[chat] {}: {}", from, line.text),
+                None => info!("[chat] {}", line.text),
+            }
+            chat.lines.push_back((line, time.elapsed_secs()));
+            if chat.lines.len() > SHOWN_LINES {
+                chat.lines.pop_front();
+            }
+        }
+    }
+}
+
+/// The chat action (T) opens the chat; ENTER sends the line and ESC closes it without
+/// sending. Nothing drives while it is open.
+fn type_chat(
+    actions: Res<ActionState>,
+    mut events: EventReader<KeyboardInput>,
+    mut chat: ResMut<Chat>,
+    mut capture: ResMut<KeyboardCapture>,
+    mut senders: Query<&mut MessageSender<ChatSend>>,
+) {
+    let Some(mut typing) = chat.typing.clone() else {
+        // The T that opens the chat is not part of the line
+        events.clear();
+        if actions.player(0).just_pressed(Action::Chat) {
+            chat.typing = Some(String::new());
+            capture.active = true;
+        }
+        return;
+    };
+
+    // The keys are hidden from the rest of the game, so read the events themselves
+    let mut changed = false;
+    let mut send = None;
+    for event in events.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+        match event.key_code {
+            KeyCode::Escape => send = Some(false),
+            KeyCode::Enter | KeyCode::NumpadEnter => send = Some(true),
+            KeyCode::Backspace => changed |= typing.pop().is_some(),
+            _ => {
+                if let Some(text) = &event.text {
+                    if text.chars().all(|c| !c.is_control())
+                        && typing.chars().count() + text.chars().count() <= MAX_CHAT_LEN
+                    {
+                        typing.push_str(text);
+                        changed = true;
+                    }
+                }
+            }
+        }
+        if send.is_some() {
+            break;
+        }
+    }
+    let Some(send) = send else {
+        if changed {
+            chat.typing = Some(typing);
+        }
+        return;
+    };
+
+    let text = typing.trim().to_string();
+    if send && !text.is_empty() {
+        for mut sender in senders.iter_mut() {
+            sender.send::<InputChannel>(ChatSend { text: text.clone() });
+        }
+    }
+    chat.typing = None;
+    capture.active = false;
+}
+
+fn draw_chat(
+    mut commands: Commands,
+    time: Res<Time>,
+    chat: Res<Chat>,
+    asset_server: Res<AssetServer>,
+    panels: Query<Entity, With<ChatPanel>>,
+    mut lines: Query<(&ChatLineText, &mut TextColor)>,
+) {
+    let Ok(panel) = panels.single() else {
+        return;
+    };
+    let now = time.elapsed_secs();
+    if chat.is_changed() {
+        commands.entity(panel).despawn_related::<Children>();
+        let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+        let text_font = TextFont {
+            font,
+            font_size: 16.0,
+            ..default()
+        };
+        commands.entity(panel).with_children(|parent| {
+            for (line, received) in chat.lines.iter() {
+                let (text, color) = match &line.from {
+                    Some(from) => (format!("{}: {}", from, line.text), PLAYER_COLOR),
+                    None => (line.text.clone(), SYSTEM_COLOR),
+                };
+                parent.spawn((
+                    Text::new(text),
+                    text_font.clone(),
+                    TextColor(color.with_alpha(line_alpha(&chat, *received, now))),
+                    ChatLineText(*received),
+                ));
+            }
+            if let Some(typing) = &chat.typing {
+                parent.spawn((
+                    Node {
+                        padding: UiRect::all(Val::Px(4.0)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+                    children![(Text::new(format!("> {}_", typing)), text_font.clone())],
+                ));
+            }
+        });
+    }
+
+    for (line, mut color) in lines.iter_mut() {
+        let alpha = line_alpha(&chat, line.0, now);
+        if color.0.alpha() != alpha {
+            color.0.set_alpha(alpha);
+        }
+    }
+}
+
+/// Old lines fade away, but all of them show while typing
+fn line_alpha(chat: &Chat, received: f32, now: f32) -> f32 {
+    if chat.typing.is_some() {
+        return 1.0;
+    }
+    ((LINE_LIFETIME + FADE_TIME - (now - received)) / FADE_TIME).clamp(0.0, 1.0)
+}
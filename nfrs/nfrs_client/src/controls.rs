@@ -0,0 +1,666 @@
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use nfrs_shared::MAX_LOCAL_PLAYERS;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::camera::local_players;
+use crate::settings::Settings;
+use crate::spectator::Spectating;
+
+// Actions count as held past this value, for analog triggers and sticks
+const PRESS_THRESHOLD: f32 = 0.5;
+// How far a stick must be pushed to be picked as a new binding
+const CAPTURE_THRESHOLD: f32 = 0.7;
+const ROW_COLOR: Color = Color::srgb(0.1, 0.1, 0.2);
+const ROW_HOVER_COLOR: Color = Color::srgb(0.2, 0.2, 0.4);
+const STICK_AXES: [GamepadAxis; 4] = [
+    GamepadAxis::LeftStickX,
+    GamepadAxis::LeftStickY,
+    GamepadAxis::RightStickX,
+    GamepadAxis::RightStickY,
+];
+
+/// Something the player can do, whatever input it is bound to
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Action {
+    Throttle,
+    Brake,
+    SteerLeft,
+    SteerRight,
+    Handbrake,
+    Boost,
+    UseItem,
+    LookBack,
+    Pause,
+    Chat,
+}
+
+impl Action {
+    pub const ALL: [Action; 10] = [
+        Action::Throttle,
+        Action::Brake,
+        Action::SteerLeft,
+        Action::SteerRight,
+        Action::Handbrake,
+        Action::Boost,
+        Action::UseItem,
+        Action::LookBack,
+        Action::Pause,
+        Action::Chat,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Action::Throttle => "Throttle",
+            Action::Brake => "Brake / reverse",
+            Action::SteerLeft => "Steer left",
+            Action::SteerRight => "Steer right",
+            Action::Handbrake => "Handbrake",
+            Action::Boost => "Nitro",
+            Action::UseItem => "Use item",
+            Action::LookBack => "Look back",
+            Action::Pause => "Menu",
+            Action::Chat => "Chat",
+        }
+    }
+}
+
+/// An input that can trigger an action
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Binding {
+    Key(KeyCode),
+    /// Gamepad buttons; triggers give an analog value
+    Button(GamepadButton),
+    /// One direction of a gamepad stick
+    Axis {
+        axis: GamepadAxis,
+        positive: bool,
+    },
+}
+
+impl Binding {
+    fn label(&self) -> String {
+        match self {
+            Binding::Key(key) => {
+                let name = format!("{:?}", key);
+                name.trim_start_matches("Key")
+                    .trim_start_matches("Digit")
+                    .to_string()
+            }
+            Binding::Button(button) => format!("Pad {:?}", button),
+            Binding::Axis { axis, positive } => {
+                format!("Pad {:?}{}", axis, if *positive { "+" } else { "-" })
+            }
+        }
+    }
+
+    /// How far the input is held, from 0 to 1, on the keyboard or the given gamepads
+    fn value<'a>(
+        &self,
+        keys: &ButtonInput<KeyCode>,
+        gamepads: impl Iterator<Item = &'a Gamepad>,
+    ) -> f32 {
+        match self {
+            Binding::Key(key) => keys.pressed(*key) as u8 as f32,
+            Binding::Button(button) => gamepads
+                .filter_map(|gamepad| gamepad.get(*button))
+                .fold(0.0, f32::max),
+            Binding::Axis { axis, positive } => {
+                let sign = if *positive { 1.0 } else { -1.0 };
+                gamepads
+                    .filter_map(|gamepad| gamepad.get(*axis))
+                    .map(|value| value * sign)
+                    .fold(0.0, f32::max)
+            }
+        }
+    }
+}
+
+/// What each action is bound to; an action can have several bindings
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Bindings(BTreeMap<Action, Vec<Binding>>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Binding::{Axis, Button, Key};
+        let bindings = [
+            (
+                Action::Throttle,
+                vec![
+                    Key(KeyCode::KeyW),
+                    Key(KeyCode::ArrowUp),
+                    Button(GamepadButton::RightTrigger2),
+                ],
+            ),
+            (
+                Action::Brake,
+                vec![
+                    Key(KeyCode::KeyS),
+                    Key(KeyCode::ArrowDown),
+                    Button(GamepadButton::LeftTrigger2),
+                ],
+            ),
+            (
+                Action::SteerLeft,
+                vec![
+                    Key(KeyCode::KeyA),
+                    Key(KeyCode::ArrowLeft),
+                    Button(GamepadButton::DPadLeft),
+                    Axis {
+                        axis: GamepadAxis::LeftStickX,
+                        positive: false,
+                    },
+                ],
+            ),
+            (
+                Action::SteerRight,
+                vec![
+                    Key(KeyCode::KeyD),
+                    Key(KeyCode::ArrowRight),
+                    Button(GamepadButton::DPadRight),
+                    Axis {
+                        axis: GamepadAxis::LeftStickX,
+                        positive: true,
+                    },
+                ],
+            ),
+            (
+                Action::Handbrake,
+                vec![Key(KeyCode::Space), Button(GamepadButton::East)],
+            ),
+            (
+                Action::Boost,
+                vec![Key(KeyCode::ShiftLeft), Button(GamepadButton::South)],
+            ),
+            (
+                Action::UseItem,
+                vec![Key(KeyCode::KeyE), Button(GamepadButton::West)],
+            ),
+            (
+                Action::LookBack,
+                vec![Key(KeyCode::KeyQ), Button(GamepadButton::North)],
+            ),
+            (
+                Action::Pause,
+                vec![Key(KeyCode::Escape), Button(GamepadButton::Start)],
+            ),
+            (Action::Chat, vec![Key(KeyCode::KeyT)]),
+        ];
+        Self(bindings.into_iter().collect())
+    }
+}
+
+impl Bindings {
+    /// Defaults of a local player. The second one also has the arrow keys and the keys
+    /// around them, so two players can share a keyboard.
+    pub fn for_player(seat: u8) -> Self {
+        if seat == 0 {
+            return Self::default();
+        }
+        let mut bindings = Self::default();
+        for list in bindings.0.values_mut() {
+            list.retain(|binding| !matches!(binding, Binding::Key(_)));
+        }
+        if seat == 1 {
+            let keys = [
+                (Action::Throttle, KeyCode::ArrowUp),
+                (Action::Brake, KeyCode::ArrowDown),
+                (Action::SteerLeft, KeyCode::ArrowLeft),
+                (Action::SteerRight, KeyCode::ArrowRight),
+                (Action::Handbrake, KeyCode::ControlRight),
+                (Action::Boost, KeyCode::ShiftRight),
+                (Action::UseItem, KeyCode::Enter),
+                (Action::LookBack, KeyCode::Slash),
+            ];
+            for (action, key) in keys {
+                bindings.add(action, Binding::Key(key));
+            }
+        }
+        bindings
+    }
+
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.0.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// A binding triggers one action only, so it is taken from any other action
+    fn add(&mut self, action: Action, binding: Binding) {
+        for (other, bindings) in self.0.iter_mut() {
+            if *other != action {
+                bindings.retain(|other| *other != binding);
+            }
+        }
+        let bindings = self.0.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Leaves the action unbound, rather than missing and given its defaults on load
+    fn clear(&mut self, action: Action) {
+        self.0.insert(action, Vec::new());
+    }
+
+    /// Give actions added since the bindings were saved their defaults, unless another
+    /// action already uses them
+    pub fn add_missing(&mut self, defaults: Bindings) {
+        for (action, bindings) in defaults.0 {
+            if self.0.contains_key(&action) {
+                continue;
+            }
+            let free: Vec<Binding> = bindings
+                .into_iter()
+                .filter(|binding| !self.0.values().flatten().any(|used| used == binding))
+                .collect();
+            self.0.insert(action, free);
+        }
+    }
+}
+
+/// How far each action is held this frame by one local player, from 0 to 1
+#[derive(Default)]
+pub struct PlayerActions {
+    values: HashMap<Action, f32>,
+    previous: HashMap<Action, f32>,
+}
+
+impl PlayerActions {
+    pub fn value(&self, action: Action) -> f32 {
+        self.values.get(&action).copied().unwrap_or_default()
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.value(action) > PRESS_THRESHOLD
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pressed(action)
+            && self.previous.get(&action).copied().unwrap_or_default() <= PRESS_THRESHOLD
+    }
+}
+
+/// Actions of every local player, by seat; players not in the race hold nothing
+#[derive(Resource, Default)]
+pub struct ActionState {
+    players: [PlayerActions; MAX_LOCAL_PLAYERS as usize],
+}
+
+impl ActionState {
+    pub fn player(&self, seat: u8) -> &PlayerActions {
+        &self.players[seat as usize]
+    }
+
+    /// Shared actions like the menu can be used by anyone
+    pub fn any_just_pressed(&self, action: Action) -> bool {
+        self.players
+            .iter()
+            .any(|player| player.just_pressed(action))
+    }
+}
+
+/// Actions held by on-screen controls, from 0 to 1, added to the bound inputs
+#[derive(Resource, Default)]
+pub struct VirtualActions(pub HashMap<Action, f32>);
+
+/// The keyboard is taken by a text field, like the chat. The rest of the game sees no
+/// keys while it is active, nor keys held from typing until they are released.
+#[derive(Resource, Default)]
+pub struct KeyboardCapture {
+    pub active: bool,
+    held: HashSet<KeyCode>,
+}
+
+/// The screen listing every action's bindings
+#[derive(Resource, Default)]
+pub struct ControlsScreen {
+    open: bool,
+    /// Local player whose bindings are shown
+    seat: u8,
+    /// Waiting for the player to press the new binding of this action
+    capturing: Option<Action>,
+}
+
+impl ControlsScreen {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+}
+
+/// Run condition for menus that also use ESC
+pub fn controls_screen_closed(screen: Res<ControlsScreen>) -> bool {
+    !screen.open
+}
+
+#[derive(Component)]
+struct ControlsRoot;
+
+#[derive(Component, Clone, Copy)]
+enum ControlsButton {
+    Player,
+    Rebind(Action),
+    Reset,
+    Back,
+}
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionState>();
+        app.init_resource::<VirtualActions>();
+        app.init_resource::<ControlsScreen>();
+        app.init_resource::<KeyboardCapture>();
+        app.add_systems(
+            PreUpdate,
+            (capture_keyboard, read_actions).chain().after(InputSystem),
+        );
+        // After the menus have seen this frame's ESC, so closing this screen does not
+        // also close the menu under it
+        app.add_systems(
+            PostUpdate,
+            (
+                press_controls_buttons,
+                capture_binding,
+                draw_controls_screen,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Gamepads a local player reads
+#[derive(Clone, Copy)]
+enum PlayerGamepads {
+    All,
+    One(usize),
+    None,
+}
+
+impl PlayerGamepads {
+    /// Gamepads go to players 2, 3 and 4 first, in the order they were connected, since
+    /// player 1 also has the keyboard. A player alone uses all of them.
+    fn of(seat: u8, players: u8, connected: usize) -> Self {
+        if players <= 1 {
+            return Self::All;
+        }
+        let index = if seat == 0 {
+            players as usize - 1
+        } else {
+            seat as usize - 1
+        };
+        if index < connected {
+            Self::One(index)
+        } else {
+            Self::None
+        }
+    }
+
+    fn includes(self, index: usize) -> bool {
+        match self {
+            Self::All => true,
+            Self::One(only) => only == index,
+            Self::None => false,
+        }
+    }
+}
+
+/// Hide captured keys from everything that reads the keyboard after this
+fn capture_keyboard(
+    mut events: EventReader<KeyboardInput>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut capture: ResMut<KeyboardCapture>,
+) {
+    for event in events.read() {
+        if !event.state.is_pressed() {
+            capture.held.remove(&event.key_code);
+        }
+    }
+    if capture.active {
+        let pressed: Vec<KeyCode> = keys.get_pressed().copied().collect();
+        capture.held.extend(pressed);
+        keys.reset_all();
+    }
+    // Key repeats press held keys again, so they are hidden every frame
+    for key in capture.held.iter() {
+        keys.reset(*key);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn read_actions(
+    settings: Res<Settings>,
+    capture: Res<KeyboardCapture>,
+    spectating: Option<Res<Spectating>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<(Entity, &Gamepad)>,
+    on_screen: Res<VirtualActions>,
+    mut actions: ResMut<ActionState>,
+) {
+    let players = local_players(&settings, spectating.is_some());
+    // In connection order, so players keep their gamepad
+    let mut gamepads: Vec<_> = gamepads.iter().collect();
+    gamepads.sort_by_key(|(entity, _)| *entity);
+    // Keys another player uses only drive that player, e.g. the arrows when sharing a keyboard
+    let guest_keys: HashSet<KeyCode> = (1..players)
+        .flat_map(|seat| settings.player_bindings(seat).0.values().flatten())
+        .filter_map(|binding| match binding {
+            Binding::Key(key) => Some(*key),
+            _ => None,
+        })
+        .collect();
+
+    for seat in 0..MAX_LOCAL_PLAYERS {
+        let bindings = settings.player_bindings(seat);
+        let own = PlayerGamepads::of(seat, players, gamepads.len());
+        let values = Action::ALL
+            .iter()
+            .map(|&action| {
+                // Nobody drives while typing, whether with keys, gamepads or the screen
+                if seat >= players || capture.active {
+                    return (action, 0.0);
+                }
+                let own_gamepads = gamepads
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| own.includes(*index))
+                    .map(|(_, (_, gamepad))| *gamepad);
+                let mut value = bindings
+                    .get(action)
+                    .iter()
+                    .filter(|binding| {
+                        !(seat == 0
+                            && matches!(binding, Binding::Key(key) if guest_keys.contains(key)))
+                    })
+                    .map(|binding| binding.value(&keys, own_gamepads.clone()))
+                    .fold(0.0, f32::max);
+                // On-screen controls belong to the first player
+                if seat == 0 {
+                    value = value.max(on_screen.0.get(&action).copied().unwrap_or_default());
+                }
+                (action, value.min(1.0))
+            })
+            .collect();
+        let player = &mut actions.players[seat as usize];
+        player.previous = std::mem::replace(&mut player.values, values);
+    }
+}
+
+fn press_controls_buttons(
+    mut buttons: Query<(&Interaction, &ControlsButton, &mut BackgroundColor), Changed<Interaction>>,
+    mut screen: ResMut<ControlsScreen>,
+    mut settings: ResMut<Settings>,
+) {
+    for (interaction, button, mut color) in buttons.iter_mut() {
+        match interaction {
+            Interaction::Pressed => match button {
+                ControlsButton::Player => {
+                    screen.seat = (screen.seat + 1) % MAX_LOCAL_PLAYERS;
+                    screen.capturing = None;
+                }
+                ControlsButton::Rebind(action) => screen.capturing = Some(*action),
+                ControlsButton::Reset => {
+                    *settings.player_bindings_mut(screen.seat) = Bindings::for_player(screen.seat)
+                }
+                ControlsButton::Back => *screen = ControlsScreen::default(),
+            },
+            Interaction::Hovered => color.0 = ROW_HOVER_COLOR,
+            Interaction::None => color.0 = ROW_COLOR,
+        }
+    }
+}
+
+/// Add the next key, gamepad button or stick direction to the action being rebound.
+/// BACKSPACE removes all its bindings, ESC cancels; ESC also closes the screen.
+fn capture_binding(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut screen: ResMut<ControlsScreen>,
+    mut settings: ResMut<Settings>,
+) {
+    if !screen.open {
+        return;
+    }
+    let Some(action) = screen.capturing else {
+        if keys.just_pressed(KeyCode::Escape) {
+            *screen = ControlsScreen::default();
+        }
+        return;
+    };
+
+    let binding = match keys.get_just_pressed().next() {
+        Some(KeyCode::Escape) => {
+            screen.capturing = None;
+            return;
+        }
+        Some(KeyCode::Backspace) => {
+            settings.player_bindings_mut(screen.seat).clear(action);
+            screen.capturing = None;
+            return;
+        }
+        Some(key) => Some(Binding::Key(*key)),
+        None => gamepads.iter().find_map(|gamepad| {
+            let button = gamepad
+                .get_just_pressed()
+                .next()
+                .copied()
+                .map(Binding::Button);
+            button.or_else(|| {
+                STICK_AXES.iter().find_map(|&axis| {
+                    let value = gamepad.get(axis)?;
+                    (value.abs() > CAPTURE_THRESHOLD).then_some(Binding::Axis {
+                        axis,
+                        positive: value > 0.0,
+                    })
+                })
+            })
+        }),
+    };
+    if let Some(binding) = binding {
+        settings
+            .player_bindings_mut(screen.seat)
+            .add(action, binding);
+        screen.capturing = None;
+    }
+}
+
+fn draw_controls_screen(
+    mut commands: Commands,
+    screen: Res<ControlsScreen>,
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    roots: Query<Entity, With<ControlsRoot>>,
+) {
+    if !screen.is_changed() && !settings.is_changed() {
+        return;
+    }
+    for root in roots.iter() {
+        commands.entity(root).despawn();
+    }
+    if !screen.open {
+        return;
+    }
+
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_font = |size| TextFont {
+        font: font.clone(),
+        font_size: size,
+        ..default()
+    };
+    let row = |parent: &mut ChildSpawnerCommands, button: ControlsButton, label: String| {
+        parent
+            .spawn((
+                Button,
+                Node {
+                    width: Val::Px(560.0),
+                    padding: UiRect::all(Val::Px(8.0)),
+                    ..default()
+                },
+                BackgroundColor(ROW_COLOR),
+                button,
+            ))
+            .with_children(|parent| {
+                parent.spawn((Text::new(label), text_font(20.0), TextColor(Color::WHITE)));
+            });
+    };
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgb(0.05, 0.05, 0.1)),
+            // Above the settings screens it is opened from
+            GlobalZIndex(20),
+            ControlsRoot,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Controls"),
+                text_font(48.0),
+                TextColor(Color::srgb(0.0, 0.8, 1.0)),
+            ));
+            row(
+                parent,
+                ControlsButton::Player,
+                format!("Player {} (click for the next player)", screen.seat + 1),
+            );
+            let player = settings.player_bindings(screen.seat);
+            for action in Action::ALL {
+                let bindings = player.get(action);
+                let label = if screen.capturing == Some(action) {
+                    format!("{}: press a key or gamepad input...", action.label())
+                } else if bindings.is_empty() {
+                    format!("{}: -", action.label())
+                } else {
+                    let names: Vec<_> = bindings.iter().map(Binding::label).collect();
+                    format!("{}: {}", action.label(), names.join(", "))
+                };
+                row(parent, ControlsButton::Rebind(action), label);
+            }
+            row(parent, ControlsButton::Reset, "Reset to defaults".to_string());
+            row(parent, ControlsButton::Back, "Back".to_string());
+            parent.spawn((
+                Text::new(
+                    "Click an action to add a binding. While waiting, BACKSPACE clears it and ESC cancels",
+                ),
+                text_font(16.0),
+                TextColor(Color::srgb(0.5, 0.5, 0.5)),
+            ));
+            parent.spawn((
+                Text::new(
+                    "In split-screen, gamepads go to players 2 to 4 first, then to player 1",
+                ),
+                text_font(16.0),
+                TextColor(Color::srgb(0.5, 0.5, 0.5)),
+            ));
+        });
+}
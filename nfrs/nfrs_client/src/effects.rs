@@ -0,0 +1,365 @@
+use bevy::prelude::*;
+use nfrs_shared::{Car, CarVelocity, ItemEffects, Nitro, SurfaceKind, SurfaceRegion};
+use rand::Rng;
+use std::collections::VecDeque;
+
+use crate::track::surface_color;
+use crate::AppState;
+
+// Sideways speed (m/s) where tires start to leave marks
+const SKID_MARK_SLIP: f32 = 3.0;
+// ...and where the marks are darkest
+const SKID_MARK_FULL_SLIP: f32 = 10.0;
+// Rear wheels, relative to the car's center
+const REAR_WHEELS: [Vec2; 2] = [Vec2::new(-0.75, -1.3), Vec2::new(0.75, -1.3)];
+const EXHAUST: Vec2 = Vec2::new(0.0, -2.1);
+// Shortest skid mark segment; shorter moves extend the current one next frame
+const MARK_STEP: f32 = 0.3;
+const MARK_WIDTH: f32 = 0.35;
+const MARK_LIFETIME: f32 = 8.0;
+// Oldest marks go first past this many
+const MAX_SKID_MARKS: usize = 1500;
+// Marks lie on the track, above the surfaces and under oil slicks and cars
+const MARK_Z: f32 = -5.0;
+const PARTICLE_Z: f32 = -0.2;
+// Speed (m/s) above which cars kick up dust off the track, and puffs per meter driven
+const DUST_SPEED: f32 = 3.0;
+const DUST_PER_METER: f32 = 1.5;
+// Exhaust smoke when gaining this much speed per second
+const SMOKE_ACCELERATION: f32 = 2.0;
+const SMOKE_PER_SECOND: f32 = 12.0;
+const TRAIL_PER_SECOND: f32 = 40.0;
+
+/// Sideways speed of a car, in m/s: how much it is sliding rather than rolling
+pub fn sideways_speed(transform: &Transform, velocity: Vec2) -> f32 {
+    let sideways = (transform.rotation * Vec3::X).truncate();
+    velocity.dot(sideways).abs()
+}
+
+/// What a car's effects remember from the last frame
+#[derive(Component, Default)]
+struct Tires {
+    /// Where each rear wheel's skid mark last ended, while it is sliding
+    marks: Option<[Vec2; 2]>,
+    /// Speed and time (elapsed seconds) of the last velocity update from the server, and
+    /// the acceleration measured between the last two. Updates arrive less often than
+    /// frames, so the acceleration is held in between.
+    speed: f32,
+    sampled_at: Option<f32>,
+    acceleration: f32,
+    /// Particles owed but not spawned yet, as spawning is whole puffs only
+    dust: f32,
+    smoke: f32,
+    trail: f32,
+}
+
+#[derive(Component)]
+struct SkidMark {
+    age: f32,
+    alpha: f32,
+}
+
+/// Every skid mark, oldest first
+#[derive(Resource, Default)]
+struct SkidMarks(VecDeque<Entity>);
+
+/// A puff of dust, smoke or boost flame that drifts, grows and fades
+#[derive(Component)]
+struct Particle {
+    velocity: Vec2,
+    age: f32,
+    lifetime: f32,
+    /// Size gained per second
+    growth: f32,
+    alpha: f32,
+}
+
+/// Skid marks, dust off the track, exhaust smoke and boost trails. Everything is worked out
+/// from each car's replicated position, rotation and velocity, so remote cars get the same
+/// effects as the local one.
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SkidMarks>();
+        app.add_systems(
+            Update,
+            (
+                add_tires,
+                leave_skid_marks,
+                emit_particles,
+                fade_skid_marks,
+                update_particles,
+            )
+                .chain()
+                .run_if(in_state(AppState::Game)),
+        );
+        app.add_systems(OnExit(AppState::Game), forget_skid_marks);
+    }
+}
+
+fn add_tires(mut commands: Commands, cars: Query<Entity, (With<Car>, Added<CarVelocity>)>) {
+    for car in cars.iter() {
+        commands.entity(car).insert(Tires::default());
+    }
+}
+
+/// The surface under a point: the highest priority region covering it, or grass
+fn surface_at(position: Vec2, regions: &Query<(&SurfaceRegion, &Transform)>) -> SurfaceKind {
+    regions
+        .iter()
+        .filter(|(region, transform)| {
+            let offset = (position - transform.translation.truncate()).abs();
+            offset.x <= region.half_extents.x && offset.y <= region.half_extents.y
+        })
+        .map(|(region, _)| region.kind)
+        .max_by_key(|kind| kind.priority())
+        .unwrap_or(SurfaceKind::Grass)
+}
+
+/// Draw a dark strip behind each rear wheel of sliding cars on hard ground
+fn leave_skid_marks(
+    mut commands: Commands,
+    mut marks: ResMut<SkidMarks>,
+    mut cars: Query<(&Transform, &CarVelocity, &mut Tires)>,
+    regions: Query<(&SurfaceRegion, &Transform)>,
+) {
+    for (transform, velocity, mut tires) in cars.iter_mut() {
+        let position = transform.translation.truncate();
+        let wheels = REAR_WHEELS
+            .map(|wheel| position + transform.rotation.mul_vec3(wheel.extend(0.0)).truncate());
+        let slip = sideways_speed(transform, velocity.0);
+        // Loose ground throws up dust instead, and ice leaves nothing
+        let hard = matches!(
+            surface_at(position, &regions),
+            SurfaceKind::Asphalt | SurfaceKind::BoostPad
+        );
+        if slip < SKID_MARK_SLIP || !hard {
+            tires.marks = None;
+            continue;
+        }
+        let Some(last) = tires.marks else {
+            tires.marks = Some(wheels);
+            continue;
+        };
+        if last[0].distance(wheels[0]) < MARK_STEP {
+            continue;
+        }
+
+        let darkness =
+            ((slip - SKID_MARK_SLIP) / (SKID_MARK_FULL_SLIP - SKID_MARK_SLIP)).clamp(0.0, 1.0);
+        let alpha = 0.25 + 0.35 * darkness;
+        for (from, to) in last.into_iter().zip(wheels) {
+            let step = to - from;
+            let mark = commands
+                .spawn((
+                    Sprite {
+                        color: Color::srgba(0.05, 0.05, 0.05, alpha),
+                        custom_size: Some(Vec2::new(MARK_WIDTH, step.length() + MARK_WIDTH)),
+                        ..default()
+                    },
+                    Transform::from_translation(((from + to) / 2.0).extend(MARK_Z)).with_rotation(
+                        Quat::from_rotation_z(step.to_angle() - std::f32::consts::FRAC_PI_2),
+                    ),
+                    SkidMark { age: 0.0, alpha },
+                    StateScoped(AppState::Game),
+                ))
+                .id();
+            marks.0.push_back(mark);
+        }
+        tires.marks = Some(wheels);
+
+        while marks.0.len() > MAX_SKID_MARKS {
+            if let Some(oldest) = marks.0.pop_front() {
+                commands.entity(oldest).try_despawn();
+            }
+        }
+    }
+}
+
+fn fade_skid_marks(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut marks: ResMut<SkidMarks>,
+    mut sprites: Query<(Entity, &mut SkidMark, &mut Sprite)>,
+) {
+    let dt = time.delta_secs();
+    for (entity, mut mark, mut sprite) in sprites.iter_mut() {
+        mark.age += dt;
+        if mark.age >= MARK_LIFETIME {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        // Full strength for most of their life, then fading out
+        let fade = ((MARK_LIFETIME - mark.age) / (MARK_LIFETIME * 0.3)).min(1.0);
+        sprite.color.set_alpha(mark.alpha * fade);
+    }
+    marks.0.retain(|mark| sprites.contains(*mark));
+}
+
+fn forget_skid_marks(mut marks: ResMut<SkidMarks>) {
+    marks.0.clear();
+}
+
+/// Dust off the track, smoke while speeding up and a trail of flame while boosting
+#[allow(clippy::type_complexity)]
+fn emit_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut cars: Query<(
+        &Transform,
+        Ref<CarVelocity>,
+        &mut Tires,
+        Option<&Nitro>,
+        Option<&ItemEffects>,
+    )>,
+    regions: Query<(&SurfaceRegion, &Transform)>,
+) {
+    let dt = time.delta_secs();
+    if dt <= 0.0 {
+        return;
+    }
+    let mut rng = rand::thread_rng();
+    for (transform, velocity, mut tires, nitro, effects) in cars.iter_mut() {
+        let position = transform.translation.truncate();
+        let speed = velocity.0.length();
+        if velocity.is_changed() {
+            let now = time.elapsed_secs();
+            if let Some(since) = tires.sampled_at.map(|sampled_at| now - sampled_at) {
+                if since > 0.0 {
+                    tires.acceleration = (speed - tires.speed) / since;
+                }
+            }
+            tires.speed = speed;
+            tires.sampled_at = Some(now);
+        }
+        let exhaust = position + transform.rotation.mul_vec3(EXHAUST.extend(0.0)).truncate();
+        let surface = surface_at(position, &regions);
+
+        if matches!(surface, SurfaceKind::Grass | SurfaceKind::Gravel) && speed > DUST_SPEED {
+            tires.dust += speed * dt * DUST_PER_METER;
+            let color = surface_color(surface).lighter(0.15);
+            while tires.dust >= 1.0 {
+                tires.dust -= 1.0;
+                let wheel = REAR_WHEELS[rng.gen_range(0..REAR_WHEELS.len())];
+                let wheel = position + transform.rotation.mul_vec3(wheel.extend(0.0)).truncate();
+                spawn_particle(
+                    &mut commands,
+                    wheel + jitter(&mut rng, 0.3),
+                    color.with_alpha(0.5),
+                    0.6,
+                    Particle {
+                        velocity: velocity.0 * 0.2 + jitter(&mut rng, 1.5),
+                        age: 0.0,
+                        lifetime: 1.0,
+                        growth: 2.0,
+                        alpha: 0.5,
+                    },
+                );
+            }
+        } else {
+            tires.dust = 0.0;
+        }
+
+        let boosting = nitro.is_some_and(|nitro| nitro.boosting)
+            || effects.is_some_and(|effects| effects.is_boosting())
+            || surface == SurfaceKind::BoostPad;
+        if boosting {
+            tires.trail += TRAIL_PER_SECOND * dt;
+            while tires.trail >= 1.0 {
+                tires.trail -= 1.0;
+                let color = if rng.gen_bool(0.5) {
+                    Color::srgb(1.0, 0.55, 0.1)
+                } else {
+                    Color::srgb(0.3, 0.7, 1.0)
+                };
+                spawn_particle(
+                    &mut commands,
+                    exhaust + jitter(&mut rng, 0.2),
+                    color,
+                    0.5,
+                    Particle {
+                        velocity: jitter(&mut rng, 0.5),
+                        age: 0.0,
+                        lifetime: 0.35,
+                        growth: -1.0,
+                        alpha: 0.9,
+                    },
+                );
+            }
+        } else {
+            tires.trail = 0.0;
+        }
+
+        if tires.acceleration > SMOKE_ACCELERATION && !boosting {
+            tires.smoke += SMOKE_PER_SECOND * dt;
+            while tires.smoke >= 1.0 {
+                tires.smoke -= 1.0;
+                spawn_particle(
+                    &mut commands,
+                    exhaust + jitter(&mut rng, 0.1),
+                    Color::srgba(0.6, 0.6, 0.6, 0.35),
+                    0.3,
+                    Particle {
+                        velocity: velocity.0 * 0.1 + jitter(&mut rng, 0.6),
+                        age: 0.0,
+                        lifetime: 0.6,
+                        growth: 1.2,
+                        alpha: 0.35,
+                    },
+                );
+            }
+        } else {
+            tires.smoke = 0.0;
+        }
+    }
+}
+
+/// A random offset of up to `amount` on each axis
+fn jitter(rng: &mut impl Rng, amount: f32) -> Vec2 {
+    Vec2::new(
+        rng.gen_range(-amount..amount),
+        rng.gen_range(-amount..amount),
+    )
+}
+
+fn spawn_particle(
+    commands: &mut Commands,
+    position: Vec2,
+    color: Color,
+    size: f32,
+    particle: Particle,
+) {
+    commands.spawn((
+        Sprite {
+            color,
+            custom_size: Some(Vec2::splat(size)),
+            ..default()
+        },
+        Transform::from_translation(position.extend(PARTICLE_Z)),
+        particle,
+        StateScoped(AppState::Game),
+    ));
+}
+
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    let dt = time.delta_secs();
+    for (entity, mut particle, mut transform, mut sprite) in particles.iter_mut() {
+        particle.age += dt;
+        if particle.age >= particle.lifetime {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        // Puffs slow down in the air
+        particle.velocity *= 1.0 - (3.0 * dt).min(1.0);
+        transform.translation += (particle.velocity * dt).extend(0.0);
+        let size = sprite.custom_size.unwrap_or(Vec2::ONE) + Vec2::splat(particle.growth * dt);
+        sprite.custom_size = Some(size.max(Vec2::splat(0.05)));
+        let left = 1.0 - particle.age / particle.lifetime;
+        sprite.color.set_alpha(particle.alpha * left);
+    }
+}
@@ -0,0 +1,101 @@
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::prelude::*;
+use nfrs_server::{BotConfig, BotDifficulty, ServerPlugin, ServerTransports};
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::GameMode;
+
+// Opponents in practice mode when `--bots` is not given
+const PRACTICE_BOTS: usize = 3;
+// Seconds counted down on the grid before a practice race
+const PRACTICE_COUNTDOWN_SECS: u8 = 3;
+const STATS_FILE: &str = "stats.ron";
+
+/// A server simulation running inside this client; it stops when this is dropped
+#[derive(Resource)]
+pub struct EmbeddedServer {
+    /// Where the local player connects to
+    pub addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for EmbeddedServer {
+    fn drop(&mut self) {
+        info!("Stopping the embedded server");
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Run the dedicated server's game logic on a background thread.
+/// Practice mode only listens on loopback; host mode listens on the LAN on the
+/// regular server port so other clients can join with `--ip`.
+pub fn start(mode: GameMode, bots: Option<usize>, difficulty: BotDifficulty) -> EmbeddedServer {
+    let (bind, bots) = match mode {
+        GameMode::Practice => (
+            SocketAddr::new(Ipv4Addr::LOCALHOST.into(), free_udp_port()),
+            bots.unwrap_or(PRACTICE_BOTS),
+        ),
+        GameMode::Host => (
+            SocketAddr::new(
+                Ipv4Addr::UNSPECIFIED.into(),
+                nfrs_server::transport::UDP_PORT,
+            ),
+            bots.unwrap_or(0),
+        ),
+        GameMode::Online => unreachable!("online games use a remote server"),
+    };
+
+    info!("Starting {:?} server on {} with {} bots", mode, bind, bots);
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = stop.clone();
+    std::thread::Builder::new()
+        .name("nfrs-server".into())
+        .spawn(move || {
+            let server = ServerPlugin {
+                bots: BotConfig {
+                    count: bots,
+                    difficulty,
+                },
+                // Keep local results and best laps between sessions
+                stats_file: Some(STATS_FILE.into()),
+                // A practice race starts as the player joins it; host games are joined
+                // by friends along the way
+                start_countdown_secs: if mode == GameMode::Practice {
+                    PRACTICE_COUNTDOWN_SECS
+                } else {
+                    0
+                },
+                ..default()
+            };
+            let mut app = App::new();
+            app.add_plugins((
+                MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(server.tick_duration)),
+                server,
+            ));
+            app.insert_resource(ServerTransports::new().udp(bind));
+            // Early in the frame, so the server can save its stats before exiting
+            app.add_systems(First, move |mut exit: EventWriter<AppExit>| {
+                if stopped.load(Ordering::Relaxed) {
+                    exit.write(AppExit::Success);
+                }
+            });
+            app.run();
+        })
+        .expect("failed to start the embedded server thread");
+
+    EmbeddedServer {
+        addr: SocketAddr::new(Ipv4Addr::LOCALHOST.into(), bind.port()),
+        stop,
+    }
+}
+
+/// Ask the OS for an unused port, so practice never clashes with a real server
+fn free_udp_port() -> u16 {
+    UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .and_then(|socket| socket.local_addr())
+        .map(|addr| addr.port())
+        .unwrap_or(nfrs_server::transport::UDP_PORT + 10)
+}
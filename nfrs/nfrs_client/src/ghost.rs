@@ -0,0 +1,308 @@
+use bevy::prelude::*;
+use lightyear::prelude::*;
+use nfrs_shared::{
+    Ghost, GhostDownload, GhostRequest, GhostSample, GhostUpload, InputChannel, Player,
+    RaceProgress, MAX_GHOST_SAMPLES,
+};
+use tracing::{info, warn};
+
+use crate::{AppState, LocalCar, LocalPlayer};
+
+// Seconds between recorded positions of the local car
+const SAMPLE_INTERVAL: f32 = 0.05;
+const GHOST_ALPHA: f32 = 0.35;
+// Never used by a real connection or bot
+const GHOST_CLIENT_ID: u64 = u64::MAX;
+#[cfg(not(target_arch = "wasm32"))]
+const GHOST_DIR: &str = "ghosts";
+
+/// Path of the local car during the current lap
+#[derive(Resource, Default)]
+struct LapRecorder {
+    lap: u32,
+    started: f32,
+    last_sample: f32,
+    samples: Vec<GhostSample>,
+}
+
+/// Ghosts available to race against
+#[derive(Resource, Default)]
+struct Ghosts {
+    /// Track the personal best was loaded for
+    track: Option<String>,
+    personal_best: Option<Ghost>,
+    /// Fastest ghost on the server, and who drove it
+    record: Option<(String, Ghost)>,
+    /// Race the server record instead of the personal best
+    race_record: bool,
+}
+
+/// The local car crossed the start line
+#[derive(Event)]
+struct LapStarted;
+
+/// A translucent, client-only car following a ghost
+#[derive(Component)]
+struct GhostCar {
+    ghost: Ghost,
+    started: f32,
+}
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LapRecorder>();
+        app.init_resource::<Ghosts>();
+        app.add_event::<LapStarted>();
+        app.add_systems(
+            Update,
+            (
+                load_personal_best,
+                record_laps,
+                choose_ghost,
+                receive_ghost_downloads,
+                start_ghost,
+                move_ghosts,
+                fade_ghosts,
+            )
+                .chain()
+                .run_if(in_state(AppState::Game)),
+        );
+        app.add_systems(OnExit(AppState::Game), reset_recorder);
+    }
+}
+
+/// The next race starts from lap 0 again
+fn reset_recorder(mut recorder: ResMut<LapRecorder>) {
+    *recorder = LapRecorder::default();
+}
+
+fn load_personal_best(local: Res<LocalPlayer>, mut ghosts: ResMut<Ghosts>) {
+    if local.track.is_empty() || ghosts.track.as_deref() == Some(local.track.as_str()) {
+        return;
+    }
+    ghosts.track = Some(local.track.clone());
+    ghosts.personal_best = load_ghost(&local.track);
+    if let Some(ghost) = &ghosts.personal_best {
+        info!(
+            "Loaded personal best ghost for '{}': {:.3} s",
+            ghost.track, ghost.lap_time
+        );
+    }
+}
+
+/// Sample the local car every lap and keep the lap as a ghost when it is a personal best.
+/// In split-screen, only the first player's laps are kept.
+fn record_laps(
+    time: Res<Time>,
+    local: Res<LocalPlayer>,
+    cars: Query<(&Transform, &RaceProgress, &LocalCar)>,
+    mut recorder: ResMut<LapRecorder>,
+    mut ghosts: ResMut<Ghosts>,
+    mut uploads: Query<&mut MessageSender<GhostUpload>>,
+    mut lap_started: EventWriter<LapStarted>,
+) {
+    let Some((transform, progress, _)) = cars.iter().find(|(.., car)| car.seat == 0) else {
+        return;
+    };
+    let now = time.elapsed_secs();
+
+    if progress.lap != recorder.lap {
+        let finished = recorder.lap >= 1 && progress.lap == recorder.lap + 1;
+        // The server only updates the best lap when the last one beat it
+        if finished && progress.last_lap.is_some() && progress.last_lap == progress.best_lap {
+            let ghost = Ghost {
+                track: local.track.clone(),
+                lap_time: progress.last_lap.unwrap_or_default(),
+                samples: std::mem::take(&mut recorder.samples),
+            };
+            let faster = ghosts
+                .personal_best
+                .as_ref()
+                .is_none_or(|best| ghost.lap_time < best.lap_time);
+            if faster {
+                info!("New personal best: {:.3} s", ghost.lap_time);
+                save_ghost(&ghost);
+                ghosts.personal_best = Some(ghost.clone());
+            }
+            for mut sender in uploads.iter_mut() {
+                sender.send::<InputChannel>(GhostUpload {
+                    ghost: ghost.clone(),
+                });
+            }
+        }
+        recorder.lap = progress.lap;
+        recorder.started = now;
+        recorder.last_sample = f32::NEG_INFINITY;
+        recorder.samples.clear();
+        if progress.lap >= 1 {
+            lap_started.write(LapStarted);
+        }
+    }
+
+    if recorder.lap >= 1
+        && now - recorder.last_sample >= SAMPLE_INTERVAL
+        && recorder.samples.len() < MAX_GHOST_SAMPLES
+    {
+        let started = recorder.started;
+        recorder.last_sample = now;
+        recorder.samples.push(GhostSample {
+            time: now - started,
+            position: transform.translation.truncate(),
+            rotation: transform.rotation.to_euler(EulerRot::ZYX).0,
+        });
+    }
+}
+
+/// G switches between racing your own best lap and the server's fastest ghost
+fn choose_ghost(
+    keys: Res<ButtonInput<KeyCode>>,
+    local: Res<LocalPlayer>,
+    mut ghosts: ResMut<Ghosts>,
+    mut requests: Query<&mut MessageSender<GhostRequest>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyG) {
+        return;
+    }
+    ghosts.race_record = !ghosts.race_record;
+    if ghosts.race_record {
+        info!("Racing the record ghost from next lap");
+        for mut sender in requests.iter_mut() {
+            sender.send::<InputChannel>(GhostRequest {
+                track: local.track.clone(),
+            });
+        }
+    } else {
+        info!("Racing your personal best ghost from next lap");
+    }
+}
+
+fn receive_ghost_downloads(
+    mut receivers: Query<&mut MessageReceiver<GhostDownload>>,
+    mut ghosts: ResMut<Ghosts>,
+) {
+    for mut receiver in receivers.iter_mut() {
+        for download in receiver.receive() {
+            match &download.record {
+                Some((driver, ghost)) => info!(
+                    "Downloaded {}'s ghost for '{}': {:.3} s",
+                    driver, download.track, ghost.lap_time
+                ),
+                None => warn!("Nobody has set a ghost on '{}' yet", download.track),
+            }
+            ghosts.record = download.record;
+        }
+    }
+}
+
+/// Restart the ghost whenever the local car starts a lap
+fn start_ghost(
+    mut commands: Commands,
+    time: Res<Time>,
+    ghosts: Res<Ghosts>,
+    mut lap_started: EventReader<LapStarted>,
+    existing: Query<Entity, With<GhostCar>>,
+) {
+    if lap_started.read().count() == 0 {
+        return;
+    }
+    for entity in existing.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let chosen = match (&ghosts.record, ghosts.race_record) {
+        (Some((driver, ghost)), true) => Some((driver.clone(), ghost)),
+        _ => ghosts
+            .personal_best
+            .as_ref()
+            .map(|ghost| ("Best".to_string(), ghost)),
+    };
+    let Some((driver, ghost)) = chosen else {
+        return;
+    };
+    let Some((position, rotation)) = ghost.sample(0.0) else {
+        return;
+    };
+
+    // A `Player` makes `spawn_cars` draw it like any other car
+    commands.spawn((
+        GhostCar {
+            ghost: ghost.clone(),
+            started: time.elapsed_secs(),
+        },
+        Player {
+            client_id: GHOST_CLIENT_ID,
+            username: format!("{} {:.2}s", driver, ghost.lap_time),
+            color: [0.8, 0.8, 0.8],
+            is_bot: true,
+        },
+        Transform::from_translation(position.extend(-0.5))
+            .with_rotation(Quat::from_rotation_z(rotation)),
+        Visibility::default(),
+        StateScoped(AppState::Game),
+    ));
+}
+
+fn move_ghosts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut ghosts: Query<(Entity, &GhostCar, &mut Transform)>,
+) {
+    for (entity, ghost, mut transform) in ghosts.iter_mut() {
+        match ghost.ghost.sample(time.elapsed_secs() - ghost.started) {
+            Some((position, rotation)) => {
+                transform.translation = position.extend(transform.translation.z);
+                transform.rotation = Quat::from_rotation_z(rotation);
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+}
+
+/// Make ghost sprites translucent once `spawn_cars` has added them
+#[allow(clippy::type_complexity)]
+fn fade_ghosts(
+    ghosts: Query<(Entity, &Children), (With<GhostCar>, Added<Sprite>)>,
+    mut sprites: Query<&mut Sprite>,
+) {
+    for (entity, children) in ghosts.iter() {
+        for sprite_entity in std::iter::once(entity).chain(children.iter()) {
+            if let Ok(mut sprite) = sprites.get_mut(sprite_entity) {
+                sprite.color.set_alpha(GHOST_ALPHA);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn ghost_path(track: &str) -> std::path::PathBuf {
+    std::path::Path::new(GHOST_DIR).join(format!("{}.ghost", track))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load_ghost(track: &str) -> Option<Ghost> {
+    let bytes = std::fs::read(ghost_path(track)).ok()?;
+    Ghost::decode(&bytes)
+        .inspect_err(|e| warn!("Ignoring unreadable ghost for '{}': {}", track, e))
+        .ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_ghost(ghost: &Ghost) {
+    let path = ghost_path(&ghost.track);
+    let result =
+        std::fs::create_dir_all(GHOST_DIR).and_then(|_| std::fs::write(&path, ghost.encode()));
+    if let Err(e) = result {
+        warn!("Failed to save ghost to {}: {}", path.display(), e);
+    }
+}
+
+// The browser build keeps ghosts for the session only
+#[cfg(target_arch = "wasm32")]
+fn load_ghost(_track: &str) -> Option<Ghost> {
+    None
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_ghost(_ghost: &Ghost) {}
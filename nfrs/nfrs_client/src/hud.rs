@@ -0,0 +1,564 @@
+use bevy::prelude::*;
+use nfrs_shared::{Car, CarVelocity, Player, RaceProgress, StartCountdown, TrackLayout, RACE_LAPS};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::camera::{local_players, player_viewport};
+use crate::settings::Settings;
+use crate::spectator::Spectating;
+use crate::touch::TouchControls;
+use crate::track::surface_color;
+use crate::{AppState, LocalCar, LocalPlayer};
+
+const MINIMAP_WIDTH: f32 = 220.0;
+const DOT_SIZE: f32 = 8.0;
+const LOCAL_DOT_SIZE: f32 = 12.0;
+const LOCAL_COLOR: Color = Color::srgb(1.0, 0.85, 0.1);
+const PANEL_COLOR: Color = Color::srgba(0.05, 0.05, 0.1, 0.7);
+// The standings are rebuilt a few times per second rather than every frame
+const STANDINGS_INTERVAL: Duration = Duration::from_millis(250);
+// How long GO! stays up once the countdown is over, in seconds
+const GO_SHOWN_SECS: f32 = 1.0;
+
+#[derive(Component)]
+struct SpeedText(u8);
+
+#[derive(Component)]
+struct RaceText(u8);
+
+/// Panel of a local player, in the corner of their part of the screen
+#[derive(Component)]
+struct DriverPanel(u8);
+
+#[derive(Component)]
+struct StandingsList;
+
+/// Start countdown over the middle of a local player's part of the screen
+#[derive(Component)]
+struct CountdownOverlay {
+    seat: u8,
+    /// Seconds shown last frame, to catch the moment the countdown ends
+    last: u8,
+    /// When GO! is taken down
+    go_until: f32,
+}
+
+#[derive(Component)]
+struct CountdownText(u8);
+
+/// Part of the HUD shown whether or not this client drives
+#[derive(Component)]
+struct HudElement;
+
+/// Track drawn on the minimap, once known
+#[derive(Component, Default)]
+struct Minimap {
+    track: Option<TrackLayout>,
+}
+
+/// Minimap dot of a car
+#[derive(Component)]
+struct MinimapDot(Entity);
+
+/// When a local car started its current lap
+#[derive(Component, Default)]
+struct LapClock {
+    lap: u32,
+    started: f32,
+}
+
+#[derive(Resource)]
+struct StandingsTimer(Timer);
+
+impl Default for StandingsTimer {
+    fn default() -> Self {
+        Self(Timer::new(STANDINGS_INTERVAL, TimerMode::Repeating))
+    }
+}
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StandingsTimer>();
+        app.add_systems(OnEnter(AppState::Game), setup_hud);
+        app.add_systems(
+            Update,
+            (
+                update_driver_panel,
+                update_countdown,
+                update_standings,
+                draw_minimap_track,
+                update_minimap_dots,
+                show_hud,
+                make_room_for_touch_controls,
+            )
+                .run_if(in_state(AppState::Game)),
+        );
+    }
+}
+
+fn setup_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    spectating: Option<Res<Spectating>>,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_font = |size| TextFont {
+        font: font.clone(),
+        font_size: size,
+        ..default()
+    };
+
+    // Speed, position, lap and timing of each local car
+    let players = local_players(&settings, spectating.is_some());
+    for seat in 0..players {
+        let area = player_viewport(seat, players);
+        commands
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Percent((1.0 - area.max.y) * 100.0),
+                    left: Val::Percent(area.min.x * 100.0),
+                    margin: UiRect::all(Val::Px(10.0)),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                BackgroundColor(PANEL_COLOR),
+                Visibility::Hidden,
+                DriverPanel(seat),
+                LapClock::default(),
+                StateScoped(AppState::Game),
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    Text::new(""),
+                    text_font(40.0),
+                    TextColor(Color::WHITE),
+                    SpeedText(seat),
+                ));
+                parent.spawn((
+                    Text::new(""),
+                    text_font(18.0),
+                    TextColor(Color::srgb(0.8, 0.8, 0.8)),
+                    RaceText(seat),
+                ));
+            });
+
+        commands
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Percent(area.min.x * 100.0),
+                    top: Val::Percent(area.min.y * 100.0),
+                    width: Val::Percent(area.width() * 100.0),
+                    height: Val::Percent(area.height() * 100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                Visibility::Hidden,
+                CountdownOverlay {
+                    seat,
+                    last: 0,
+                    go_until: 0.0,
+                },
+                StateScoped(AppState::Game),
+            ))
+            .with_child((
+                Text::new(""),
+                text_font(120.0),
+                TextColor(LOCAL_COLOR),
+                CountdownText(seat),
+            ));
+    }
+
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.0),
+            ..default()
+        },
+        BackgroundColor(PANEL_COLOR),
+        StandingsList,
+        HudElement,
+        StateScoped(AppState::Game),
+    ));
+
+    // Sized once the track is known
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            right: Val::Px(10.0),
+            width: Val::Px(MINIMAP_WIDTH),
+            ..default()
+        },
+        BackgroundColor(PANEL_COLOR),
+        Minimap::default(),
+        HudElement,
+        StateScoped(AppState::Game),
+    ));
+}
+
+/// Cars from first to last: most checkpoints passed, then closest to the next one
+pub fn race_order<'a>(
+    cars: impl Iterator<Item = (Entity, &'a Player, &'a Transform, &'a RaceProgress)>,
+    track: Option<&TrackLayout>,
+) -> Vec<(Entity, &'a Player, &'a Transform, &'a RaceProgress)> {
+    let checkpoints = track.map_or(&[][..], |track| &track.checkpoints[..]);
+    let mut cars: Vec<_> = cars
+        .map(|car| {
+            let (_, _, transform, progress) = car;
+            let passed = progress.checkpoints_passed(checkpoints.len());
+            let to_next = checkpoints
+                .get(progress.next_checkpoint as usize)
+                .map_or(0.0, |(center, _)| {
+                    transform.translation.truncate().distance(*center)
+                });
+            (passed, to_next, car)
+        })
+        .collect();
+    cars.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.total_cmp(&b.1)));
+    cars.into_iter().map(|(_, _, car)| car).collect()
+}
+
+fn show_hud(settings: Res<Settings>, mut elements: Query<&mut Visibility, With<HudElement>>) {
+    if !settings.is_changed() {
+        return;
+    }
+    for mut visibility in elements.iter_mut() {
+        *visibility = if settings.show_hud {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// The touch controls take the bottom corners, so the panel and minimap move to the top.
+/// Split-screen keeps its layout.
+#[allow(clippy::type_complexity)]
+fn make_room_for_touch_controls(
+    controls: Res<TouchControls>,
+    new_panels: Query<(), Added<DriverPanel>>,
+    mut panels: Query<&mut Node, (With<DriverPanel>, Without<Minimap>)>,
+    mut minimaps: Query<&mut Node, (With<Minimap>, Without<DriverPanel>)>,
+) {
+    if !controls.is_changed() && new_panels.is_empty() {
+        return;
+    }
+    if let Ok(mut node) = panels.single_mut() {
+        // The panel has a margin of its own
+        (node.top, node.bottom) = if controls.active {
+            (Val::Px(0.0), Val::Auto)
+        } else {
+            (Val::Auto, Val::Px(0.0))
+        };
+        node.left = if controls.active {
+            Val::Percent(35.0)
+        } else {
+            Val::Px(0.0)
+        };
+    }
+    // Below the menu button
+    for mut node in minimaps.iter_mut() {
+        node.top = if controls.active {
+            Val::Percent(12.0)
+        } else {
+            Val::Auto
+        };
+        node.bottom = if controls.active {
+            Val::Auto
+        } else {
+            Val::Px(10.0)
+        };
+    }
+}
+
+/// Minutes, seconds and hundredths
+fn format_time(secs: f32) -> String {
+    format!("{}:{:05.2}", (secs / 60.0) as u32, secs % 60.0)
+}
+
+fn player_color(player: &Player) -> Color {
+    Color::srgb(player.color[0], player.color[1], player.color[2])
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_driver_panel(
+    time: Res<Time>,
+    local: Res<LocalPlayer>,
+    settings: Res<Settings>,
+    cars: Query<(Entity, &Player, &Transform, &RaceProgress), With<Car>>,
+    local_cars: Query<(Entity, Option<&CarVelocity>, &RaceProgress, &LocalCar)>,
+    mut panels: Query<(&DriverPanel, &mut LapClock, &mut Visibility)>,
+    mut speed_texts: Query<(&mut Text, &SpeedText)>,
+    mut race_texts: Query<(&mut Text, &RaceText), Without<SpeedText>>,
+) {
+    let track = TrackLayout::by_name(&local.track);
+    let order = race_order(cars.iter(), track.as_ref());
+    let now = time.elapsed_secs();
+    for (panel, mut clock, mut visibility) in panels.iter_mut() {
+        // Spectators, and drivers whose car has not arrived yet
+        let Some((entity, velocity, progress, _)) =
+            local_cars.iter().find(|(.., car)| car.seat == panel.0)
+        else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        if !settings.show_hud {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        *visibility = Visibility::Inherited;
+
+        if progress.lap != clock.lap {
+            clock.lap = progress.lap;
+            clock.started = now;
+        }
+
+        if let Some((mut text, _)) = speed_texts.iter_mut().find(|(_, speed)| speed.0 == panel.0) {
+            let speed = velocity.map_or(0.0, |velocity| velocity.0.length());
+            text.0 = format!("{:.0} km/h", speed * 3.6);
+        }
+
+        if let Some((mut text, _)) = race_texts.iter_mut().find(|(_, race)| race.0 == panel.0) {
+            text.0 = race_lines(entity, progress, &order, now - clock.started);
+        }
+    }
+}
+
+/// Count each local car down to the start, then show GO! as it is let go
+fn update_countdown(
+    time: Res<Time>,
+    local_cars: Query<(&StartCountdown, &LocalCar)>,
+    mut overlays: Query<(&mut CountdownOverlay, &mut Visibility)>,
+    mut texts: Query<(&mut Text, &CountdownText)>,
+) {
+    let now = time.elapsed_secs();
+    for (mut overlay, mut visibility) in overlays.iter_mut() {
+        let seconds = local_cars
+            .iter()
+            .find(|(_, car)| car.seat == overlay.seat)
+            .map_or(0, |(countdown, _)| countdown.0);
+        if seconds == 0 && overlay.last > 0 {
+            overlay.go_until = now + GO_SHOWN_SECS;
+        }
+        overlay.last = seconds;
+
+        let shown = if seconds > 0 {
+            seconds.to_string()
+        } else if now < overlay.go_until {
+            "GO!".to_string()
+        } else {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        };
+        visibility.set_if_neq(Visibility::Inherited);
+        if let Some((mut text, _)) = texts.iter_mut().find(|(_, text)| text.0 == overlay.seat) {
+            if text.0 != shown {
+                text.0 = shown;
+            }
+        }
+    }
+}
+
+/// Position, lap and timing of a local car
+fn race_lines(
+    entity: Entity,
+    progress: &RaceProgress,
+    order: &[(Entity, &Player, &Transform, &RaceProgress)],
+    lap_time: f32,
+) -> String {
+    let position = order.iter().position(|(car, ..)| *car == entity);
+    let mut lines = vec![match position {
+        Some(position) => format!("P{}/{}", position + 1, order.len()),
+        None => String::new(),
+    }];
+    lines.push(match progress.lap {
+        0 => "Cross the start line to begin".to_string(),
+        lap if lap <= RACE_LAPS => format!("Lap {}/{}   {}", lap, RACE_LAPS, format_time(lap_time)),
+        lap => format!("Finished   lap {}   {}", lap, format_time(lap_time)),
+    });
+    let timing =
+        |label, lap: Option<f32>| format!("{} {}", label, lap.map_or("-".into(), format_time));
+    lines.push(format!(
+        "{}   {}",
+        timing("Last", progress.last_lap),
+        timing("Best", progress.best_lap)
+    ));
+    lines.join("\n")
+}
+
+fn update_standings(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut timer: ResMut<StandingsTimer>,
+    asset_server: Res<AssetServer>,
+    local: Res<LocalPlayer>,
+    cars: Query<(Entity, &Player, &Transform, &RaceProgress), With<Car>>,
+    lists: Query<Entity, With<StandingsList>>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    let Ok(list) = lists.single() else {
+        return;
+    };
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let track = TrackLayout::by_name(&local.track);
+    let order = race_order(cars.iter(), track.as_ref());
+
+    commands.entity(list).despawn_related::<Children>();
+    commands.entity(list).with_children(|parent| {
+        for (position, (_, player, _, progress)) in order.into_iter().enumerate() {
+            let is_local = local.is_local(player.client_id);
+            parent
+                .spawn(Node {
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(6.0),
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn((
+                        Node {
+                            width: Val::Px(12.0),
+                            height: Val::Px(12.0),
+                            ..default()
+                        },
+                        BackgroundColor(player_color(player)),
+                    ));
+                    row.spawn((
+                        Text::new(format!(
+                            "{}. {}   lap {}",
+                            position + 1,
+                            player.username,
+                            progress.lap
+                        )),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: 16.0,
+                            ..default()
+                        },
+                        TextColor(if is_local { LOCAL_COLOR } else { Color::WHITE }),
+                    ));
+                });
+        }
+    });
+}
+
+/// Draw the track's surfaces on the minimap once the track is known
+fn draw_minimap_track(
+    mut commands: Commands,
+    local: Res<LocalPlayer>,
+    mut minimaps: Query<(Entity, &mut Minimap, &mut Node)>,
+) {
+    let Ok((entity, mut minimap, mut node)) = minimaps.single_mut() else {
+        return;
+    };
+    if minimap.track.as_ref().map(|track| track.name) == Some(local.track.as_str()) {
+        return;
+    }
+    let Some(track) = TrackLayout::by_name(&local.track) else {
+        return;
+    };
+    let bounds = track.bounds();
+    let scale = MINIMAP_WIDTH / bounds.width();
+    node.height = Val::Px(bounds.height() * scale);
+
+    let mut surfaces = track.surfaces.clone();
+    surfaces.sort_by_key(|(_, region)| region.kind.priority());
+    commands.entity(entity).despawn_related::<Children>();
+    commands.entity(entity).with_children(|parent| {
+        for (center, region) in surfaces {
+            let top_left = Vec2::new(
+                center.x - region.half_extents.x - bounds.min.x,
+                bounds.max.y - center.y - region.half_extents.y,
+            ) * scale;
+            let size = region.half_extents * 2.0 * scale;
+            parent.spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(top_left.x),
+                    top: Val::Px(top_left.y),
+                    width: Val::Px(size.x),
+                    height: Val::Px(size.y),
+                    ..default()
+                },
+                BackgroundColor(surface_color(region.kind).with_alpha(0.8)),
+            ));
+        }
+    });
+    minimap.track = Some(track);
+}
+
+fn update_minimap_dots(
+    mut commands: Commands,
+    local: Res<LocalPlayer>,
+    minimaps: Query<(Entity, &Minimap)>,
+    cars: Query<(Entity, &Player, &Transform), With<Car>>,
+    mut dots: Query<(Entity, &MinimapDot, &mut Node)>,
+) {
+    let Ok((minimap, Minimap { track: Some(track) })) = minimaps.single() else {
+        return;
+    };
+    let bounds = track.bounds();
+    let scale = MINIMAP_WIDTH / bounds.width();
+    let place = |position: Vec2, size: f32| {
+        let offset = Vec2::new(position.x - bounds.min.x, bounds.max.y - position.y) * scale;
+        (
+            Val::Px(offset.x - size / 2.0),
+            Val::Px(offset.y - size / 2.0),
+        )
+    };
+
+    let mut positions: HashMap<Entity, (&Player, Vec2)> = cars
+        .iter()
+        .map(|(entity, player, transform)| (entity, (player, transform.translation.truncate())))
+        .collect();
+    for (dot, car, mut node) in dots.iter_mut() {
+        match positions.remove(&car.0) {
+            Some((_, position)) => {
+                let size = node.width;
+                let Val::Px(size) = size else {
+                    continue;
+                };
+                (node.left, node.top) = place(position, size);
+            }
+            None => commands.entity(dot).despawn(),
+        }
+    }
+
+    for (car, (player, position)) in positions {
+        let is_local = local.is_local(player.client_id);
+        let size = if is_local { LOCAL_DOT_SIZE } else { DOT_SIZE };
+        let (left, top) = place(position, size);
+        let dot = commands
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    left,
+                    top,
+                    width: Val::Px(size),
+                    height: Val::Px(size),
+                    border: UiRect::all(Val::Px(2.0)),
+                    ..default()
+                },
+                BackgroundColor(player_color(player)),
+                BorderColor(if is_local { LOCAL_COLOR } else { Color::BLACK }),
+                BorderRadius::MAX,
+                // Above the surfaces
+                ZIndex(1),
+                MinimapDot(car),
+            ))
+            .id();
+        commands.entity(minimap).add_child(dot);
+    }
+}
@@ -0,0 +1,145 @@
+use bevy::prelude::*;
+use lightyear::prelude::*;
+use nfrs_shared::{CarDamage, ImpactEvent, ImpactKind};
+use rand::Rng;
+
+use crate::settings::Settings;
+use crate::AppState;
+
+// Impact strength (m/s of velocity change) that produces a full-strength shake
+const FULL_SHAKE_STRENGTH: f32 = 15.0;
+// Maximum camera offset in world units at full trauma
+const MAX_SHAKE_OFFSET: f32 = 0.8;
+// Trauma lost per second
+const SHAKE_DECAY: f32 = 2.0;
+const SPARK_LIFETIME: f32 = 0.4;
+
+/// Screen shake state. Trauma is in 0..=1; the offset grows with trauma squared.
+#[derive(Resource, Default)]
+pub struct CameraShake {
+    trauma: f32,
+    offset: Vec2,
+}
+
+impl CameraShake {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+
+    /// Current camera offset, added on top of wherever the camera is aimed
+    pub fn offset(&self) -> Vec2 {
+        self.offset
+    }
+}
+
+/// An impact reported by the server, for the effects and sounds that follow it
+#[derive(Event)]
+pub struct ImpactReceived(pub ImpactEvent);
+
+#[derive(Component)]
+struct Spark {
+    velocity: Vec2,
+    age: f32,
+}
+
+pub struct ImpactPlugin;
+
+impl Plugin for ImpactPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraShake>();
+        app.add_event::<ImpactReceived>();
+        app.add_systems(
+            Update,
+            (
+                (receive_impacts, shake_and_spark).chain(),
+                update_sparks,
+                update_camera_shake,
+                tint_damaged_cars,
+            )
+                .run_if(in_state(AppState::Game)),
+        );
+    }
+}
+
+fn receive_impacts(
+    mut receivers: Query<&mut MessageReceiver<ImpactEvent>>,
+    mut received: EventWriter<ImpactReceived>,
+) {
+    for mut receiver in receivers.iter_mut() {
+        received.write_batch(receiver.receive().map(ImpactReceived));
+    }
+}
+
+fn shake_and_spark(
+    mut commands: Commands,
+    mut impacts: EventReader<ImpactReceived>,
+    mut shake: ResMut<CameraShake>,
+) {
+    let mut rng = rand::thread_rng();
+    for ImpactReceived(impact) in impacts.read() {
+        let intensity = (impact.strength / FULL_SHAKE_STRENGTH).clamp(0.0, 1.0);
+        shake.add_trauma(intensity * 0.6);
+
+        // Walls throw off pale sparks, car-to-car hits are hotter
+        let color = match impact.kind {
+            ImpactKind::CarToCar => Color::srgb(1.0, 0.6, 0.1),
+            ImpactKind::CarToWall => Color::srgb(1.0, 0.9, 0.6),
+        };
+        let count = 4 + (intensity * 16.0) as usize;
+        for _ in 0..count {
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let speed = rng.gen_range(4.0..12.0) * (0.5 + intensity);
+            commands.spawn((
+                Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(0.2)),
+                    ..default()
+                },
+                Transform::from_translation(impact.position.extend(5.0)),
+                StateScoped(AppState::Game),
+                Spark {
+                    velocity: Vec2::from_angle(angle) * speed,
+                    age: 0.0,
+                },
+            ));
+        }
+    }
+}
+
+fn update_sparks(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut sparks: Query<(Entity, &mut Spark, &mut Transform, &mut Sprite)>,
+) {
+    let dt = time.delta_secs();
+    for (entity, mut spark, mut transform, mut sprite) in sparks.iter_mut() {
+        spark.age += dt;
+        if spark.age >= SPARK_LIFETIME {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        // Sparks slow down quickly and fade out
+        spark.velocity *= 1.0 - (6.0 * dt).min(1.0);
+        transform.translation += (spark.velocity * dt).extend(0.0);
+        sprite.color.set_alpha(1.0 - spark.age / SPARK_LIFETIME);
+    }
+}
+
+fn update_camera_shake(time: Res<Time>, settings: Res<Settings>, mut shake: ResMut<CameraShake>) {
+    let mut rng = rand::thread_rng();
+    shake.trauma = (shake.trauma - SHAKE_DECAY * time.delta_secs()).max(0.0);
+    let magnitude = shake.trauma * shake.trauma * MAX_SHAKE_OFFSET;
+    shake.offset = if magnitude > 0.0 && settings.screen_shake {
+        Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)) * magnitude
+    } else {
+        Vec2::ZERO
+    };
+}
+
+/// Darken car sprites as they take damage
+fn tint_damaged_cars(mut cars: Query<(&CarDamage, &mut Sprite), Changed<CarDamage>>) {
+    for (damage, mut sprite) in cars.iter_mut() {
+        let shade = 1.0 - 0.6 * damage.fraction();
+        sprite.color = Color::srgb(shade, shade, shade);
+    }
+}
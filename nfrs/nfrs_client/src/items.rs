@@ -0,0 +1,152 @@
+use bevy::prelude::*;
+use nfrs_shared::{Hazard, HazardKind, ItemBox, ItemEffects, Nitro};
+
+use crate::AppState;
+
+/// Spinning crate drawn for an item box
+#[derive(Component)]
+struct ItemBoxCrate;
+
+#[derive(Component)]
+struct ShieldBubble;
+
+#[derive(Component)]
+struct BoostFlame;
+
+pub struct ItemPlugin;
+
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_item_boxes,
+                update_item_boxes,
+                spawn_hazards,
+                spawn_effect_sprites,
+                update_effect_sprites,
+            )
+                .run_if(in_state(AppState::Game)),
+        );
+    }
+}
+
+fn spawn_item_boxes(mut commands: Commands, boxes: Query<(Entity, &ItemBox), Added<ItemBox>>) {
+    for (entity, item_box) in boxes.iter() {
+        commands
+            .entity(entity)
+            .insert(visibility(item_box.active))
+            .with_children(|parent| {
+                parent.spawn((
+                    ItemBoxCrate,
+                    Sprite {
+                        color: Color::srgb(1.0, 0.85, 0.1),
+                        custom_size: Some(Vec2::splat(1.2)),
+                        ..default()
+                    },
+                    Transform::from_xyz(0.0, 0.0, -1.0),
+                ));
+            });
+    }
+}
+
+/// Hide collected boxes and keep the active ones spinning
+fn update_item_boxes(
+    time: Res<Time>,
+    mut boxes: Query<(&ItemBox, &mut Visibility), Changed<ItemBox>>,
+    mut crates: Query<&mut Transform, With<ItemBoxCrate>>,
+) {
+    for (item_box, mut vis) in boxes.iter_mut() {
+        *vis = visibility(item_box.active);
+    }
+    let angle = time.elapsed_secs() * 2.0;
+    for mut transform in crates.iter_mut() {
+        transform.rotation = Quat::from_rotation_z(angle);
+    }
+}
+
+fn spawn_hazards(mut commands: Commands, hazards: Query<(Entity, &Hazard), Added<Hazard>>) {
+    for (entity, hazard) in hazards.iter() {
+        let (color, size) = match hazard.kind {
+            HazardKind::OilSlick => (Color::srgba(0.05, 0.05, 0.08, 0.85), 3.0),
+            HazardKind::Projectile => (Color::srgb(0.9, 0.1, 0.1), 1.0),
+        };
+        // Oil lies on the track under the cars, projectiles fly above them
+        let z = match hazard.kind {
+            HazardKind::OilSlick => -1.0,
+            HazardKind::Projectile => 1.0,
+        };
+        commands
+            .entity(entity)
+            .insert(Visibility::default())
+            .with_children(|parent| {
+                parent.spawn((
+                    Sprite {
+                        color,
+                        custom_size: Some(Vec2::splat(size)),
+                        ..default()
+                    },
+                    Transform::from_xyz(0.0, 0.0, z),
+                ));
+            });
+    }
+}
+
+/// Give every car a (hidden) shield bubble and boost flame
+fn spawn_effect_sprites(mut commands: Commands, cars: Query<Entity, Added<ItemEffects>>) {
+    for entity in cars.iter() {
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                ShieldBubble,
+                Sprite {
+                    color: Color::srgba(0.3, 0.7, 1.0, 0.35),
+                    custom_size: Some(Vec2::new(3.2, 5.0)),
+                    ..default()
+                },
+                Transform::from_xyz(0.0, 0.0, 0.5),
+                Visibility::Hidden,
+            ));
+            parent.spawn((
+                BoostFlame,
+                Sprite {
+                    color: Color::srgb(1.0, 0.55, 0.1),
+                    custom_size: Some(Vec2::new(1.0, 1.6)),
+                    ..default()
+                },
+                Transform::from_xyz(0.0, -2.6, -0.02),
+                Visibility::Hidden,
+            ));
+        });
+    }
+}
+
+fn update_effect_sprites(
+    time: Res<Time>,
+    cars: Query<(&ItemEffects, Option<&Nitro>, &Children)>,
+    mut shields: Query<&mut Visibility, (With<ShieldBubble>, Without<BoostFlame>)>,
+    mut flames: Query<(&mut Visibility, &mut Transform), With<BoostFlame>>,
+) {
+    // Flicker the flame length
+    let flicker = 1.0 + 0.25 * (time.elapsed_secs() * 40.0).sin();
+    for (effects, nitro, children) in cars.iter() {
+        // Item boosts and nitro share the same flame
+        let boosting = effects.is_boosting() || nitro.is_some_and(|nitro| nitro.boosting);
+        for child in children.iter() {
+            if let Ok(mut vis) = shields.get_mut(child) {
+                vis.set_if_neq(visibility(effects.is_shielded()));
+            }
+            if let Ok((mut vis, mut transform)) = flames.get_mut(child) {
+                vis.set_if_neq(visibility(boosting));
+                transform.scale.y = flicker;
+            }
+        }
+    }
+}
+
+fn visibility(visible: bool) -> Visibility {
+    if visible {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    }
+}
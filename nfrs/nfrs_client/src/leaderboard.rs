@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+use lightyear::prelude::*;
+use nfrs_shared::{InputChannel, Leaderboard, LeaderboardRequest};
+
+use crate::{AppState, LocalPlayer};
+
+/// Panel showing the server's leaderboards for the current track, toggled with L
+#[derive(Component)]
+struct LeaderboardPanel;
+
+#[derive(Component)]
+struct LeaderboardText;
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Game), setup_leaderboard);
+        app.add_systems(
+            Update,
+            (toggle_leaderboard, receive_leaderboard).run_if(in_state(AppState::Game)),
+        );
+    }
+}
+
+fn setup_leaderboard(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            Visibility::Hidden,
+            LeaderboardPanel,
+            StateScoped(AppState::Game),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        padding: UiRect::all(Val::Px(20.0)),
+                        border: UiRect::all(Val::Px(3.0)),
+                        ..default()
+                    },
+                    BorderColor(Color::srgb(0.3, 0.3, 0.8)),
+                    BackgroundColor(Color::srgba(0.05, 0.05, 0.1, 0.9)),
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new("Loading leaderboard..."),
+                        TextFont {
+                            font,
+                            font_size: 20.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                        LeaderboardText,
+                    ));
+                });
+        });
+}
+
+/// L shows the leaderboard, fetching it fresh from the server each time
+fn toggle_leaderboard(
+    keys: Res<ButtonInput<KeyCode>>,
+    local: Res<LocalPlayer>,
+    mut panels: Query<&mut Visibility, With<LeaderboardPanel>>,
+    mut requests: Query<&mut MessageSender<LeaderboardRequest>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyL) {
+        return;
+    }
+    for mut visibility in panels.iter_mut() {
+        *visibility = if *visibility == Visibility::Hidden {
+            for mut sender in requests.iter_mut() {
+                sender.send::<InputChannel>(LeaderboardRequest {
+                    track: local.track.clone(),
+                });
+            }
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn receive_leaderboard(
+    mut receivers: Query<&mut MessageReceiver<Leaderboard>>,
+    mut text: Query<&mut Text, With<LeaderboardText>>,
+) {
+    for mut receiver in receivers.iter_mut() {
+        for leaderboard in receiver.receive() {
+            if let Ok(mut text) = text.single_mut() {
+                text.0 = format_leaderboard(&leaderboard);
+            }
+        }
+    }
+}
+
+fn format_leaderboard(leaderboard: &Leaderboard) -> String {
+    let mut lines = vec![format!("Fastest laps on '{}'", leaderboard.track)];
+    if leaderboard.best_laps.is_empty() {
+        lines.push("  No laps yet".to_string());
+    }
+    for (rank, record) in leaderboard.best_laps.iter().enumerate() {
+        lines.push(format!(
+            "  {:>2}. {:<16} {:>7.3} s",
+            rank + 1,
+            record.player,
+            record.lap_time
+        ));
+    }
+
+    lines.push(String::new());
+    lines.push("Top drivers".to_string());
+    if leaderboard.players.is_empty() {
+        lines.push("  No races finished yet".to_string());
+    }
+    for (rank, stats) in leaderboard.players.iter().enumerate() {
+        lines.push(format!(
+            "  {:>2}. {:<16} {} wins / {} races, {:.1} km",
+            rank + 1,
+            stats.player,
+            stats.wins,
+            stats.races,
+            stats.distance / 1000.0
+        ));
+    }
+    lines.push(String::new());
+    lines.push("Press L to close".to_string());
+    lines.join("\n")
+}
@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+
+use crate::camera::{player_layer, CameraRig};
+use crate::{AppState, LocalCar};
+
+const HIGHLIGHT_COLOR: Color = Color::srgb(1.0, 0.85, 0.1);
+// Height of the marker above the car, above its name label
+const MARKER_HEIGHT: f32 = 5.0;
+// Distance of the off-screen arrow from the window edge, in pixels
+const EDGE_MARGIN: f32 = 40.0;
+// Camera scale at which markers are drawn at their natural size
+const MARKER_REFERENCE_SCALE: f32 = 0.03;
+
+/// Arrow floating above a local car
+#[derive(Component)]
+struct CarMarker(Entity);
+
+/// Arrow at the edge of a local player's view pointing at their car while it is out of
+/// view; only that player's camera sees it
+#[derive(Component)]
+struct OffscreenArrow {
+    car: Entity,
+    seat: u8,
+}
+
+pub struct LocalCarPlugin;
+
+impl Plugin for LocalCarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (add_glow_and_markers, move_markers, move_offscreen_arrows)
+                .chain()
+                .run_if(in_state(AppState::Game)),
+        );
+    }
+}
+
+/// A triangle pointing down (-Y)
+fn arrow_mesh() -> Triangle2d {
+    Triangle2d::new(
+        Vec2::new(0.0, -0.6),
+        Vec2::new(-0.6, 0.6),
+        Vec2::new(0.6, 0.6),
+    )
+}
+
+/// Soft glow under each local car, and its markers
+fn add_glow_and_markers(
+    mut commands: Commands,
+    cars: Query<(Entity, &LocalCar), Added<LocalCar>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (entity, car) in cars.iter() {
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                Mesh2d(meshes.add(Circle::new(3.0))),
+                MeshMaterial2d(materials.add(HIGHLIGHT_COLOR.with_alpha(0.25))),
+                Transform::from_xyz(0.0, 0.0, -0.02),
+            ));
+        });
+
+        let mesh = meshes.add(arrow_mesh());
+        let material = materials.add(HIGHLIGHT_COLOR);
+        commands.spawn((
+            Mesh2d(mesh.clone()),
+            MeshMaterial2d(material.clone()),
+            Transform::default(),
+            Visibility::Hidden,
+            CarMarker(entity),
+            StateScoped(AppState::Game),
+        ));
+        commands.spawn((
+            Mesh2d(mesh),
+            MeshMaterial2d(material),
+            Transform::default(),
+            Visibility::Hidden,
+            OffscreenArrow {
+                car: entity,
+                seat: car.seat,
+            },
+            player_layer(car.seat),
+            StateScoped(AppState::Game),
+        ));
+    }
+}
+
+fn move_markers(
+    mut commands: Commands,
+    cars: Query<&GlobalTransform, With<LocalCar>>,
+    cameras: Query<&CameraRig>,
+    mut markers: Query<(Entity, &CarMarker, &mut Transform, &mut Visibility)>,
+) {
+    let (rotation, scale) =
+        cameras
+            .iter()
+            .find(|rig| rig.seat == 0)
+            .map_or((Quat::IDENTITY, 1.0), |rig| {
+                (
+                    Quat::from_rotation_z(rig.rotation),
+                    rig.scale / MARKER_REFERENCE_SCALE,
+                )
+            });
+    for (entity, marker, mut transform, mut visibility) in markers.iter_mut() {
+        let Ok(car) = cars.get(marker.0) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        // Upright on the first player's screen like the name labels, and a constant size
+        // whatever the zoom
+        transform.translation = car.translation() + rotation * Vec3::new(0.0, MARKER_HEIGHT, 10.0);
+        transform.rotation = rotation;
+        transform.scale = Vec3::splat(scale);
+        *visibility = Visibility::Inherited;
+    }
+}
+
+fn move_offscreen_arrows(
+    mut commands: Commands,
+    cars: Query<&GlobalTransform, With<LocalCar>>,
+    cameras: Query<(&Camera, &GlobalTransform, &CameraRig)>,
+    mut arrows: Query<(Entity, &OffscreenArrow, &mut Transform, &mut Visibility)>,
+) {
+    for (entity, arrow, mut transform, mut visibility) in arrows.iter_mut() {
+        *visibility = Visibility::Hidden;
+        let Ok(car) = cars.get(arrow.car) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        let Some((camera, camera_transform, rig)) =
+            cameras.iter().find(|(.., rig)| rig.seat == arrow.seat)
+        else {
+            continue;
+        };
+        // In window coordinates; in split-screen the view is only part of the window
+        let (Ok(on_screen), Some(view)) = (
+            camera.world_to_viewport(camera_transform, car.translation()),
+            camera.logical_viewport_rect(),
+        ) else {
+            continue;
+        };
+        if view.contains(on_screen) {
+            continue;
+        }
+
+        let edge = on_screen.clamp(view.min + EDGE_MARGIN, view.max - EDGE_MARGIN);
+        let Ok(position) = camera.viewport_to_world_2d(camera_transform, edge) else {
+            continue;
+        };
+        let direction = car.translation().truncate() - position;
+        transform.translation = position.extend(20.0);
+        // The mesh points down (-Y)
+        transform.rotation =
+            Quat::from_rotation_z(direction.to_angle() + std::f32::consts::FRAC_PI_2);
+        transform.scale = Vec3::splat(rig.scale / MARKER_REFERENCE_SCALE);
+        *visibility = Visibility::Inherited;
+    }
+}
@@ -3,9 +3,39 @@ use clap::Parser;
 use lightyear::netcode::Key;
 use lightyear::prelude::client::*;
 use lightyear::prelude::*;
-use nfrs_shared::{CarInput, InputChannel, Player, ProtocolPlugin};
+use nfrs_shared::{
+    CarInput, ChatLine, ChatSend, GhostDownload, GhostRequest, GhostUpload, ImpactEvent,
+    InputChannel, JoinAccepted, Leaderboard, LeaderboardRequest, NoticeLevel, Player,
+    ProtocolPlugin, ServerNotice, ServerStatus,
+};
+use rand::Rng;
+use std::collections::BTreeMap;
 use std::net::{Ipv4Addr, SocketAddr};
-use tracing::info;
+use tracing::{debug, info, warn};
+
+use controls::{Action, ActionState};
+
+mod camera;
+mod chat;
+mod controls;
+mod effects;
+#[cfg(not(target_arch = "wasm32"))]
+mod embedded;
+mod ghost;
+mod hud;
+mod impact;
+mod items;
+mod leaderboard;
+mod local_car;
+mod pause;
+#[cfg(not(target_arch = "wasm32"))]
+mod replay;
+mod settings;
+#[cfg(any(feature = "audio", target_arch = "wasm32"))]
+mod sound;
+mod spectator;
+mod touch;
+mod track;
 
 #[cfg(not(target_arch = "wasm32"))]
 use lightyear::prelude::UdpIo;
@@ -22,9 +52,67 @@ fn log_digest() {
 #[derive(Parser, Debug, Resource)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Server IP address
-    #[arg(short, long, default_value = "127.0.0.1")]
-    ip: String,
+    /// Server IP address, instead of the one in the settings
+    #[arg(short, long)]
+    ip: Option<String>,
+    /// Server UDP port, instead of the one in the settings
+    #[arg(short, long)]
+    port: Option<u16>,
+    /// Where the game runs; can also be changed in the menu
+    #[arg(long, value_enum, default_value_t = GameMode::Online)]
+    mode: GameMode,
+    /// Watch the race without driving
+    #[arg(long)]
+    spectate: bool,
+    /// Show the race this many seconds late while spectating, e.g. when streaming it
+    #[arg(long, default_value_t = 0.0)]
+    spectate_delay: f32,
+    /// Bots to add when running the server in-process (practice defaults to 3)
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long)]
+    bots: Option<usize>,
+    /// Difficulty of those bots
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long, value_enum, default_value_t = nfrs_server::BotDifficulty::Medium)]
+    bot_difficulty: nfrs_server::BotDifficulty,
+    /// Watch a replay file recorded by a server instead of playing
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long)]
+    replay: Option<std::path::PathBuf>,
+}
+
+/// Whether to join a remote server or run the server simulation in this process
+#[derive(clap::ValueEnum, Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GameMode {
+    /// Join a dedicated server
+    #[default]
+    Online,
+    /// Offline race against bots
+    Practice,
+    /// Play while hosting a server other players on the LAN can join
+    Host,
+}
+
+impl GameMode {
+    fn label(self) -> &'static str {
+        match self {
+            GameMode::Online => "Online",
+            GameMode::Practice => "Practice (offline)",
+            GameMode::Host => "Host LAN game",
+        }
+    }
+
+    /// Next mode in the menu; the browser build can only play online
+    fn next(self) -> Self {
+        if cfg!(target_arch = "wasm32") {
+            return GameMode::Online;
+        }
+        match self {
+            GameMode::Online => GameMode::Practice,
+            GameMode::Practice => GameMode::Host,
+            GameMode::Host => GameMode::Online,
+        }
+    }
 }
 
 #[derive(States, Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
@@ -32,11 +120,72 @@ enum AppState {
     #[default]
     Menu,
     Game,
+    /// Watching a recorded replay
+    Replay,
+}
+
+/// Run condition for systems that draw the track and cars
+fn showing_race(state: Res<State<AppState>>) -> bool {
+    matches!(state.get(), AppState::Game | AppState::Replay)
 }
 
 #[derive(Resource, Default)]
 struct UsernameInput(String);
 
+/// Latest health report from the server
+#[derive(Resource, Default)]
+pub struct LatestServerStatus(pub Option<ServerStatus>);
+
+/// Our own car, as confirmed by the server after joining
+#[derive(Resource, Default)]
+pub struct LocalPlayer {
+    pub client_id: Option<u64>,
+    /// Cars of the other players on this screen in split-screen, by seat
+    pub guests: BTreeMap<u8, u64>,
+    /// Name of the track being raced
+    pub track: String,
+}
+
+impl LocalPlayer {
+    /// Which of the players on this screen drives a car, if any
+    pub fn seat_of(&self, client_id: u64) -> Option<u8> {
+        if self.client_id == Some(client_id) {
+            return Some(0);
+        }
+        self.guests
+            .iter()
+            .find(|(_, id)| **id == client_id)
+            .map(|(seat, _)| *seat)
+    }
+
+    pub fn is_local(&self, client_id: u64) -> bool {
+        self.seat_of(client_id).is_some()
+    }
+}
+
+/// Marks a car driven by this client
+#[derive(Component)]
+pub struct LocalCar {
+    /// Which of the local players drives it
+    pub seat: u8,
+}
+
+// Server usernames are limited to 24 characters
+const MAX_USERNAME_LEN: usize = 24;
+// ...leaving room for the seat after a split-screen player's name
+const GUEST_NAME_LEN: usize = 20;
+
+/// Name to start with until the player picks one, so joining needs no keyboard
+fn random_username() -> String {
+    format!("Driver{}", rand::thread_rng().gen_range(100..1000))
+}
+
+/// Name of a split-screen player after the first, e.g. "alice (2)"
+fn guest_username(username: &str, seat: u8) -> String {
+    let name: String = username.trim().chars().take(GUEST_NAME_LEN).collect();
+    format!("{} ({})", name, seat + 1)
+}
+
 fn main() {
     // Set up panic hook and logging for WASM
     #[cfg(target_arch = "wasm32")]
@@ -52,32 +201,120 @@ fn main() {
 
     let args = Args::parse();
 
-    App::new()
+    let mut app = App::new();
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = &args.replay {
+        app.insert_resource(replay::ReplayFile(path.clone()))
+            .add_plugins(replay::ReplayPlugin);
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    let initial_state = if args.replay.is_some() {
+        AppState::Replay
+    } else {
+        AppState::Menu
+    };
+    #[cfg(target_arch = "wasm32")]
+    let initial_state = AppState::Menu;
+
+    if args.spectate {
+        app.insert_resource(spectator::Spectating {
+            delay: args.spectate_delay,
+        });
+    }
+
+    let settings = settings::load();
+    app.insert_resource(args.mode)
         .insert_resource(args)
-        .init_resource::<UsernameInput>()
-        .add_plugins(DefaultPlugins.set(bevy::asset::AssetPlugin {
-            meta_check: bevy::asset::AssetMetaCheck::Never,
-            ..default()
+        .insert_resource(UsernameInput(if settings.username.trim().is_empty() {
+            random_username()
+        } else {
+            settings.username.clone()
         }))
-        .init_state::<AppState>()
+        .insert_resource(settings)
+        .init_resource::<LatestServerStatus>()
+        .init_resource::<LocalPlayer>()
+        .add_plugins(
+            DefaultPlugins
+                .set(bevy::asset::AssetPlugin {
+                    meta_check: bevy::asset::AssetMetaCheck::Never,
+                    ..default()
+                })
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: "NFRS Racing".into(),
+                        // In the browser, fill the canvas laid out by index.html
+                        canvas: Some("#game".into()),
+                        fit_canvas_to_parent: true,
+                        prevent_default_event_handling: true,
+                        ..default()
+                    }),
+                    ..default()
+                }),
+        )
+        .insert_state(initial_state)
+        .enable_state_scoped_entities::<AppState>()
         .add_plugins(ClientPlugins::default())
         .add_plugins(ProtocolPlugin)
-        .add_systems(Startup, setup_camera) // Separate camera setup
+        .add_plugins(camera::CameraPlugin)
+        .add_plugins(chat::ChatPlugin)
+        .add_plugins(controls::ControlsPlugin)
+        .add_plugins(effects::EffectsPlugin)
+        .add_plugins(ghost::GhostPlugin)
+        .add_plugins(hud::HudPlugin)
+        .add_plugins(impact::ImpactPlugin)
+        .add_plugins(items::ItemPlugin)
+        .add_plugins(leaderboard::LeaderboardPlugin)
+        .add_plugins(local_car::LocalCarPlugin)
+        .add_plugins(pause::PausePlugin)
+        .add_plugins(settings::SettingsPlugin)
+        .add_plugins(spectator::SpectatorPlugin)
+        .add_plugins(touch::TouchPlugin)
+        .add_plugins(track::TrackPlugin)
         .add_systems(OnEnter(AppState::Menu), setup_menu)
-        .add_systems(Update, (handle_input_text).run_if(in_state(AppState::Menu)))
+        .add_systems(
+            Update,
+            handle_input_text
+                .run_if(in_state(AppState::Menu).and(settings::settings_screen_closed)),
+        )
         .add_systems(OnExit(AppState::Menu), cleanup_menu)
         .add_systems(OnEnter(AppState::Game), connect_to_server)
-        .add_systems(Update, spawn_cars.run_if(in_state(AppState::Game)))
-        .add_systems(Update, update_car_labels.run_if(in_state(AppState::Game)))
-        .add_systems(Update, input_system.run_if(in_state(AppState::Game)))
+        .add_systems(OnExit(AppState::Game), leave_game)
+        .add_systems(Update, spawn_cars.run_if(showing_race))
+        .add_systems(Update, update_car_labels.run_if(showing_race))
+        // Inputs are sent once per fixed tick to match the server's input budget
+        .add_systems(
+            FixedUpdate,
+            input_system.run_if(
+                in_state(AppState::Game)
+                    .and(not(resource_exists::<spectator::Spectating>))
+                    .and(pause::pause_menu_closed),
+            ),
+        )
         .add_systems(
             Update,
-            (handle_connect, handle_disconnect, handle_join_handshake)
+            (
+                handle_connect,
+                handle_disconnect,
+                handle_join_handshake,
+                handle_server_notices,
+                receive_server_status,
+                receive_join_accepted,
+                mark_local_car,
+            )
                 .run_if(in_state(AppState::Game)),
         )
         .add_systems(Update, debug_entities)
-        .add_observer(debug_player_spawn)
-        .run();
+        .add_observer(debug_player_spawn);
+    #[cfg(target_arch = "wasm32")]
+    app.add_systems(
+        Update,
+        prompt_username
+            .before(handle_input_text)
+            .run_if(in_state(AppState::Menu).and(settings::settings_screen_closed)),
+    );
+    #[cfg(any(feature = "audio", target_arch = "wasm32"))]
+    app.add_plugins(sound::SoundPlugin);
+    app.run();
 }
 
 fn debug_player_spawn(trigger: Trigger<OnAdd, Player>, query: Query<&Player>) {
@@ -120,31 +357,104 @@ fn handle_join_handshake(
         Added<MessageSender<nfrs_shared::JoinRequest>>,
     >,
     username: Res<UsernameInput>,
+    settings: Res<settings::Settings>,
+    spectating: Option<Res<spectator::Spectating>>,
 ) {
+    let players = camera::local_players(&settings, spectating.is_some());
     for mut sender in query.iter_mut() {
-        info!("Sending JoinRequest: {}", username.0);
-        // Use InputChannel because that's the only channel we registered and it is OrderedReliable.
-        // We could create a separate channel but InputChannel works for now.
-        sender.send::<nfrs_shared::InputChannel>(nfrs_shared::JoinRequest {
-            username: username.0.clone(),
-        });
+        // Split-screen players share the connection, one request each
+        for seat in 0..players {
+            let username = match seat {
+                0 => username.0.clone(),
+                seat => guest_username(&username.0, seat),
+            };
+            info!("Sending JoinRequest: {}", username);
+            // Use InputChannel because that's the only channel we registered and it is OrderedReliable.
+            // We could create a separate channel but InputChannel works for now.
+            sender.send::<nfrs_shared::InputChannel>(nfrs_shared::JoinRequest {
+                username,
+                spectator: spectating.is_some(),
+                seat,
+            });
+        }
     }
 }
 
-fn handle_disconnect(mut removals: RemovedComponents<Connected>) {
+/// The server dropped the connection: back to the menu, which tears the race down
+fn handle_disconnect(
+    mut removals: RemovedComponents<Connected>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
     for _ in removals.read() {
         info!("Client disconnected from server!");
+        next_state.set(AppState::Menu);
     }
 }
 
-fn setup_camera(mut commands: Commands) {
-    commands.spawn((
-        Camera2d,
-        Projection::Orthographic(OrthographicProjection {
-            scale: 0.05,
-            ..OrthographicProjection::default_2d()
-        }),
-    ));
+fn handle_server_notices(
+    mut commands: Commands,
+    mut receivers: Query<(Entity, &mut MessageReceiver<ServerNotice>)>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for (client, mut receiver) in receivers.iter_mut() {
+        for notice in receiver.receive() {
+            match notice.level {
+                NoticeLevel::Info => info!("Server: {}", notice.text),
+                NoticeLevel::Warning => warn!("Server: {}", notice.text),
+                NoticeLevel::Kicked => {
+                    warn!("Server: {}", notice.text);
+                    commands.trigger_targets(Disconnect, client);
+                    next_state.set(AppState::Menu);
+                }
+            }
+        }
+    }
+}
+
+fn receive_server_status(
+    mut receivers: Query<&mut MessageReceiver<ServerStatus>>,
+    mut latest: ResMut<LatestServerStatus>,
+) {
+    for mut receiver in receivers.iter_mut() {
+        if let Some(status) = receiver.receive().last() {
+            debug!("Server status: {:?}", status);
+            latest.0 = Some(status);
+        }
+    }
+}
+
+fn receive_join_accepted(
+    mut receivers: Query<&mut MessageReceiver<JoinAccepted>>,
+    mut local: ResMut<LocalPlayer>,
+) {
+    for mut receiver in receivers.iter_mut() {
+        for accepted in receiver.receive() {
+            info!(
+                "Player {} joined as client {} on track '{}'",
+                accepted.seat + 1,
+                accepted.client_id,
+                accepted.track
+            );
+            if accepted.seat == 0 {
+                local.client_id = Some(accepted.client_id);
+            } else {
+                local.guests.insert(accepted.seat, accepted.client_id);
+            }
+            local.track = accepted.track;
+        }
+    }
+}
+
+fn mark_local_car(
+    mut commands: Commands,
+    local: Res<LocalPlayer>,
+    cars: Query<(Entity, &Player), Without<LocalCar>>,
+) {
+    for (entity, player) in cars.iter() {
+        if let Some(seat) = local.seat_of(player.client_id) {
+            commands.entity(entity).insert(LocalCar { seat });
+        }
+    }
 }
 
 // UI Components
@@ -154,10 +464,25 @@ struct MenuRoot;
 #[derive(Component)]
 struct UserInputText;
 
+/// The box showing the username, tapped to change it on phones
+#[derive(Component)]
+struct UsernameBox;
+
+#[derive(Component)]
+struct JoinButton;
+
+#[derive(Component)]
+struct ModeText;
+
 #[derive(Component)]
 struct CarLabel(Entity);
 
-fn setup_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn setup_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mode: Res<GameMode>,
+    username: Res<UsernameInput>,
+) {
     let font = asset_server.load("fonts/FiraSans-Bold.ttf");
 
     commands
@@ -200,6 +525,7 @@ fn setup_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
             // Input Box
             parent
                 .spawn((
+                    Button,
                     Node {
                         width: Val::Px(400.0),
                         height: Val::Px(60.0),
@@ -211,10 +537,12 @@ fn setup_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
                     },
                     BorderColor(Color::srgb(0.3, 0.3, 0.8)), // Blue border
                     BackgroundColor(Color::srgb(0.1, 0.1, 0.2)),
+                    UsernameBox,
                 ))
                 .with_children(|parent| {
+                    // Still filled in after leaving a race
                     parent.spawn((
-                        Text::new(""),
+                        Text::new(username.0.clone()),
                         TextFont {
                             font: font.clone(),
                             font_size: 35.0,
@@ -225,9 +553,21 @@ fn setup_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
                     ));
                 });
 
+            // Game mode
+            parent.spawn((
+                Text::new(mode_label(*mode)),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 25.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.0, 0.8, 1.0)),
+                ModeText,
+            ));
+
             // Join Instruction
             parent.spawn((
-                Text::new("Press ENTER to Join"),
+                Text::new("Press ENTER to Join, TAB to change mode"),
                 TextFont {
                     font: font.clone(),
                     font_size: 20.0,
@@ -235,9 +575,62 @@ fn setup_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
                 },
                 TextColor(Color::srgb(0.5, 0.5, 0.5)),
             ));
+
+            // Phones have no ENTER key
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        padding: UiRect::axes(Val::Px(40.0), Val::Px(12.0)),
+                        border: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    BorderColor(Color::srgb(0.0, 0.8, 1.0)),
+                    BackgroundColor(Color::srgb(0.1, 0.1, 0.2)),
+                    JoinButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new("Join"),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: 30.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                });
+
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        padding: UiRect::axes(Val::Px(20.0), Val::Px(8.0)),
+                        border: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    BorderColor(Color::srgb(0.3, 0.3, 0.8)),
+                    BackgroundColor(Color::srgb(0.1, 0.1, 0.2)),
+                    settings::OpenSettingsButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new("Settings"),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: 20.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                });
         });
 }
 
+fn mode_label(mode: GameMode) -> String {
+    format!("Mode: {}", mode.label())
+}
+
 fn cleanup_menu(mut commands: Commands, query: Query<Entity, With<MenuRoot>>) {
     // ...
     for entity in query.iter() {
@@ -339,15 +732,21 @@ fn update_car_labels(
     mut commands: Commands,
     mut label_query: Query<(Entity, &CarLabel, &mut Transform)>,
     car_query: Query<&GlobalTransform>,
+    cameras: Query<&camera::CameraRig>,
 ) {
+    // Labels stay upright on screen, even when the camera turns (the first player's, in
+    // split-screen)
+    let rotation = cameras
+        .iter()
+        .find(|rig| rig.seat == 0)
+        .map_or(Quat::IDENTITY, |rig| Quat::from_rotation_z(rig.rotation));
     for (label_entity, car_label, mut transform) in label_query.iter_mut() {
         if let Ok(car_transform) = car_query.get(car_label.0) {
             let car_pos = car_transform.translation();
             // Position label above car
             // We set Z to 10.0 to ensure it's always on top of everything
-            transform.translation = car_pos + Vec3::new(0.0, 3.5, 10.0);
-            // Force rotation to identity (straight)
-            transform.rotation = Quat::IDENTITY;
+            transform.translation = car_pos + rotation * Vec3::new(0.0, 3.5, 10.0);
+            transform.rotation = rotation;
         } else {
             // Car despawned, cleanup label
             commands.entity(label_entity).despawn();
@@ -355,37 +754,47 @@ fn update_car_labels(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_input_text(
     mut events: EventReader<bevy::input::keyboard::KeyboardInput>,
     keys: Res<ButtonInput<KeyCode>>,
+    join_buttons: Query<&Interaction, (Changed<Interaction>, With<JoinButton>)>,
     mut username: ResMut<UsernameInput>,
     mut query: Query<&mut Text, With<UserInputText>>,
+    mut mode_text: Query<&mut Text, (With<ModeText>, Without<UserInputText>)>,
+    mut mode: ResMut<GameMode>,
+    mut settings: ResMut<settings::Settings>,
     mut next_state: ResMut<NextState<AppState>>,
 ) {
-    let mut changed = false;
-
     // Handle character input
     for event in events.read() {
         if event.state.is_pressed() {
             if let Some(text) = &event.text {
                 let s = text.as_str();
                 // Filter control characters to prevent weird symbols
-                if s.chars().all(|c| !c.is_control()) {
+                if s.chars().all(|c| !c.is_control())
+                    && username.0.chars().count() + s.chars().count() <= MAX_USERNAME_LEN
+                {
                     username.0.push_str(s);
-                    changed = true;
                 }
             }
         }
     }
 
+    if keys.just_pressed(KeyCode::Tab) {
+        *mode = mode.next();
+        if let Ok(mut text) = mode_text.single_mut() {
+            text.0 = mode_label(*mode);
+        }
+    }
+
     // Handle Backspace
     if keys.just_pressed(KeyCode::Backspace) {
         username.0.pop();
-        changed = true;
     }
 
-    // Update Text UI if changed
-    if changed {
+    // Update Text UI if changed, here or by the phone prompt
+    if username.is_changed() {
         if let Ok(mut text) = query.single_mut() {
             text.0 = username.0.clone();
             // Add a blinking cursor effect or static symbol to show activity?
@@ -393,32 +802,99 @@ fn handle_input_text(
         }
     }
 
-    // Handle Enter to Join
-    if keys.just_pressed(KeyCode::Enter) && !username.0.is_empty() {
+    // Handle Enter (or the Join button) to Join
+    let join = keys.just_pressed(KeyCode::Enter)
+        || join_buttons
+            .iter()
+            .any(|interaction| *interaction == Interaction::Pressed);
+    if join && !username.0.trim().is_empty() {
         info!("Joining game with username: {}", username.0);
+        if settings.username != username.0 {
+            settings.username = username.0.clone();
+        }
         next_state.set(AppState::Game);
     }
 }
 
-fn connect_to_server(mut commands: Commands, args: Res<Args>) {
+/// Phones have no keyboard to type into the box with, so tapping it asks the browser
+#[cfg(target_arch = "wasm32")]
+fn prompt_username(
+    boxes: Query<&Interaction, (Changed<Interaction>, With<UsernameBox>)>,
+    mut username: ResMut<UsernameInput>,
+) {
+    if !boxes
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    if let Ok(Some(name)) = window.prompt_with_message_and_default("Username", &username.0) {
+        let name: String = name.trim().chars().take(MAX_USERNAME_LEN).collect();
+        if !name.is_empty() {
+            username.0 = name;
+        }
+    }
+}
+
+/// Address of the dedicated server to join
+fn remote_server_addr(args: &Args, settings: &settings::Settings) -> SocketAddr {
+    // The browser build is configured at build time and always uses WebTransport
+    if cfg!(target_arch = "wasm32") {
+        return option_env!("NFRS_SERVER_ADDR")
+            .unwrap_or("127.0.0.1:5001")
+            .parse()
+            .expect("Invalid NFRS_SERVER_ADDR format. Expected IP:PORT");
+    }
+    // Flags win over NFRS_SERVER_ADDR (at run or build time), which wins over the settings
+    if args.ip.is_none() && args.port.is_none() {
+        let env = std::env::var("NFRS_SERVER_ADDR")
+            .ok()
+            .or(option_env!("NFRS_SERVER_ADDR").map(str::to_string));
+        if let Some(addr) = env {
+            return addr
+                .parse()
+                .expect("Invalid NFRS_SERVER_ADDR format. Expected IP:PORT");
+        }
+    }
+    let ip = match &args.ip {
+        Some(ip) => ip
+            .parse()
+            .expect("Invalid --ip format. Expected an IP address"),
+        None => settings
+            .server_ip
+            .parse()
+            .expect("saved server addresses are checked when the settings are loaded"),
+    };
+    SocketAddr::new(ip, args.port.unwrap_or(settings.server_port))
+}
+
+fn connect_to_server(
+    mut commands: Commands,
+    args: Res<Args>,
+    mode: Res<GameMode>,
+    settings: Res<settings::Settings>,
+) {
     let client_id = rand::random::<u64>();
-    // Use build-time configured address or fallback to WSL IP
-    let server_addr_str = option_env!("NFRS_SERVER_ADDR").unwrap_or("127.0.0.1:5001");
-    // Override with args if provided and not default localhost (though arg default is localhost)
-    let final_addr = if args.ip != "127.0.0.1" {
-        // This logic is a bit flawed if we want to support args overriding env.
-        // But for now let's use the logic: if args.ip is set, use it with port 5001?
-        // The original code just used args for nothing?
-        // Ah, original code had `args` unused in setup_client basically.
-        // Let's stick to the simpler env var or hardcode for now to minimize risk.
-        server_addr_str
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let server_addr = if *mode == GameMode::Online {
+        remote_server_addr(&args, &settings)
     } else {
-        server_addr_str
+        let server = embedded::start(*mode, args.bots, args.bot_difficulty);
+        let addr = server.addr;
+        commands.insert_resource(server);
+        addr
+    };
+    #[cfg(target_arch = "wasm32")]
+    let server_addr = {
+        if *mode != GameMode::Online {
+            warn!("The browser build can only play online");
+        }
+        remote_server_addr(&args, &settings)
     };
-
-    let server_addr: SocketAddr = final_addr
-        .parse()
-        .expect("Invalid NFRS_SERVER_ADDR format. Expected IP:PORT");
 
     let client_addr = SocketAddr::new(Ipv4Addr::new(0, 0, 0, 0).into(), 0);
 
@@ -467,36 +943,84 @@ fn connect_to_server(mut commands: Commands, args: Res<Args>) {
         .entity(client)
         .insert(MessageSender::<CarInput>::default());
 
+    // Add receivers for server notices, impact effects and status reports
+    commands.entity(client).insert((
+        MessageReceiver::<ServerNotice>::default(),
+        MessageReceiver::<ImpactEvent>::default(),
+        MessageReceiver::<ServerStatus>::default(),
+    ));
+
+    // Join confirmation and ghost exchange
+    commands.entity(client).insert((
+        MessageReceiver::<JoinAccepted>::default(),
+        MessageSender::<GhostUpload>::default(),
+        MessageSender::<GhostRequest>::default(),
+        MessageReceiver::<GhostDownload>::default(),
+    ));
+
+    // Leaderboard queries
+    commands.entity(client).insert((
+        MessageSender::<LeaderboardRequest>::default(),
+        MessageReceiver::<Leaderboard>::default(),
+    ));
+
+    // Chat
+    commands.entity(client).insert((
+        MessageSender::<ChatSend>::default(),
+        MessageReceiver::<ChatLine>::default(),
+    ));
+
     // Start the link first
     commands.entity(client).trigger(LinkStart);
     // Then start the connection
     commands.entity(client).trigger(Connect);
 }
 
+/// Tear down the connection and everything it replicated, ready to join again
+fn leave_game(
+    mut commands: Commands,
+    clients: Query<Entity, With<Client>>,
+    replicated: Query<Entity, With<Replicated>>,
+    labels: Query<Entity, With<CarLabel>>,
+    mut local: ResMut<LocalPlayer>,
+    mut status: ResMut<LatestServerStatus>,
+) {
+    for entity in clients.iter().chain(replicated.iter()).chain(labels.iter()) {
+        commands.entity(entity).try_despawn();
+    }
+    *local = LocalPlayer::default();
+    status.0 = None;
+    // Stops the in-process server, if there is one
+    #[cfg(not(target_arch = "wasm32"))]
+    commands.remove_resource::<embedded::EmbeddedServer>();
+}
+
 fn input_system(
     mut input_sender: Query<&mut MessageSender<CarInput>>,
-    keyboard: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
+    local: Res<LocalPlayer>,
 ) {
-    if let Ok(mut sender) = input_sender.single_mut() {
-        let mut input = CarInput::default();
-
-        if keyboard.pressed(KeyCode::KeyW) {
-            input.forward = true;
-        }
-        if keyboard.pressed(KeyCode::KeyS) {
-            input.backward = true;
-        }
-        if keyboard.pressed(KeyCode::KeyA) {
-            input.left = true;
-        }
-        if keyboard.pressed(KeyCode::KeyD) {
-            input.right = true;
-        }
+    let Ok(mut sender) = input_sender.single_mut() else {
+        return;
+    };
+    // Every player on this screen whose join was accepted
+    let seats = local.client_id.map(|_| 0).into_iter();
+    for seat in seats.chain(local.guests.keys().copied()) {
+        let actions = actions.player(seat);
+        // Keys give full throttle and lock, triggers and sticks anything in between
+        let input = CarInput {
+            throttle: actions.value(Action::Throttle) - actions.value(Action::Brake),
+            steering: actions.value(Action::SteerRight) - actions.value(Action::SteerLeft),
+            handbrake: actions.pressed(Action::Handbrake),
+            use_item: actions.pressed(Action::UseItem),
+            boost: actions.pressed(Action::Boost),
+            seat,
+            ..default()
+        };
 
         // Only send if any key is pressed
-        if input.forward || input.backward || input.left || input.right {
-            // println!("Sending input: {:?}", input);
-            info!("Sending input: {:?}", input);
+        if input != (CarInput { seat, ..default() }) {
+            debug!("Sending input: {:?}", input);
             sender.send::<InputChannel>(input);
         }
     }
@@ -505,7 +1029,7 @@ fn input_system(
 fn debug_entities(query: Query<Entity>, player_query: Query<&Player>, time: Res<Time>) {
     // Log every 5 seconds using elapsed_secs as integer
     let elapsed = time.elapsed_secs() as u32;
-    if elapsed % 5 == 0 && time.delta_secs() < 0.1 {
+    if elapsed.is_multiple_of(5) && time.delta_secs() < 0.1 {
         info!(
             "Total entities: {}, Player entities: {}",
             query.iter().count(),
@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+use lightyear::prelude::*;
+use tracing::info;
+
+use crate::controls::{controls_screen_closed, Action, ActionState};
+use crate::settings::{spawn_settings, Settings};
+use crate::AppState;
+
+const BUTTON_COLOR: Color = Color::srgb(0.1, 0.1, 0.2);
+const BUTTON_HOVER_COLOR: Color = Color::srgb(0.2, 0.2, 0.4);
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum PausePage {
+    #[default]
+    Main,
+    Settings,
+}
+
+/// In-game menu opened with ESC. The race goes on underneath it.
+#[derive(Resource, Default)]
+pub struct PauseMenu {
+    open: bool,
+    page: PausePage,
+}
+
+/// Run condition for systems that must ignore the player while the menu is open
+pub fn pause_menu_closed(menu: Res<PauseMenu>) -> bool {
+    !menu.open
+}
+
+#[derive(Component)]
+struct PauseRoot;
+
+#[derive(Component, Clone, Copy)]
+enum PauseButton {
+    Resume,
+    Settings,
+    Back,
+    Leave,
+    Quit,
+}
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PauseMenu>();
+        app.add_systems(
+            Update,
+            (
+                toggle_pause_menu.run_if(controls_screen_closed),
+                press_pause_buttons,
+                draw_pause_menu,
+            )
+                .chain()
+                .run_if(in_state(AppState::Game)),
+        );
+        app.add_systems(OnExit(AppState::Game), close_pause_menu);
+    }
+}
+
+/// ESC (or whatever the menu action is bound to, for any local player) opens and closes
+/// the menu, or goes back from the settings
+fn toggle_pause_menu(actions: Res<ActionState>, mut menu: ResMut<PauseMenu>) {
+    if !actions.any_just_pressed(Action::Pause) {
+        return;
+    }
+    if menu.open && menu.page == PausePage::Settings {
+        menu.page = PausePage::Main;
+    } else {
+        menu.open = !menu.open;
+        menu.page = PausePage::Main;
+    }
+}
+
+fn close_pause_menu(mut menu: ResMut<PauseMenu>) {
+    *menu = PauseMenu::default();
+}
+
+fn press_pause_buttons(
+    mut commands: Commands,
+    mut buttons: Query<(&Interaction, &PauseButton, &mut BackgroundColor), Changed<Interaction>>,
+    mut menu: ResMut<PauseMenu>,
+    clients: Query<Entity, With<Client>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for (interaction, button, mut color) in buttons.iter_mut() {
+        match interaction {
+            Interaction::Pressed => {}
+            Interaction::Hovered => {
+                color.0 = BUTTON_HOVER_COLOR;
+                continue;
+            }
+            Interaction::None => {
+                color.0 = BUTTON_COLOR;
+                continue;
+            }
+        }
+        match button {
+            PauseButton::Resume => menu.open = false,
+            PauseButton::Settings => menu.page = PausePage::Settings,
+            PauseButton::Back => menu.page = PausePage::Main,
+            PauseButton::Leave | PauseButton::Quit => {
+                // Tell the server we are going, rather than letting the connection time out.
+                // The disconnect packets go out this frame; the client itself is torn down
+                // when leaving the Game state.
+                for client in clients.iter() {
+                    commands.trigger_targets(Disconnect, client);
+                }
+                if matches!(button, PauseButton::Quit) {
+                    info!("Quitting");
+                    exit.write(AppExit::Success);
+                } else {
+                    info!("Leaving the race");
+                    next_state.set(AppState::Menu);
+                }
+            }
+        }
+    }
+}
+
+/// Rebuild the menu whenever it opens, closes, changes page or shows new settings
+fn draw_pause_menu(
+    mut commands: Commands,
+    menu: Res<PauseMenu>,
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    roots: Query<Entity, With<PauseRoot>>,
+) {
+    if !menu.is_changed() && !settings.is_changed() {
+        return;
+    }
+    for root in roots.iter() {
+        commands.entity(root).despawn();
+    }
+    if !menu.open {
+        return;
+    }
+
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_font = |size| TextFont {
+        font: font.clone(),
+        font_size: size,
+        ..default()
+    };
+    let button = |parent: &mut ChildSpawnerCommands, kind: PauseButton, label: &str| {
+        parent
+            .spawn((
+                Button,
+                Node {
+                    width: Val::Px(320.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                BackgroundColor(BUTTON_COLOR),
+                kind,
+            ))
+            .with_children(|parent| {
+                parent.spawn((Text::new(label), text_font(24.0), TextColor(Color::WHITE)));
+            });
+    };
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+            // Above the HUD and leaderboard
+            GlobalZIndex(10),
+            PauseRoot,
+            StateScoped(AppState::Game),
+        ))
+        .with_children(|parent| {
+            let title = match menu.page {
+                PausePage::Main => "Paused",
+                PausePage::Settings => "Settings",
+            };
+            parent.spawn((
+                Text::new(title),
+                text_font(48.0),
+                TextColor(Color::srgb(0.0, 0.8, 1.0)),
+            ));
+            match menu.page {
+                PausePage::Main => {
+                    parent.spawn((
+                        Text::new("The race goes on while this menu is open"),
+                        text_font(16.0),
+                        TextColor(Color::srgb(0.5, 0.5, 0.5)),
+                    ));
+                    button(parent, PauseButton::Resume, "Resume");
+                    button(parent, PauseButton::Settings, "Settings");
+                    button(parent, PauseButton::Leave, "Leave race");
+                    button(parent, PauseButton::Quit, "Quit");
+                }
+                PausePage::Settings => {
+                    spawn_settings(parent, &font, &settings);
+                    button(parent, PauseButton::Back, "Back");
+                }
+            }
+        });
+}
@@ -0,0 +1,358 @@
+use bevy::prelude::*;
+use bevy::ui::RelativeCursorPosition;
+use nfrs_shared::{CarInput, Replay, SurfaceRegion, TrackLayout};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tracing::{error, info};
+
+use crate::camera::CameraRig;
+use crate::AppState;
+
+const SKIP_SECS: f32 = 5.0;
+const MIN_SPEED: f32 = 0.125;
+const MAX_SPEED: f32 = 8.0;
+// Camera zoom for the whole track, and when following a car
+const OVERVIEW_SCALE: f32 = 0.05;
+const FOLLOW_SCALE: f32 = 0.03;
+
+/// Replay file to open at startup
+#[derive(Resource)]
+pub struct ReplayFile(pub PathBuf);
+
+/// Playback position and controls of the open replay
+#[derive(Resource)]
+struct Playback {
+    replay: Replay,
+    /// Ground the cars are simulated on, as (center, region) pairs
+    surfaces: Vec<(Vec2, SurfaceRegion)>,
+    /// Current position, in (fractional) ticks
+    tick: f32,
+    speed: f32,
+    paused: bool,
+    /// Car the camera follows; `None` shows the whole track
+    follow: Option<u16>,
+}
+
+/// Stand-in for a car during playback, drawn like a replicated car
+#[derive(Component)]
+struct ReplayCarEntity(u16);
+
+#[derive(Component)]
+struct ReplayUi;
+
+#[derive(Component)]
+struct PlaybackText;
+
+#[derive(Component)]
+struct Timeline;
+
+#[derive(Component)]
+struct TimelineFill;
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Replay), (open_replay, setup_replay_ui));
+        app.add_systems(
+            Update,
+            (
+                playback_controls,
+                scrub_timeline,
+                advance_playback,
+                update_replay_cars,
+                follow_car,
+                update_replay_ui,
+            )
+                .chain()
+                .run_if(in_state(AppState::Replay).and(resource_exists::<Playback>)),
+        );
+    }
+}
+
+fn open_replay(
+    mut commands: Commands,
+    file: Res<ReplayFile>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let replay = std::fs::read(&file.0)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| Replay::decode(&bytes));
+    let replay = match replay {
+        Ok(replay) => replay,
+        Err(e) => {
+            error!("Could not open replay {}: {}", file.0.display(), e);
+            next_state.set(AppState::Menu);
+            return;
+        }
+    };
+    info!(
+        "Playing replay of {:.0} s with {} cars on '{}'",
+        replay.duration_secs(),
+        replay.cars.len(),
+        replay.track
+    );
+
+    // Surfaces are drawn by the track plugin, as if they had been replicated
+    let surfaces = match TrackLayout::by_name(&replay.track) {
+        Some(track) => track.surfaces,
+        None => {
+            error!("Unknown track '{}' in replay", replay.track);
+            Vec::new()
+        }
+    };
+    for (center, region) in &surfaces {
+        commands.spawn((
+            region.clone(),
+            Transform::from_translation(center.extend(0.0)),
+        ));
+    }
+
+    commands.insert_resource(Playback {
+        replay,
+        surfaces,
+        tick: 0.0,
+        speed: 1.0,
+        paused: false,
+        follow: None,
+    });
+}
+
+fn setup_replay_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.0),
+                width: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(6.0),
+                ..default()
+            },
+            ReplayUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 18.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                PlaybackText,
+            ));
+            parent.spawn((
+                Text::new(
+                    "SPACE pause  LEFT/RIGHT skip  UP/DOWN speed  TAB follow car  ESC overview",
+                ),
+                TextFont {
+                    font,
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.6, 0.6, 0.6)),
+            ));
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Percent(90.0),
+                        height: Val::Px(12.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(1.0, 1.0, 1.0, 0.2)),
+                    Button,
+                    RelativeCursorPosition::default(),
+                    Timeline,
+                ))
+                .with_children(|bar| {
+                    bar.spawn((
+                        Node {
+                            width: Val::Percent(0.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.0, 0.8, 1.0)),
+                        TimelineFill,
+                    ));
+                });
+        });
+}
+
+fn playback_controls(keys: Res<ButtonInput<KeyCode>>, mut playback: ResMut<Playback>) {
+    let skip = SKIP_SECS * playback.replay.tick_rate;
+    let end = playback.replay.last_tick() as f32;
+    if keys.just_pressed(KeyCode::Space) {
+        playback.paused = !playback.paused;
+    }
+    if keys.just_pressed(KeyCode::ArrowLeft) {
+        playback.tick = (playback.tick - skip).max(0.0);
+    }
+    if keys.just_pressed(KeyCode::ArrowRight) {
+        playback.tick = (playback.tick + skip).min(end);
+    }
+    if keys.just_pressed(KeyCode::ArrowUp) {
+        playback.speed = (playback.speed * 2.0).min(MAX_SPEED);
+    }
+    if keys.just_pressed(KeyCode::ArrowDown) {
+        playback.speed = (playback.speed / 2.0).max(MIN_SPEED);
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        playback.follow = None;
+    }
+    if keys.just_pressed(KeyCode::Tab) {
+        // Cycle through the cars on track at the moment
+        let mut on_track: Vec<u16> = playback
+            .replay
+            .sample(&playback.surfaces, playback.tick)
+            .iter()
+            .map(|state| state.car)
+            .collect();
+        on_track.sort_unstable();
+        playback.follow = match playback.follow {
+            Some(current) => on_track.into_iter().find(|car| *car > current),
+            None => on_track.first().copied(),
+        };
+    }
+}
+
+/// Jump to the clicked (or dragged) point of the timeline
+fn scrub_timeline(
+    timeline: Query<(&Interaction, &RelativeCursorPosition), With<Timeline>>,
+    mut playback: ResMut<Playback>,
+) {
+    for (interaction, cursor) in timeline.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some(position) = cursor.normalized {
+            playback.tick = position.x.clamp(0.0, 1.0) * playback.replay.last_tick() as f32;
+        }
+    }
+}
+
+fn advance_playback(time: Res<Time>, mut playback: ResMut<Playback>) {
+    if playback.paused {
+        return;
+    }
+    let end = playback.replay.last_tick() as f32;
+    playback.tick += time.delta_secs() * playback.replay.tick_rate * playback.speed;
+    if playback.tick >= end {
+        playback.tick = end;
+        playback.paused = true;
+    }
+}
+
+/// Move the stand-in cars to their simulated state, spawning and removing them as they
+/// join and leave the recording
+fn update_replay_cars(
+    mut commands: Commands,
+    playback: Res<Playback>,
+    mut cars: Query<(Entity, &ReplayCarEntity, &mut Transform)>,
+) {
+    let mut states: HashMap<u16, _> = playback
+        .replay
+        .sample(&playback.surfaces, playback.tick)
+        .into_iter()
+        .map(|state| (state.car, state))
+        .collect();
+
+    for (entity, car, mut transform) in cars.iter_mut() {
+        match states.remove(&car.0) {
+            Some(state) => {
+                transform.translation = state.position.extend(0.0);
+                transform.rotation = Quat::from_rotation_z(state.rotation);
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+
+    for (car, state) in states {
+        let Some(recorded) = playback.replay.cars.get(car as usize) else {
+            continue;
+        };
+        commands.spawn((
+            ReplayCarEntity(car),
+            recorded.player.clone(),
+            Transform::from_translation(state.position.extend(0.0))
+                .with_rotation(Quat::from_rotation_z(state.rotation)),
+            Visibility::default(),
+        ));
+    }
+}
+
+fn follow_car(
+    playback: Res<Playback>,
+    cars: Query<(&ReplayCarEntity, &Transform)>,
+    mut rigs: Query<&mut CameraRig>,
+) {
+    let target = playback.follow.and_then(|follow| {
+        cars.iter()
+            .find(|(car, _)| car.0 == follow)
+            .map(|(_, transform)| transform.translation.truncate())
+    });
+    for mut rig in rigs.iter_mut() {
+        rig.position = target.unwrap_or(Vec2::ZERO);
+        rig.scale = if target.is_some() {
+            FOLLOW_SCALE
+        } else {
+            OVERVIEW_SCALE
+        };
+    }
+}
+
+fn update_replay_ui(
+    playback: Res<Playback>,
+    mut text: Query<&mut Text, With<PlaybackText>>,
+    mut fill: Query<&mut Node, With<TimelineFill>>,
+) {
+    let replay = &playback.replay;
+    let end = replay.last_tick().max(1) as f32;
+    if let Ok(mut node) = fill.single_mut() {
+        node.width = Val::Percent(playback.tick / end * 100.0);
+    }
+
+    let Ok(mut text) = text.single_mut() else {
+        return;
+    };
+    let mut line = format!(
+        "{:.1} / {:.1} s   x{}{}",
+        playback.tick / replay.tick_rate,
+        replay.duration_secs(),
+        playback.speed,
+        if playback.paused { "   paused" } else { "" }
+    );
+    if let Some(car) = playback.follow.and_then(|id| replay.cars.get(id as usize)) {
+        let input = replay.input_at(car.id, playback.tick as u32);
+        line.push_str(&format!(
+            "   following {} [{}]",
+            car.player.username,
+            describe_input(input)
+        ));
+    }
+    text.0 = line;
+}
+
+fn describe_input(input: CarInput) -> String {
+    let throttle = input.throttle_axis();
+    let steering = input.steering_axis();
+    let pressed: Vec<&str> = [
+        (throttle > 0.0, "throttle"),
+        (throttle < 0.0, "brake"),
+        (steering < 0.0, "left"),
+        (steering > 0.0, "right"),
+        (input.handbrake, "handbrake"),
+        (input.boost, "nitro"),
+        (input.use_item, "item"),
+    ]
+    .into_iter()
+    .filter_map(|(held, name)| held.then_some(name))
+    .collect();
+    if pressed.is_empty() {
+        "coasting".to_string()
+    } else {
+        pressed.join(" ")
+    }
+}
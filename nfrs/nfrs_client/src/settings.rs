@@ -0,0 +1,499 @@
+use bevy::input::keyboard::KeyboardInput;
+use bevy::prelude::*;
+use bevy::window::{MonitorSelection, PresentMode, PrimaryWindow, WindowMode};
+use nfrs_shared::MAX_LOCAL_PLAYERS;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use tracing::{info, warn};
+
+use crate::camera::DriverCamera;
+use crate::controls::{controls_screen_closed, Bindings, ControlsScreen};
+use crate::AppState;
+
+const ROW_COLOR: Color = Color::srgb(0.1, 0.1, 0.2);
+const ROW_HOVER_COLOR: Color = Color::srgb(0.2, 0.2, 0.4);
+#[cfg(not(target_arch = "wasm32"))]
+const SETTINGS_FILE: &str = "settings.ron";
+#[cfg(target_arch = "wasm32")]
+const STORAGE_KEY: &str = "nfrs.settings";
+
+/// Everything the player can change, kept between launches: in `settings.ron` next to the
+/// game, or in the browser's local storage
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    /// Last name joined with
+    pub username: String,
+    /// Server to join online, unless `--ip`/`--port` are given
+    pub server_ip: String,
+    pub server_port: u16,
+    pub camera: DriverCamera,
+    pub screen_shake: bool,
+    pub show_hud: bool,
+    /// 0 to 1
+    pub volume: f32,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    /// Players on this screen, in split-screen when more than one
+    pub local_players: u8,
+    /// Bindings of the first player
+    pub bindings: Bindings,
+    /// Bindings of players 2 and up
+    pub guest_bindings: Vec<Bindings>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            username: String::new(),
+            server_ip: "127.0.0.1".to_string(),
+            server_port: 5000,
+            camera: DriverCamera::default(),
+            screen_shake: true,
+            show_hud: true,
+            volume: 0.8,
+            fullscreen: false,
+            vsync: true,
+            local_players: 1,
+            bindings: Bindings::default(),
+            guest_bindings: (1..MAX_LOCAL_PLAYERS).map(Bindings::for_player).collect(),
+        }
+    }
+}
+
+impl Settings {
+    fn server_addr(&self) -> String {
+        format!("{}:{}", self.server_ip, self.server_port)
+    }
+
+    pub fn player_bindings(&self, seat: u8) -> &Bindings {
+        match seat {
+            0 => &self.bindings,
+            seat => &self.guest_bindings[seat as usize - 1],
+        }
+    }
+
+    pub fn player_bindings_mut(&mut self, seat: u8) -> &mut Bindings {
+        match seat {
+            0 => &mut self.bindings,
+            seat => &mut self.guest_bindings[seat as usize - 1],
+        }
+    }
+
+    /// Fix up hand-edited and older files: a server address that is not an IP address
+    /// goes back to the default, and every local player gets bindings for every action
+    fn validated(mut self) -> Self {
+        if self.server_ip.parse::<IpAddr>().is_err() {
+            warn!(
+                "Saved server '{}' is not an IP address, using the default",
+                self.server_ip
+            );
+            self.server_ip = Settings::default().server_ip;
+        }
+        self.local_players = self.local_players.clamp(1, MAX_LOCAL_PLAYERS);
+        self.guest_bindings.truncate(MAX_LOCAL_PLAYERS as usize - 1);
+        for seat in self.guest_bindings.len() as u8 + 1..MAX_LOCAL_PLAYERS {
+            self.guest_bindings.push(Bindings::for_player(seat));
+        }
+        for seat in 0..MAX_LOCAL_PLAYERS {
+            self.player_bindings_mut(seat)
+                .add_missing(Bindings::for_player(seat));
+        }
+        self
+    }
+}
+
+/// A row of the settings screen; clicking it changes the option
+#[derive(Component, Clone, Copy)]
+enum SettingButton {
+    Server,
+    LocalPlayers,
+    Camera,
+    ScreenShake,
+    Hud,
+    Volume,
+    Fullscreen,
+    Vsync,
+    Controls,
+    Back,
+}
+
+/// Button in the main menu that opens the settings
+#[derive(Component)]
+pub struct OpenSettingsButton;
+
+/// The settings screen of the main menu
+#[derive(Resource, Default)]
+pub struct SettingsScreen {
+    open: bool,
+    /// Server address being typed, until ENTER
+    editing_server: Option<String>,
+}
+
+#[derive(Component)]
+struct SettingsScreenRoot;
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SettingsScreen>();
+        app.add_systems(
+            Update,
+            (change_settings, save_settings, apply_window_settings),
+        );
+        app.add_systems(
+            Update,
+            (
+                open_settings_screen.run_if(controls_screen_closed),
+                edit_server_address,
+                draw_settings_screen,
+            )
+                .chain()
+                .run_if(in_state(AppState::Menu)),
+        );
+        app.add_systems(OnExit(AppState::Menu), close_settings_screen);
+    }
+}
+
+/// Run condition for the main menu's own keyboard handling
+pub fn settings_screen_closed(screen: Res<SettingsScreen>) -> bool {
+    !screen.open
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load() -> Settings {
+    match std::fs::read_to_string(SETTINGS_FILE) {
+        Ok(text) => ron::from_str(&text)
+            .map(Settings::validated)
+            .unwrap_or_else(|e| {
+                warn!("Ignoring unreadable {}: {}", SETTINGS_FILE, e);
+                Settings::default()
+            }),
+        Err(_) => Settings::default(),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save(settings: &Settings) {
+    let result = ron::ser::to_string_pretty(settings, ron::ser::PrettyConfig::default())
+        .map_err(|e| e.to_string())
+        .and_then(|text| std::fs::write(SETTINGS_FILE, text).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        warn!("Failed to save settings to {}: {}", SETTINGS_FILE, e);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load() -> Settings {
+    let Some(text) = local_storage().and_then(|storage| storage.get_item(STORAGE_KEY).ok()?) else {
+        return Settings::default();
+    };
+    ron::from_str(&text)
+        .map(Settings::validated)
+        .unwrap_or_else(|e| {
+            warn!("Ignoring unreadable saved settings: {}", e);
+            Settings::default()
+        })
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save(settings: &Settings) {
+    let Some(storage) = local_storage() else {
+        warn!("No local storage to save settings in");
+        return;
+    };
+    match ron::to_string(settings) {
+        Ok(text) => {
+            if storage.set_item(STORAGE_KEY, &text).is_err() {
+                warn!("Failed to save settings to local storage");
+            }
+        }
+        Err(e) => warn!("Failed to save settings: {}", e),
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    if settings.is_changed() && !settings.is_added() {
+        save(&settings);
+    }
+}
+
+fn apply_window_settings(
+    settings: Res<Settings>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    let Ok(mut window) = windows.single_mut() else {
+        return;
+    };
+    let mode = if settings.fullscreen {
+        WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+    } else {
+        WindowMode::Windowed
+    };
+    let present_mode = if settings.vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    };
+    if window.mode != mode {
+        window.mode = mode;
+    }
+    if window.present_mode != present_mode {
+        window.present_mode = present_mode;
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+fn setting_row(
+    parent: &mut ChildSpawnerCommands,
+    font: &Handle<Font>,
+    button: SettingButton,
+    label: String,
+) {
+    parent
+        .spawn((
+            Button,
+            Node {
+                width: Val::Px(320.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            BackgroundColor(ROW_COLOR),
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(label),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 22.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+/// Add the rows of settings that can be changed during a race to a menu
+pub fn spawn_settings(parent: &mut ChildSpawnerCommands, font: &Handle<Font>, settings: &Settings) {
+    let rows = [
+        (
+            SettingButton::Camera,
+            format!("Camera: {}", settings.camera.label()),
+        ),
+        (
+            SettingButton::ScreenShake,
+            format!("Screen shake: {}", on_off(settings.screen_shake)),
+        ),
+        (
+            SettingButton::Hud,
+            format!("HUD: {}", on_off(settings.show_hud)),
+        ),
+        (
+            SettingButton::Volume,
+            format!("Volume: {:.0}%", settings.volume * 100.0),
+        ),
+        (
+            SettingButton::Fullscreen,
+            format!("Fullscreen: {}", on_off(settings.fullscreen)),
+        ),
+        (
+            SettingButton::Vsync,
+            format!("VSync: {}", on_off(settings.vsync)),
+        ),
+    ];
+    for (button, label) in rows {
+        setting_row(parent, font, button, label);
+    }
+    setting_row(
+        parent,
+        font,
+        SettingButton::Controls,
+        "Controls...".to_string(),
+    );
+}
+
+fn change_settings(
+    mut buttons: Query<(&Interaction, &SettingButton, &mut BackgroundColor), Changed<Interaction>>,
+    mut settings: ResMut<Settings>,
+    mut screen: ResMut<SettingsScreen>,
+    mut controls: ResMut<ControlsScreen>,
+) {
+    for (interaction, button, mut color) in buttons.iter_mut() {
+        match interaction {
+            Interaction::Pressed => match button {
+                SettingButton::Server => screen.editing_server = Some(settings.server_addr()),
+                SettingButton::LocalPlayers => {
+                    settings.local_players = settings.local_players % MAX_LOCAL_PLAYERS + 1
+                }
+                SettingButton::Camera => settings.camera = settings.camera.next(),
+                SettingButton::ScreenShake => settings.screen_shake = !settings.screen_shake,
+                SettingButton::Hud => settings.show_hud = !settings.show_hud,
+                // Steps of 10%, back to silent after full volume
+                SettingButton::Volume => {
+                    let step = (settings.volume * 10.0).round() as u32 + 1;
+                    settings.volume = (step % 11) as f32 / 10.0;
+                }
+                SettingButton::Fullscreen => settings.fullscreen = !settings.fullscreen,
+                SettingButton::Vsync => settings.vsync = !settings.vsync,
+                SettingButton::Controls => controls.open(),
+                SettingButton::Back => *screen = SettingsScreen::default(),
+            },
+            Interaction::Hovered => color.0 = ROW_HOVER_COLOR,
+            Interaction::None => color.0 = ROW_COLOR,
+        }
+    }
+}
+
+fn open_settings_screen(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<OpenSettingsButton>)>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut screen: ResMut<SettingsScreen>,
+) {
+    if buttons
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        screen.open = true;
+    }
+    if screen.open && keys.just_pressed(KeyCode::Escape) {
+        *screen = SettingsScreen::default();
+    }
+}
+
+fn close_settings_screen(mut screen: ResMut<SettingsScreen>) {
+    *screen = SettingsScreen::default();
+}
+
+/// Type a new server address; ENTER keeps it if it is a valid `ip:port`
+fn edit_server_address(
+    mut events: EventReader<KeyboardInput>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut screen: ResMut<SettingsScreen>,
+    mut settings: ResMut<Settings>,
+) {
+    // Read-only until something changes, so the screen is only redrawn when it does
+    let Some(mut edited) = screen.editing_server.clone() else {
+        events.clear();
+        return;
+    };
+    for event in events.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+        if let Some(typed) = &event.text {
+            if typed.chars().all(|c| !c.is_control()) {
+                edited.push_str(typed);
+            }
+        }
+    }
+    if keys.just_pressed(KeyCode::Backspace) {
+        edited.pop();
+    }
+    if screen.editing_server.as_ref() != Some(&edited) {
+        screen.editing_server = Some(edited);
+    }
+    if !keys.just_pressed(KeyCode::Enter) {
+        return;
+    }
+
+    let edited = screen.editing_server.take().unwrap_or_default();
+    match edited.parse::<SocketAddr>() {
+        Ok(addr) => {
+            info!("Server address set to {}", addr);
+            settings.server_ip = addr.ip().to_string();
+            settings.server_port = addr.port();
+        }
+        Err(_) => warn!("'{}' is not a valid ip:port address", edited),
+    }
+}
+
+fn draw_settings_screen(
+    mut commands: Commands,
+    screen: Res<SettingsScreen>,
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    roots: Query<Entity, With<SettingsScreenRoot>>,
+) {
+    if !screen.is_changed() && !settings.is_changed() {
+        return;
+    }
+    for root in roots.iter() {
+        commands.entity(root).despawn();
+    }
+    if !screen.open {
+        return;
+    }
+
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let server = match &screen.editing_server {
+        Some(text) => format!("Server: {}_", text),
+        None => format!("Server: {}", settings.server_addr()),
+    };
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgb(0.05, 0.05, 0.1)),
+            // Above the main menu
+            GlobalZIndex(10),
+            SettingsScreenRoot,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Settings"),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 48.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.0, 0.8, 1.0)),
+            ));
+            setting_row(parent, &font, SettingButton::Server, server);
+            setting_row(
+                parent,
+                &font,
+                SettingButton::LocalPlayers,
+                match settings.local_players {
+                    1 => "Players: 1".to_string(),
+                    players => format!("Players: {} (split-screen)", players),
+                },
+            );
+            spawn_settings(parent, &font, &settings);
+            setting_row(parent, &font, SettingButton::Back, "Back".to_string());
+            parent.spawn((
+                Text::new("Click the server to type a new ip:port, then press ENTER"),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.5, 0.5, 0.5)),
+            ));
+        });
+}
@@ -0,0 +1,381 @@
+use bevy::audio::{
+    AddAudioSource, AudioSinkPlayback, Decodable, DefaultSpatialScale, Source, SpatialAudioSink,
+    SpatialListener, SpatialScale, Volume,
+};
+use bevy::prelude::*;
+use nfrs_shared::{Car, CarVelocity, ImpactKind, RaceProgress, StartCountdown, RACE_LAPS};
+use rand::Rng;
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::camera::CameraRig;
+use crate::effects::sideways_speed;
+use crate::impact::ImpactReceived;
+use crate::settings::Settings;
+use crate::LocalCar;
+
+const SAMPLE_RATE: u32 = 22050;
+// World units heard at full volume; sounds fade with the square of the distance beyond it
+const HEARING_DISTANCE: f32 = 12.0;
+// Distance between the listener's ears, in world units
+const EAR_GAP: f32 = 4.0;
+// Engine pitch at rest and at FULL_PITCH_SPEED, as playback speeds
+const IDLE_PITCH: f32 = 0.6;
+const FULL_PITCH: f32 = 2.0;
+const FULL_PITCH_SPEED: f32 = 25.0;
+const IDLE_VOLUME: f32 = 0.25;
+const FULL_THROTTLE_VOLUME: f32 = 0.5;
+// Sideways speed (m/s) where tires start to squeal, and where they are loudest
+const SKID_START: f32 = 3.0;
+const SKID_FULL: f32 = 10.0;
+const SKID_VOLUME: f32 = 0.4;
+// Impact strength (m/s of velocity change) of the loudest crash
+const FULL_IMPACT_STRENGTH: f32 = 15.0;
+
+/// A sound made in code, so the game ships no audio files. Mono samples in -1..=1.
+#[derive(Asset, TypePath, Clone)]
+struct Synth {
+    samples: Arc<[f32]>,
+}
+
+impl Synth {
+    /// `seconds` of sound, with each sample computed from its time
+    fn new(seconds: f32, wave: impl FnMut(f32) -> f32) -> Self {
+        let count = (seconds * SAMPLE_RATE as f32) as usize;
+        Self {
+            samples: (0..count)
+                .map(|i| i as f32 / SAMPLE_RATE as f32)
+                .map(wave)
+                .collect(),
+        }
+    }
+}
+
+struct SynthDecoder {
+    samples: Arc<[f32]>,
+    position: usize,
+}
+
+impl Iterator for SynthDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.samples.get(self.position).copied();
+        self.position += 1;
+        sample
+    }
+}
+
+impl Source for SynthDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(
+            self.samples.len() as f32 / SAMPLE_RATE as f32,
+        ))
+    }
+}
+
+impl Decodable for Synth {
+    type DecoderItem = f32;
+    type Decoder = SynthDecoder;
+
+    fn decoder(&self) -> SynthDecoder {
+        SynthDecoder {
+            samples: self.samples.clone(),
+            position: 0,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct Sounds {
+    engine: Handle<Synth>,
+    skid: Handle<Synth>,
+    impact: Handle<Synth>,
+    click: Handle<Synth>,
+    countdown: Handle<Synth>,
+    go: Handle<Synth>,
+    lap: Handle<Synth>,
+    last_lap: Handle<Synth>,
+    finish: Handle<Synth>,
+}
+
+/// Engine loop playing from a car, pitched by its speed
+#[derive(Component)]
+struct EngineSound;
+
+/// Tire squeal playing from a car, as loud as it is sliding
+#[derive(Component)]
+struct SkidSound;
+
+/// Engine, tire, crash and menu sounds, mixed with the volume setting. Cars are heard
+/// from the first player's camera, louder when close and panned to their side.
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_audio_source::<Synth>();
+        app.insert_resource(DefaultSpatialScale(SpatialScale::new_2d(
+            1.0 / HEARING_DISTANCE,
+        )));
+        app.add_systems(Startup, make_sounds);
+        app.add_systems(
+            Update,
+            (
+                add_listener,
+                add_car_sounds,
+                update_car_sounds,
+                play_impacts,
+                play_countdown_beeps,
+                play_lap_beeps,
+                play_clicks,
+            ),
+        );
+    }
+}
+
+fn make_sounds(mut commands: Commands, mut synths: ResMut<Assets<Synth>>) {
+    let mut rng = rand::thread_rng();
+    let mut noise = move || rng.gen_range(-1.0..1.0);
+
+    // Whole periods of a 55 Hz note, so the loop has no seam: a buzzy fundamental with
+    // fading harmonics, throbbing with the firing of the cylinders
+    let engine = Synth::new(0.4, |t| {
+        let phase = TAU * 55.0 * t;
+        let tone: f32 = (1..=6)
+            .map(|harmonic| (phase * harmonic as f32).sin() / harmonic as f32)
+            .sum();
+        tone * (0.75 + 0.25 * (TAU * 27.5 * t).sin()) * 0.4
+    });
+
+    // A wavering squeal over road noise
+    let mut rumble = 0.0;
+    let skid = Synth::new(0.5, |t| {
+        rumble += (noise() - rumble) * 0.3;
+        let squeal = (TAU * 900.0 * t + 2.0 * (TAU * 8.0 * t).sin()).sin();
+        squeal * 0.3 + rumble * 0.5
+    });
+
+    // A dull thump under a burst of crunching noise
+    let mut crunch = 0.0;
+    let impact = Synth::new(0.4, |t| {
+        crunch += (noise() - crunch) * 0.4;
+        let thump = (TAU * 60.0 * t).sin() * (-t / 0.1).exp();
+        (crunch * (-t / 0.06).exp() + thump) * 0.8
+    });
+
+    let click = Synth::new(0.03, |t| {
+        (TAU * 1500.0 * t).sin() * (-t / 0.008).exp() * 0.5
+    });
+
+    commands.insert_resource(Sounds {
+        engine: synths.add(engine),
+        skid: synths.add(skid),
+        impact: synths.add(impact),
+        click: synths.add(click),
+        countdown: synths.add(beep(440.0, 0.2)),
+        go: synths.add(beep(880.0, 0.6)),
+        lap: synths.add(beep(660.0, 0.15)),
+        last_lap: synths.add(beep(880.0, 0.3)),
+        finish: synths.add(beep(1320.0, 0.8)),
+    });
+}
+
+/// A plain tone that starts and stops without a click
+fn beep(frequency: f32, seconds: f32) -> Synth {
+    Synth::new(seconds, |t| {
+        let envelope = (t / 0.01).min(1.0) * ((seconds - t) / 0.05).clamp(0.0, 1.0);
+        (TAU * frequency * t).sin() * envelope * 0.4
+    })
+}
+
+/// Hear the race from the first player's camera
+fn add_listener(mut commands: Commands, rigs: Query<(Entity, &CameraRig), Added<CameraRig>>) {
+    for (entity, rig) in rigs.iter() {
+        if rig.seat == 0 {
+            commands
+                .entity(entity)
+                .insert(SpatialListener::new(EAR_GAP));
+        }
+    }
+}
+
+fn add_car_sounds(
+    mut commands: Commands,
+    sounds: Option<Res<Sounds>>,
+    cars: Query<Entity, (With<Car>, Added<CarVelocity>)>,
+) {
+    let Some(sounds) = sounds else {
+        return;
+    };
+    // Silent until the first update sets their volume
+    let looping = PlaybackSettings::LOOP
+        .with_spatial(true)
+        .with_volume(Volume::Linear(0.0));
+    for car in cars.iter() {
+        commands.entity(car).with_children(|parent| {
+            parent.spawn((
+                AudioPlayer(sounds.engine.clone()),
+                looping,
+                Transform::default(),
+                EngineSound,
+            ));
+            parent.spawn((
+                AudioPlayer(sounds.skid.clone()),
+                looping,
+                Transform::default(),
+                SkidSound,
+            ));
+        });
+    }
+}
+
+/// Pitch engines by speed and let tires squeal as cars slide sideways
+#[allow(clippy::type_complexity)]
+fn update_car_sounds(
+    settings: Res<Settings>,
+    cars: Query<(&Transform, &CarVelocity)>,
+    mut engines: Query<(&ChildOf, &mut SpatialAudioSink), With<EngineSound>>,
+    mut skids: Query<(&ChildOf, &mut SpatialAudioSink), (With<SkidSound>, Without<EngineSound>)>,
+) {
+    for (child_of, mut sink) in engines.iter_mut() {
+        let Ok((_, velocity)) = cars.get(child_of.parent()) else {
+            continue;
+        };
+        let speed = (velocity.0.length() / FULL_PITCH_SPEED).min(1.0);
+        sink.set_speed(IDLE_PITCH + (FULL_PITCH - IDLE_PITCH) * speed);
+        let volume = IDLE_VOLUME + (FULL_THROTTLE_VOLUME - IDLE_VOLUME) * speed;
+        sink.set_volume(Volume::Linear(volume * settings.volume));
+    }
+    for (child_of, mut sink) in skids.iter_mut() {
+        let Ok((transform, velocity)) = cars.get(child_of.parent()) else {
+            continue;
+        };
+        let slip = sideways_speed(transform, velocity.0);
+        let squeal = ((slip - SKID_START) / (SKID_FULL - SKID_START)).clamp(0.0, 1.0);
+        sink.set_volume(Volume::Linear(squeal * SKID_VOLUME * settings.volume));
+    }
+}
+
+fn play_impacts(
+    mut commands: Commands,
+    sounds: Option<Res<Sounds>>,
+    settings: Res<Settings>,
+    mut impacts: EventReader<ImpactReceived>,
+) {
+    let Some(sounds) = sounds else {
+        return;
+    };
+    let mut rng = rand::thread_rng();
+    for ImpactReceived(impact) in impacts.read() {
+        let loudness = (impact.strength / FULL_IMPACT_STRENGTH).clamp(0.1, 1.0);
+        // Walls sound deeper than other cars
+        let pitch = match impact.kind {
+            ImpactKind::CarToCar => 1.1,
+            ImpactKind::CarToWall => 0.8,
+        } * rng.gen_range(0.9..1.1);
+        commands.spawn((
+            AudioPlayer(sounds.impact.clone()),
+            PlaybackSettings::DESPAWN
+                .with_spatial(true)
+                .with_speed(pitch)
+                .with_volume(Volume::Linear(loudness * settings.volume)),
+            Transform::from_translation(impact.position.extend(0.0)),
+        ));
+    }
+}
+
+/// A beep for each second of the start countdown and a higher, longer one when the
+/// car is let go. Split-screen cars counting down together beep once.
+fn play_countdown_beeps(
+    mut commands: Commands,
+    sounds: Option<Res<Sounds>>,
+    settings: Res<Settings>,
+    cars: Query<(Entity, &StartCountdown), With<LocalCar>>,
+    mut seconds: Local<HashMap<Entity, u8>>,
+) {
+    let Some(sounds) = sounds else {
+        return;
+    };
+    seconds.retain(|car, _| cars.contains(*car));
+    let mut sound = None;
+    for (car, countdown) in cars.iter() {
+        let previous = seconds.insert(car, countdown.0);
+        if previous == Some(countdown.0) {
+            continue;
+        }
+        if countdown.0 > 0 {
+            sound = sound.or(Some(&sounds.countdown));
+        } else if previous.is_some() {
+            sound = Some(&sounds.go);
+        }
+    }
+    if let Some(sound) = sound {
+        commands.spawn((
+            AudioPlayer(sound.clone()),
+            PlaybackSettings::DESPAWN.with_volume(Volume::Linear(settings.volume)),
+        ));
+    }
+}
+
+/// One beep for each lap, a higher one for the last lap and a long one at the finish
+fn play_lap_beeps(
+    mut commands: Commands,
+    sounds: Option<Res<Sounds>>,
+    settings: Res<Settings>,
+    cars: Query<(Entity, &RaceProgress), With<LocalCar>>,
+    mut laps: Local<HashMap<Entity, u32>>,
+) {
+    let Some(sounds) = sounds else {
+        return;
+    };
+    laps.retain(|car, _| cars.contains(*car));
+    for (car, progress) in cars.iter() {
+        let previous = laps.insert(car, progress.lap).unwrap_or(progress.lap);
+        if progress.lap <= previous {
+            continue;
+        }
+        let sound = match progress.lap {
+            lap if lap > RACE_LAPS => &sounds.finish,
+            RACE_LAPS => &sounds.last_lap,
+            _ => &sounds.lap,
+        };
+        commands.spawn((
+            AudioPlayer(sound.clone()),
+            PlaybackSettings::DESPAWN.with_volume(Volume::Linear(settings.volume)),
+        ));
+    }
+}
+
+fn play_clicks(
+    mut commands: Commands,
+    sounds: Option<Res<Sounds>>,
+    settings: Res<Settings>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<Button>)>,
+) {
+    let Some(sounds) = sounds else {
+        return;
+    };
+    if buttons
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        commands.spawn((
+            AudioPlayer(sounds.click.clone()),
+            PlaybackSettings::DESPAWN.with_volume(Volume::Linear(settings.volume)),
+        ));
+    }
+}
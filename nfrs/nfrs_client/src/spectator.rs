@@ -0,0 +1,257 @@
+use bevy::ecs::component::Mutable;
+use bevy::prelude::*;
+use nfrs_shared::{Car, Player, RaceProgress, TrackLayout};
+use std::collections::VecDeque;
+
+use crate::camera::CameraRig;
+use crate::hud::race_order;
+use crate::{AppState, LocalPlayer};
+
+const PAN_SPEED: f32 = 40.0;
+const ZOOM_SPEED: f32 = 1.5;
+const MIN_SCALE: f32 = 0.01;
+const MAX_SCALE: f32 = 0.2;
+const FOLLOW_SCALE: f32 = 0.03;
+// How quickly the camera catches up with the car it follows (per second)
+const FOLLOW_SMOOTHING: f32 = 5.0;
+
+/// This client watches the race instead of driving
+#[derive(Resource)]
+pub struct Spectating {
+    /// Seconds the race is shown late, so drivers cannot use a public broadcast
+    pub delay: f32,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+enum CameraMode {
+    /// Moved by hand
+    #[default]
+    Free,
+    /// Follows one car
+    Follow(Entity),
+    /// Follows whoever leads the race
+    Leader,
+}
+
+#[derive(Resource, Default)]
+struct SpectatorCamera {
+    mode: CameraMode,
+}
+
+/// Replicated values of a component waiting to be shown, oldest first
+#[derive(Component)]
+struct Delayed<T> {
+    history: VecDeque<(f32, T)>,
+    /// Value last written to the component, to tell it apart from new replicated values
+    shown: Option<T>,
+}
+
+impl<T> Default for Delayed<T> {
+    fn default() -> Self {
+        Self {
+            history: VecDeque::new(),
+            shown: None,
+        }
+    }
+}
+
+#[derive(Component)]
+struct CameraText;
+
+pub struct SpectatorPlugin;
+
+impl Plugin for SpectatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpectatorCamera>();
+        app.add_systems(
+            OnEnter(AppState::Game),
+            setup_spectator_ui.run_if(resource_exists::<Spectating>),
+        );
+        app.add_systems(
+            Update,
+            (
+                add_delay_buffers,
+                delay_component::<Transform>,
+                delay_component::<RaceProgress>,
+                choose_camera_mode,
+                move_camera,
+            )
+                .chain()
+                .run_if(in_state(AppState::Game).and(resource_exists::<Spectating>)),
+        );
+    }
+}
+
+fn setup_spectator_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font,
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(Color::srgb(0.6, 0.6, 0.6)),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+        CameraText,
+        StateScoped(AppState::Game),
+    ));
+}
+
+fn add_delay_buffers(
+    mut commands: Commands,
+    spectating: Res<Spectating>,
+    cars: Query<Entity, Added<Car>>,
+) {
+    if spectating.delay <= 0.0 {
+        return;
+    }
+    for entity in cars.iter() {
+        commands.entity(entity).insert((
+            Delayed::<Transform>::default(),
+            Delayed::<RaceProgress>::default(),
+        ));
+    }
+}
+
+/// Hold back replicated values of `T` by the broadcast delay
+fn delay_component<T: Component<Mutability = Mutable> + Clone + PartialEq>(
+    time: Res<Time>,
+    spectating: Res<Spectating>,
+    mut cars: Query<(&mut T, &mut Delayed<T>)>,
+) {
+    let now = time.elapsed_secs();
+    let target = now - spectating.delay;
+    for (mut value, mut delayed) in cars.iter_mut() {
+        if delayed.shown.as_ref() != Some(&*value) {
+            delayed.history.push_back((now, value.clone()));
+        }
+        // Keep the newest value old enough to show; until there is one, show the oldest
+        while delayed.history.len() > 1 && delayed.history[1].0 <= target {
+            delayed.history.pop_front();
+        }
+        let Some((_, shown)) = delayed.history.front().cloned() else {
+            continue;
+        };
+        if *value != shown {
+            *value = shown.clone();
+        }
+        delayed.shown = Some(shown);
+    }
+}
+
+/// 1 free camera, 2 follow a car (TAB for the next one), 3 follow the leader
+fn choose_camera_mode(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut camera: ResMut<SpectatorCamera>,
+    cars: Query<(Entity, &Player), With<Car>>,
+) {
+    if keys.just_pressed(KeyCode::Digit1) {
+        camera.mode = CameraMode::Free;
+    }
+    if keys.just_pressed(KeyCode::Digit3) {
+        camera.mode = CameraMode::Leader;
+    }
+
+    let mut order: Vec<(u64, Entity)> = cars
+        .iter()
+        .map(|(entity, player)| (player.client_id, entity))
+        .collect();
+    order.sort_unstable();
+    let first = order.first().map(|(_, entity)| *entity);
+    match camera.mode {
+        CameraMode::Follow(current) if keys.just_pressed(KeyCode::Tab) => {
+            let index = order.iter().position(|(_, entity)| *entity == current);
+            let next = index
+                .and_then(|index| order.get(index + 1))
+                .map(|(_, e)| *e);
+            camera.mode = next.or(first).map_or(CameraMode::Free, CameraMode::Follow);
+        }
+        // The followed car left
+        CameraMode::Follow(current) if cars.get(current).is_err() => {
+            camera.mode = first.map_or(CameraMode::Free, CameraMode::Follow);
+        }
+        _ if keys.just_pressed(KeyCode::Digit2) || keys.just_pressed(KeyCode::Tab) => {
+            if let Some(first) = first {
+                camera.mode = CameraMode::Follow(first);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn move_camera(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    local: Res<LocalPlayer>,
+    camera: Res<SpectatorCamera>,
+    cars: Query<(Entity, &Player, &Transform, &RaceProgress), With<Car>>,
+    mut rigs: Query<&mut CameraRig>,
+    mut text: Query<&mut Text, With<CameraText>>,
+) {
+    let Ok(mut rig) = rigs.single_mut() else {
+        return;
+    };
+    let dt = time.delta_secs();
+
+    let target = match camera.mode {
+        CameraMode::Free => None,
+        CameraMode::Follow(entity) => cars.get(entity).ok(),
+        CameraMode::Leader => {
+            let track = TrackLayout::by_name(&local.track);
+            race_order(cars.iter(), track.as_ref()).into_iter().next()
+        }
+    };
+    let label = match (camera.mode, target) {
+        (CameraMode::Free, _) => "Free camera".to_string(),
+        (CameraMode::Leader, Some((_, player, ..))) => format!("Leader: {}", player.username),
+        (_, Some((_, player, ..))) => format!("Following {}", player.username),
+        (_, None) => "Waiting for cars".to_string(),
+    };
+
+    match target {
+        Some((_, _, car, _)) => {
+            let t = 1.0 - (-FOLLOW_SMOOTHING * dt).exp();
+            rig.position = rig.position.lerp(car.translation.truncate(), t);
+            rig.scale += (FOLLOW_SCALE - rig.scale) * t;
+        }
+        None if camera.mode == CameraMode::Free => {
+            let mut pan = Vec2::ZERO;
+            if keys.pressed(KeyCode::KeyW) || keys.pressed(KeyCode::ArrowUp) {
+                pan.y += 1.0;
+            }
+            if keys.pressed(KeyCode::KeyS) || keys.pressed(KeyCode::ArrowDown) {
+                pan.y -= 1.0;
+            }
+            if keys.pressed(KeyCode::KeyA) || keys.pressed(KeyCode::ArrowLeft) {
+                pan.x -= 1.0;
+            }
+            if keys.pressed(KeyCode::KeyD) || keys.pressed(KeyCode::ArrowRight) {
+                pan.x += 1.0;
+            }
+            // Pan at the same on-screen speed whatever the zoom
+            let speed = PAN_SPEED * rig.scale / FOLLOW_SCALE;
+            rig.position += pan.normalize_or_zero() * speed * dt;
+            if keys.pressed(KeyCode::KeyQ) {
+                rig.scale *= 1.0 + ZOOM_SPEED * dt;
+            }
+            if keys.pressed(KeyCode::KeyE) {
+                rig.scale /= 1.0 + ZOOM_SPEED * dt;
+            }
+            rig.scale = rig.scale.clamp(MIN_SCALE, MAX_SCALE);
+        }
+        None => {}
+    }
+
+    if let Ok(mut text) = text.single_mut() {
+        text.0 = format!(
+            "{}   1 free (WASD move, Q/E zoom)  2/TAB follow  3 leader",
+            label
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use bevy::input::touch::Touches;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use std::collections::HashMap;
+use tracing::info;
+
+use crate::controls::{read_actions, Action, VirtualActions};
+use crate::spectator::Spectating;
+use crate::AppState;
+
+// How far a thumb moves from where it landed for full steering lock, in logical pixels
+const JOYSTICK_RADIUS: f32 = 70.0;
+const KNOB_SIZE: f32 = 60.0;
+// Touches starting left of this share of the screen, and below the top bar, steer
+const JOYSTICK_AREA_WIDTH: f32 = 0.4;
+const JOYSTICK_AREA_TOP: f32 = 0.3;
+// Where the joystick is drawn until a thumb lands, as a share of the screen
+const JOYSTICK_REST: Vec2 = Vec2::new(0.15, 0.75);
+const BUTTON_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.15);
+const HELD_COLOR: Color = Color::srgba(0.0, 0.8, 1.0, 0.45);
+
+/// An on-screen button, placed as shares of the screen: left, top, width, height
+struct TouchButton {
+    action: Action,
+    label: &'static str,
+    area: [f32; 4],
+}
+
+// Pedals under the right thumb, with the other actions above them
+const BUTTONS: [TouchButton; 6] = [
+    TouchButton {
+        action: Action::Throttle,
+        label: "GAS",
+        area: [0.82, 0.5, 0.16, 0.46],
+    },
+    TouchButton {
+        action: Action::Brake,
+        label: "BRAKE",
+        area: [0.65, 0.66, 0.15, 0.3],
+    },
+    TouchButton {
+        action: Action::Handbrake,
+        label: "DRIFT",
+        area: [0.65, 0.47, 0.15, 0.17],
+    },
+    TouchButton {
+        action: Action::Boost,
+        label: "NITRO",
+        area: [0.82, 0.28, 0.16, 0.2],
+    },
+    TouchButton {
+        action: Action::UseItem,
+        label: "ITEM",
+        area: [0.65, 0.28, 0.15, 0.17],
+    },
+    TouchButton {
+        action: Action::Pause,
+        label: "MENU",
+        area: [0.88, 0.02, 0.1, 0.09],
+    },
+];
+
+impl TouchButton {
+    fn contains(&self, position: Vec2, window: Vec2) -> bool {
+        let [left, top, width, height] = self.area;
+        let min = Vec2::new(left, top) * window;
+        Rect::from_corners(min, min + Vec2::new(width, height) * window).contains(position)
+    }
+}
+
+/// Whether the on-screen controls are shown. They turn on by themselves on touch screens.
+#[derive(Resource, Default)]
+pub struct TouchControls {
+    pub active: bool,
+}
+
+/// The steering thumb: where it landed and where it is now
+#[derive(Resource, Default)]
+struct Joystick(Option<(Vec2, Vec2)>);
+
+#[derive(Component)]
+struct TouchRoot;
+
+#[derive(Component)]
+struct TouchButtonNode(usize);
+
+#[derive(Component)]
+struct JoystickBase;
+
+#[derive(Component)]
+struct JoystickKnob;
+
+pub struct TouchPlugin;
+
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TouchControls {
+            active: has_touch_screen(),
+        });
+        app.init_resource::<Joystick>();
+        app.add_systems(
+            PreUpdate,
+            read_touches.after(InputSystem).before(read_actions),
+        );
+        app.add_systems(OnEnter(AppState::Game), spawn_touch_controls);
+        app.add_systems(Update, draw_touch_controls.run_if(in_state(AppState::Game)));
+    }
+}
+
+/// Browsers on phones and tablets report touch points before the first touch
+#[cfg(target_arch = "wasm32")]
+fn has_touch_screen() -> bool {
+    web_sys::window().is_some_and(|window| window.navigator().max_touch_points() > 0)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn has_touch_screen() -> bool {
+    false
+}
+
+/// Turn the fingers on screen into actions
+fn read_touches(
+    touches: Res<Touches>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    state: Res<State<AppState>>,
+    spectating: Option<Res<Spectating>>,
+    mut controls: ResMut<TouchControls>,
+    mut joystick: ResMut<Joystick>,
+    mut on_screen: ResMut<VirtualActions>,
+) {
+    if !controls.active && touches.any_just_pressed() {
+        info!("Touch screen detected, showing touch controls");
+        controls.active = true;
+    }
+    on_screen.0.clear();
+    joystick.0 = None;
+    let Ok(window) = windows.single() else {
+        return;
+    };
+    if !controls.active || *state.get() != AppState::Game || spectating.is_some() {
+        return;
+    }
+
+    let size = window.size();
+    let mut held: HashMap<Action, f32> = HashMap::new();
+    for touch in touches.iter() {
+        let start = touch.start_position();
+        let steering =
+            start.x < size.x * JOYSTICK_AREA_WIDTH && start.y > size.y * JOYSTICK_AREA_TOP;
+        if steering && joystick.0.is_none() {
+            joystick.0 = Some((start, touch.position()));
+            let offset = ((touch.position().x - start.x) / JOYSTICK_RADIUS).clamp(-1.0, 1.0);
+            let action = if offset < 0.0 {
+                Action::SteerLeft
+            } else {
+                Action::SteerRight
+            };
+            held.insert(action, offset.abs());
+            continue;
+        }
+        // Thumbs can slide from one pedal to the other
+        for button in BUTTONS.iter() {
+            if button.contains(touch.position(), size) {
+                held.insert(button.action, 1.0);
+            }
+        }
+    }
+    on_screen.0 = held;
+}
+
+fn percent_node([left, top, width, height]: [f32; 4]) -> Node {
+    Node {
+        position_type: PositionType::Absolute,
+        left: Val::Percent(left * 100.0),
+        top: Val::Percent(top * 100.0),
+        width: Val::Percent(width * 100.0),
+        height: Val::Percent(height * 100.0),
+        align_items: AlignItems::Center,
+        justify_content: JustifyContent::Center,
+        border: UiRect::all(Val::Px(2.0)),
+        ..default()
+    }
+}
+
+fn circle_node(size: f32) -> Node {
+    Node {
+        position_type: PositionType::Absolute,
+        width: Val::Px(size),
+        height: Val::Px(size),
+        border: UiRect::all(Val::Px(2.0)),
+        ..default()
+    }
+}
+
+fn spawn_touch_controls(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            Visibility::Hidden,
+            TouchRoot,
+            StateScoped(AppState::Game),
+        ))
+        .with_children(|parent| {
+            for (index, button) in BUTTONS.iter().enumerate() {
+                parent
+                    .spawn((
+                        percent_node(button.area),
+                        BackgroundColor(BUTTON_COLOR),
+                        BorderColor(Color::srgba(1.0, 1.0, 1.0, 0.4)),
+                        BorderRadius::all(Val::Px(12.0)),
+                        TouchButtonNode(index),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            Text::new(button.label),
+                            TextFont {
+                                font: font.clone(),
+                                font_size: 20.0,
+                                ..default()
+                            },
+                            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.8)),
+                        ));
+                    });
+            }
+            parent.spawn((
+                circle_node(JOYSTICK_RADIUS * 2.0 + KNOB_SIZE),
+                BorderColor(Color::srgba(1.0, 1.0, 1.0, 0.4)),
+                BorderRadius::MAX,
+                JoystickBase,
+            ));
+            parent.spawn((
+                circle_node(KNOB_SIZE),
+                BackgroundColor(BUTTON_COLOR),
+                BorderColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+                BorderRadius::MAX,
+                JoystickKnob,
+            ));
+        });
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn draw_touch_controls(
+    controls: Res<TouchControls>,
+    joystick: Res<Joystick>,
+    on_screen: Res<VirtualActions>,
+    spectating: Option<Res<Spectating>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut roots: Query<&mut Visibility, With<TouchRoot>>,
+    mut buttons: Query<(&TouchButtonNode, &mut BackgroundColor)>,
+    mut base: Query<&mut Node, (With<JoystickBase>, Without<JoystickKnob>)>,
+    mut knob: Query<&mut Node, (With<JoystickKnob>, Without<JoystickBase>)>,
+) {
+    let shown = controls.active && spectating.is_none();
+    for mut visibility in roots.iter_mut() {
+        let wanted = if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+    if !shown {
+        return;
+    }
+
+    for (node, mut color) in buttons.iter_mut() {
+        let held = on_screen.0.contains_key(&BUTTONS[node.0].action);
+        color.0 = if held { HELD_COLOR } else { BUTTON_COLOR };
+    }
+
+    let Ok(window) = windows.single() else {
+        return;
+    };
+    let (center, thumb) = joystick.0.unwrap_or_else(|| {
+        let rest = JOYSTICK_REST * window.size();
+        (rest, rest)
+    });
+    // Steering only reads the sideways movement
+    let thumb = Vec2::new(
+        thumb
+            .x
+            .clamp(center.x - JOYSTICK_RADIUS, center.x + JOYSTICK_RADIUS),
+        center.y,
+    );
+    let place = |node: &mut Node, position: Vec2, size: f32| {
+        node.left = Val::Px(position.x - size / 2.0);
+        node.top = Val::Px(position.y - size / 2.0);
+    };
+    if let Ok(mut node) = base.single_mut() {
+        place(&mut node, center, JOYSTICK_RADIUS * 2.0 + KNOB_SIZE);
+    }
+    if let Ok(mut node) = knob.single_mut() {
+        place(&mut node, thumb, KNOB_SIZE);
+    }
+}
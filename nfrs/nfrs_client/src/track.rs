@@ -0,0 +1,65 @@
+use bevy::prelude::*;
+use nfrs_shared::{SurfaceKind, SurfaceRegion};
+
+use crate::showing_race;
+
+// Surfaces are drawn well below the cars (z = 0)
+const SURFACE_BASE_Z: f32 = -10.0;
+
+#[derive(Component)]
+struct BoostPadGlow;
+
+pub struct TrackPlugin;
+
+impl Plugin for TrackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (spawn_surfaces, animate_boost_pads).run_if(showing_race),
+        );
+    }
+}
+
+pub fn surface_color(kind: SurfaceKind) -> Color {
+    match kind {
+        SurfaceKind::Asphalt => Color::srgb(0.22, 0.22, 0.24),
+        SurfaceKind::Grass => Color::srgb(0.16, 0.42, 0.16),
+        SurfaceKind::Gravel => Color::srgb(0.62, 0.56, 0.42),
+        SurfaceKind::Ice => Color::srgb(0.72, 0.86, 0.95),
+        SurfaceKind::BoostPad => Color::srgb(1.0, 0.45, 0.0),
+    }
+}
+
+/// Attach a sprite to every replicated surface region.
+/// The sprite is a child so the replicated Transform (z = 0) can stay untouched.
+fn spawn_surfaces(
+    mut commands: Commands,
+    regions: Query<(Entity, &SurfaceRegion), Added<SurfaceRegion>>,
+) {
+    for (entity, region) in regions.iter() {
+        let z = SURFACE_BASE_Z + region.kind.priority() as f32 * 0.1;
+        commands
+            .entity(entity)
+            .insert(Visibility::default())
+            .with_children(|parent| {
+                let mut child = parent.spawn((
+                    Sprite {
+                        color: surface_color(region.kind),
+                        custom_size: Some(region.half_extents * 2.0),
+                        ..default()
+                    },
+                    Transform::from_xyz(0.0, 0.0, z),
+                ));
+                if region.kind == SurfaceKind::BoostPad {
+                    child.insert(BoostPadGlow);
+                }
+            });
+    }
+}
+
+fn animate_boost_pads(time: Res<Time>, mut pads: Query<&mut Sprite, With<BoostPadGlow>>) {
+    let pulse = 0.75 + 0.25 * (time.elapsed_secs() * 6.0).sin();
+    for mut sprite in pads.iter_mut() {
+        sprite.color = surface_color(SurfaceKind::BoostPad).with_luminance(0.5 * pulse);
+    }
+}
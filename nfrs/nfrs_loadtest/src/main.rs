@@ -0,0 +1,157 @@
+use clap::Parser;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing_subscriber::FmtSubscriber;
+
+use crate::sim::{InputMode, SimConfig, Transport};
+use crate::stats::LoadStats;
+
+mod sim;
+mod stats;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum TransportArg {
+    Udp,
+    Webtransport,
+    /// Alternate between UDP and WebTransport
+    Mixed,
+}
+
+/// Opens many simulated player connections against an nfrs server and reports how it copes
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Server IP address
+    #[arg(short, long, default_value = "127.0.0.1")]
+    ip: IpAddr,
+    #[arg(long, default_value_t = 5000)]
+    udp_port: u16,
+    #[arg(long, default_value_t = 5001)]
+    wt_port: u16,
+    /// Number of simulated clients
+    #[arg(short, long, default_value_t = 50)]
+    clients: usize,
+    #[arg(long, value_enum, default_value_t = TransportArg::Udp)]
+    transport: TransportArg,
+    /// SHA-256 digest of the server certificate (printed by the server on startup),
+    /// required for WebTransport
+    #[arg(long, default_value = "")]
+    cert_digest: String,
+    #[arg(long, value_enum, default_value_t = InputMode::Scripted)]
+    inputs: InputMode,
+    /// Delay between opening connections, in milliseconds
+    #[arg(long, default_value_t = 50)]
+    ramp_ms: u64,
+    /// Total test duration, in seconds
+    #[arg(short, long, default_value_t = 60)]
+    duration: u64,
+    /// Seconds between progress reports
+    #[arg(long, default_value_t = 5)]
+    report_every: u64,
+    /// Seconds a client may take to connect before it counts as failed
+    #[arg(long, default_value_t = 10)]
+    connect_timeout: u64,
+    /// Show client library logs
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    // Hundreds of clients log the same lines; keep them quiet unless asked
+    let level = if args.verbose {
+        tracing::Level::INFO
+    } else {
+        tracing::Level::ERROR
+    };
+    let subscriber = FmtSubscriber::builder().with_max_level(level).finish();
+    tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
+
+    if args.transport != TransportArg::Udp && args.cert_digest.is_empty() {
+        eprintln!("--cert-digest is required for WebTransport clients");
+        std::process::exit(2);
+    }
+
+    let stats = Arc::new(LoadStats::default());
+    let stop = Arc::new(AtomicBool::new(false));
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(args.duration);
+
+    println!(
+        "Starting {} {:?} clients against {} ({:?} inputs, {} ms apart, {} s)",
+        args.clients, args.transport, args.ip, args.inputs, args.ramp_ms, args.duration
+    );
+
+    // Open connections gradually from a separate thread so reports start right away
+    let spawner = {
+        let stats = stats.clone();
+        let stop = stop.clone();
+        let base = SimConfig {
+            index: 0,
+            server_addr: SocketAddr::new(args.ip, args.udp_port),
+            transport: Transport::Udp,
+            // Accept the colon-separated form the server prints
+            certificate_digest: args.cert_digest.replace(':', ""),
+            inputs: args.inputs,
+            connect_timeout: Duration::from_secs(args.connect_timeout),
+        };
+        let (clients, transport, wt_port, ramp) = (
+            args.clients,
+            args.transport,
+            args.wt_port,
+            Duration::from_millis(args.ramp_ms),
+        );
+        std::thread::spawn(move || {
+            let mut handles = Vec::with_capacity(clients);
+            for index in 0..clients {
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                let mut config = base.clone();
+                config.index = index;
+                let webtransport = match transport {
+                    TransportArg::Udp => false,
+                    TransportArg::Webtransport => true,
+                    TransportArg::Mixed => index % 2 == 1,
+                };
+                if webtransport {
+                    config.transport = Transport::WebTransport;
+                    config.server_addr.set_port(wt_port);
+                }
+                let (stats, stop) = (stats.clone(), stop.clone());
+                handles.push(
+                    std::thread::Builder::new()
+                        .name(format!("client-{}", index))
+                        .spawn(move || sim::run(config, stats, stop))
+                        .expect("failed to spawn client thread"),
+                );
+                std::thread::sleep(ramp);
+            }
+            for handle in handles {
+                let _ = handle.join();
+            }
+        })
+    };
+
+    let interval = Duration::from_secs(args.report_every.max(1));
+    let mut previous = stats.snapshot();
+    let mut last_report = Instant::now();
+    while Instant::now() < deadline {
+        std::thread::sleep(interval.min(deadline - Instant::now()));
+        let snapshot = stats.snapshot();
+        println!(
+            "[{:>4.0}s] {}",
+            started.elapsed().as_secs_f64(),
+            snapshot.interval_report(&previous, last_report.elapsed())
+        );
+        previous = snapshot;
+        last_report = Instant::now();
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    let _ = spawner.join();
+    println!("{}", stats.snapshot().final_report(started.elapsed()));
+}
@@ -0,0 +1,383 @@
+use bevy::app::{AppExit, ScheduleRunnerPlugin};
+use bevy::prelude::*;
+use lightyear::netcode::Key;
+use lightyear::prelude::client::*;
+use lightyear::prelude::*;
+use nfrs_shared::{
+    CarInput, ChatLine, ImpactEvent, InputChannel, JoinRequest, NoticeLevel, Player,
+    ProtocolPlugin, ServerNotice, ServerStatus,
+};
+use rand::Rng;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::stats::LoadStats;
+
+// Frames to keep running after the disconnect so it reaches the server
+const SHUTDOWN_FRAMES: u8 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transport {
+    Udp,
+    WebTransport,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputMode {
+    /// Full throttle, weaving left and right, using items and nitro on a timer
+    Scripted,
+    /// Random key combinations, changed every fraction of a second
+    Random,
+    /// Connect and join, but never send inputs
+    Idle,
+}
+
+/// Everything one simulated client needs to know
+#[derive(Resource, Clone)]
+pub struct SimConfig {
+    pub index: usize,
+    pub server_addr: SocketAddr,
+    pub transport: Transport,
+    pub certificate_digest: String,
+    pub inputs: InputMode,
+    pub connect_timeout: Duration,
+}
+
+impl SimConfig {
+    fn username(&self) -> String {
+        format!("load-{:04}", self.index)
+    }
+}
+
+#[derive(Resource)]
+struct Shared {
+    stats: Arc<LoadStats>,
+    stop: Arc<AtomicBool>,
+}
+
+/// Per-client progress through connect -> join -> drive
+#[derive(Resource)]
+struct SimState {
+    started: Instant,
+    connected_at: Option<Instant>,
+    own_car: Option<Entity>,
+    last_update: Option<Instant>,
+    input: CarInput,
+    next_input_change: f32,
+    shutdown_in: Option<u8>,
+}
+
+/// Run one simulated client until the stop flag is raised or it loses its connection
+pub fn run(config: SimConfig, stats: Arc<LoadStats>, stop: Arc<AtomicBool>) {
+    stats.attempted.fetch_add(1, Ordering::Relaxed);
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins
+            // Hundreds of clients share the machine: one worker thread each is plenty
+            .set(TaskPoolPlugin {
+                task_pool_options: TaskPoolOptions::with_num_threads(1),
+            })
+            .set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+                1.0 / 60.0,
+            ))),
+        ClientPlugins::default(),
+        ProtocolPlugin,
+    ));
+    app.insert_resource(config);
+    app.insert_resource(Shared { stats, stop });
+    app.insert_resource(SimState {
+        started: Instant::now(),
+        connected_at: None,
+        own_car: None,
+        last_update: None,
+        input: CarInput::default(),
+        next_input_change: 0.0,
+        shutdown_in: None,
+    });
+    app.add_systems(Startup, connect);
+    app.add_systems(
+        PreUpdate,
+        count_received
+            .after(LinkSet::Receive)
+            .before(ConnectionSet::Receive),
+    );
+    app.add_systems(
+        Update,
+        (
+            handle_connected,
+            handle_disconnected,
+            find_own_car,
+            track_replication,
+            receive_messages,
+            sample_rtt,
+            check_shutdown,
+        ),
+    );
+    app.add_systems(FixedUpdate, send_inputs);
+    app.add_systems(
+        PostUpdate,
+        count_sent.after(ConnectionSet::Send).before(LinkSet::Send),
+    );
+    app.run();
+}
+
+fn connect(mut commands: Commands, config: Res<SimConfig>) {
+    let auth = Authentication::Manual {
+        server_addr: config.server_addr,
+        client_id: rand::random::<u64>(),
+        private_key: Key::default(),
+        protocol_id: 0,
+    };
+    let client_addr = SocketAddr::new(Ipv4Addr::new(0, 0, 0, 0).into(), 0);
+
+    let mut client = commands.spawn((
+        Client::default(),
+        LocalAddr(client_addr),
+        PeerAddr(config.server_addr),
+        Link::new(None),
+        ReplicationReceiver::default(),
+        NetcodeClient::new(auth, NetcodeConfig::default()).unwrap(),
+    ));
+    match config.transport {
+        Transport::Udp => {
+            client.insert(UdpIo::default());
+        }
+        Transport::WebTransport => {
+            client.insert(WebTransportClientIo {
+                certificate_digest: config.certificate_digest.clone(),
+            });
+        }
+    }
+    client.insert((
+        MessageSender::<JoinRequest>::default(),
+        MessageSender::<CarInput>::default(),
+        MessageReceiver::<ServerNotice>::default(),
+        MessageReceiver::<ImpactEvent>::default(),
+        MessageReceiver::<ServerStatus>::default(),
+        MessageReceiver::<ChatLine>::default(),
+    ));
+
+    let client = client.id();
+    commands.entity(client).trigger(LinkStart);
+    commands.entity(client).trigger(Connect);
+}
+
+fn handle_connected(
+    mut clients: Query<&mut MessageSender<JoinRequest>, Added<Connected>>,
+    config: Res<SimConfig>,
+    shared: Res<Shared>,
+    mut state: ResMut<SimState>,
+) {
+    for mut sender in clients.iter_mut() {
+        let now = Instant::now();
+        shared.stats.record_connect(now - state.started);
+        state.connected_at = Some(now);
+        sender.send::<InputChannel>(JoinRequest {
+            username: config.username(),
+            spectator: false,
+            seat: 0,
+        });
+    }
+}
+
+fn handle_disconnected(
+    clients: Query<(), Added<Disconnected>>,
+    config: Res<SimConfig>,
+    shared: Res<Shared>,
+    state: Res<SimState>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if state.shutdown_in.is_some() {
+        return;
+    }
+    if state.connected_at.is_some() {
+        if !clients.is_empty() {
+            shared.stats.disconnected.fetch_add(1, Ordering::Relaxed);
+            exit.write(AppExit::Success);
+        }
+    } else if state.started.elapsed() > config.connect_timeout {
+        // Disconnected is also present before the first connection, so rely on the timeout
+        shared.stats.failed.fetch_add(1, Ordering::Relaxed);
+        exit.write(AppExit::error());
+    }
+}
+
+fn find_own_car(
+    cars: Query<(Entity, &Player), Added<Player>>,
+    config: Res<SimConfig>,
+    shared: Res<Shared>,
+    mut state: ResMut<SimState>,
+) {
+    if state.own_car.is_some() {
+        return;
+    }
+    let username = config.username();
+    for (entity, player) in cars.iter() {
+        if player.username == username {
+            if let Some(connected_at) = state.connected_at {
+                shared.stats.record_join(connected_at.elapsed());
+            }
+            state.own_car = Some(entity);
+            state.last_update = Some(Instant::now());
+        }
+    }
+}
+
+/// Time between replicated transform updates of our own car
+fn track_replication(
+    cars: Query<(), Changed<Transform>>,
+    shared: Res<Shared>,
+    mut state: ResMut<SimState>,
+) {
+    let Some(car) = state.own_car else {
+        return;
+    };
+    if !cars.contains(car) {
+        return;
+    }
+    let now = Instant::now();
+    if let Some(last) = state.last_update.replace(now) {
+        shared.stats.record_update_gap(now - last);
+    }
+}
+
+fn receive_messages(
+    mut clients: Query<(
+        &mut MessageReceiver<ServerNotice>,
+        &mut MessageReceiver<ImpactEvent>,
+        &mut MessageReceiver<ServerStatus>,
+    )>,
+    config: Res<SimConfig>,
+    shared: Res<Shared>,
+) {
+    for (mut notices, mut impacts, mut statuses) in clients.iter_mut() {
+        for notice in notices.receive() {
+            if notice.level == NoticeLevel::Kicked {
+                shared.stats.kicked.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        impacts.receive().for_each(drop);
+        // Every client gets the same report; one reporter is enough
+        let latest = statuses.receive().last();
+        if config.index == 0 {
+            if let Some(status) = latest {
+                shared.stats.record_server_status(status);
+            }
+        }
+    }
+}
+
+fn sample_rtt(
+    time: Res<Time>,
+    clients: Query<&Link, With<Connected>>,
+    shared: Res<Shared>,
+    mut timer: Local<Option<Timer>>,
+) {
+    let timer = timer.get_or_insert_with(|| Timer::from_seconds(1.0, TimerMode::Repeating));
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    for link in clients.iter() {
+        if link.stats.rtt > Duration::ZERO {
+            shared.stats.record_rtt(link.stats.rtt);
+        }
+    }
+}
+
+fn send_inputs(
+    time: Res<Time>,
+    config: Res<SimConfig>,
+    mut state: ResMut<SimState>,
+    mut clients: Query<&mut MessageSender<CarInput>, With<Connected>>,
+) {
+    if state.own_car.is_none() || state.shutdown_in.is_some() {
+        return;
+    }
+    let elapsed = state.started.elapsed().as_secs_f32();
+    let input = match config.inputs {
+        InputMode::Idle => return,
+        InputMode::Scripted => {
+            // Offset each client so they don't all weave in lockstep
+            let phase = elapsed + config.index as f32 * 0.37;
+            CarInput {
+                forward: true,
+                left: phase % 4.0 < 1.0,
+                right: (2.0..3.0).contains(&(phase % 4.0)),
+                use_item: phase % 5.0 < 0.1,
+                boost: phase % 10.0 < 2.0,
+                ..default()
+            }
+        }
+        InputMode::Random => {
+            state.next_input_change -= time.delta_secs();
+            if state.next_input_change <= 0.0 {
+                let mut rng = rand::thread_rng();
+                state.next_input_change = rng.gen_range(0.2..1.0);
+                state.input = CarInput {
+                    forward: rng.gen_bool(0.8),
+                    backward: rng.gen_bool(0.1),
+                    left: rng.gen_bool(0.3),
+                    right: rng.gen_bool(0.3),
+                    use_item: rng.gen_bool(0.1),
+                    boost: rng.gen_bool(0.2),
+                    handbrake: rng.gen_bool(0.05),
+                    ..default()
+                };
+            }
+            state.input
+        }
+    };
+    for mut sender in clients.iter_mut() {
+        sender.send::<InputChannel>(input);
+    }
+}
+
+fn check_shutdown(
+    mut commands: Commands,
+    shared: Res<Shared>,
+    mut state: ResMut<SimState>,
+    clients: Query<Entity, With<Client>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    match state.shutdown_in {
+        None if shared.stop.load(Ordering::Relaxed) => {
+            if state.connected_at.is_none() {
+                // Never made it in before the end of the test
+                shared.stats.failed.fetch_add(1, Ordering::Relaxed);
+            }
+            for client in clients.iter() {
+                commands.trigger_targets(Disconnect, client);
+            }
+            state.shutdown_in = Some(SHUTDOWN_FRAMES);
+        }
+        Some(0) => {
+            exit.write(AppExit::Success);
+        }
+        Some(frames) => state.shutdown_in = Some(frames - 1),
+        None => {}
+    }
+}
+
+/// Count incoming packets while they sit in the link buffer, before netcode consumes them
+fn count_received(mut links: Query<&mut Link>, shared: Res<Shared>) {
+    for mut link in links.iter_mut() {
+        let payloads: Vec<_> = link.recv.drain().collect();
+        for payload in payloads {
+            shared.stats.record_received(payload.len());
+            link.recv.push_raw(payload);
+        }
+    }
+}
+
+/// Count outgoing packets after netcode has written them to the link buffer
+fn count_sent(mut links: Query<&mut Link>, shared: Res<Shared>) {
+    for mut link in links.iter_mut() {
+        let payloads: Vec<_> = link.send.drain().collect();
+        for payload in payloads {
+            shared.stats.record_sent(payload.len());
+            link.send.push(payload);
+        }
+    }
+}
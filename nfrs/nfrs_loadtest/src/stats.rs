@@ -0,0 +1,266 @@
+use nfrs_shared::ServerStatus;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Counters shared by every simulated client thread
+#[derive(Default)]
+pub struct LoadStats {
+    pub attempted: AtomicU64,
+    pub connected: AtomicU64,
+    pub failed: AtomicU64,
+    pub disconnected: AtomicU64,
+    pub joined: AtomicU64,
+    pub kicked: AtomicU64,
+    pub bytes_sent: AtomicU64,
+    pub bytes_received: AtomicU64,
+    pub packets_sent: AtomicU64,
+    pub packets_received: AtomicU64,
+    connect: Samples,
+    join: Samples,
+    rtt: Samples,
+    /// Gaps between replication updates of a client's own car
+    update_gap: Samples,
+    server: Mutex<ServerSummary>,
+}
+
+/// Running count, total and max of millisecond samples
+#[derive(Default)]
+struct Samples {
+    count: AtomicU64,
+    total_ms: AtomicU64,
+    max_ms: AtomicU64,
+}
+
+impl Samples {
+    fn record(&self, duration: Duration) {
+        let ms = duration.as_millis() as u64;
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_ms.fetch_add(ms, Ordering::Relaxed);
+        self.max_ms.fetch_max(ms, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> SampleSnapshot {
+        SampleSnapshot {
+            count: self.count.load(Ordering::Relaxed),
+            total_ms: self.total_ms.load(Ordering::Relaxed),
+            max_ms: self.max_ms.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Worst server health seen so far, plus the latest report
+#[derive(Default, Clone)]
+struct ServerSummary {
+    latest: Option<ServerStatus>,
+    worst_tick_rate: Option<f32>,
+    worst_frame_ms: f32,
+}
+
+impl LoadStats {
+    pub fn record_connect(&self, duration: Duration) {
+        self.connected.fetch_add(1, Ordering::Relaxed);
+        self.connect.record(duration);
+    }
+
+    pub fn record_join(&self, duration: Duration) {
+        self.joined.fetch_add(1, Ordering::Relaxed);
+        self.join.record(duration);
+    }
+
+    pub fn record_rtt(&self, rtt: Duration) {
+        self.rtt.record(rtt);
+    }
+
+    pub fn record_update_gap(&self, gap: Duration) {
+        self.update_gap.record(gap);
+    }
+
+    pub fn record_sent(&self, bytes: usize) {
+        self.packets_sent.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_received(&self, bytes: usize) {
+        self.packets_received.fetch_add(1, Ordering::Relaxed);
+        self.bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_server_status(&self, status: ServerStatus) {
+        let Ok(mut server) = self.server.lock() else {
+            return;
+        };
+        server.worst_tick_rate = Some(
+            server
+                .worst_tick_rate
+                .map_or(status.tick_rate, |worst| worst.min(status.tick_rate)),
+        );
+        server.worst_frame_ms = server.worst_frame_ms.max(status.frame_ms_max);
+        server.latest = Some(status);
+    }
+
+    pub fn snapshot(&self) -> StatsSnapshot {
+        let server = self
+            .server
+            .lock()
+            .map(|server| server.clone())
+            .unwrap_or_default();
+        StatsSnapshot {
+            attempted: self.attempted.load(Ordering::Relaxed),
+            connected: self.connected.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            disconnected: self.disconnected.load(Ordering::Relaxed),
+            joined: self.joined.load(Ordering::Relaxed),
+            kicked: self.kicked.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            packets_sent: self.packets_sent.load(Ordering::Relaxed),
+            packets_received: self.packets_received.load(Ordering::Relaxed),
+            connect: self.connect.snapshot(),
+            join: self.join.snapshot(),
+            rtt: self.rtt.snapshot(),
+            update_gap: self.update_gap.snapshot(),
+            server: server.latest,
+            worst_tick_rate: server.worst_tick_rate,
+            worst_frame_ms: server.worst_frame_ms,
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct SampleSnapshot {
+    count: u64,
+    total_ms: u64,
+    max_ms: u64,
+}
+
+impl SampleSnapshot {
+    fn avg_ms(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total_ms as f64 / self.count as f64
+        }
+    }
+
+    /// Average over the samples recorded since `earlier`
+    fn avg_ms_since(&self, earlier: &SampleSnapshot) -> f64 {
+        let count = self.count - earlier.count;
+        if count == 0 {
+            0.0
+        } else {
+            (self.total_ms - earlier.total_ms) as f64 / count as f64
+        }
+    }
+}
+
+/// Point-in-time copy of [`LoadStats`], used to compute per-interval rates
+#[derive(Default, Clone)]
+pub struct StatsSnapshot {
+    attempted: u64,
+    connected: u64,
+    failed: u64,
+    disconnected: u64,
+    joined: u64,
+    kicked: u64,
+    bytes_sent: u64,
+    bytes_received: u64,
+    packets_sent: u64,
+    packets_received: u64,
+    connect: SampleSnapshot,
+    join: SampleSnapshot,
+    rtt: SampleSnapshot,
+    update_gap: SampleSnapshot,
+    server: Option<ServerStatus>,
+    worst_tick_rate: Option<f32>,
+    worst_frame_ms: f32,
+}
+
+impl StatsSnapshot {
+    fn connections(&self) -> String {
+        format!(
+            "clients {}/{} connected, {} joined, {} failed, {} dropped, {} kicked",
+            self.connected.saturating_sub(self.disconnected),
+            self.attempted,
+            self.joined,
+            self.failed,
+            self.disconnected,
+            self.kicked
+        )
+    }
+
+    fn server_health(&self) -> String {
+        match &self.server {
+            Some(status) => format!(
+                "server {:.1}/{:.0} tps, frame avg {:.1} ms max {:.1} ms, {} clients, {} cars",
+                status.tick_rate,
+                status.target_tick_rate,
+                status.frame_ms_avg,
+                status.frame_ms_max,
+                status.clients,
+                status.cars
+            ),
+            None => "server: no status yet".to_string(),
+        }
+    }
+
+    /// One line describing the interval since `previous`
+    pub fn interval_report(&self, previous: &StatsSnapshot, interval: Duration) -> String {
+        let secs = interval.as_secs_f64().max(f64::EPSILON);
+        let down_kbps =
+            (self.bytes_received - previous.bytes_received) as f64 * 8.0 / 1000.0 / secs;
+        let up_kbps = (self.bytes_sent - previous.bytes_sent) as f64 * 8.0 / 1000.0 / secs;
+        let updates = (self.update_gap.count - previous.update_gap.count) as f64 / secs;
+        let joined = self.joined.max(1) as f64;
+        format!(
+            "{} | down {:.0} kbps up {:.0} kbps | rtt {:.0} ms | own-car updates {:.1}/s per client | {}",
+            self.connections(),
+            down_kbps,
+            up_kbps,
+            self.rtt.avg_ms_since(&previous.rtt),
+            updates / joined,
+            self.server_health()
+        )
+    }
+
+    /// Multi-line summary of the whole run
+    pub fn final_report(&self, elapsed: Duration) -> String {
+        let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+        let mut lines = vec![
+            format!("Load test finished after {:.0} s", secs),
+            format!("  {}", self.connections()),
+            format!(
+                "  connect time avg {:.0} ms max {} ms, join time avg {:.0} ms max {} ms",
+                self.connect.avg_ms(),
+                self.connect.max_ms,
+                self.join.avg_ms(),
+                self.join.max_ms
+            ),
+            format!(
+                "  received {} packets / {:.1} MB ({:.0} kbps), sent {} packets / {:.1} MB ({:.0} kbps)",
+                self.packets_received,
+                self.bytes_received as f64 / 1_000_000.0,
+                self.bytes_received as f64 * 8.0 / 1000.0 / secs,
+                self.packets_sent,
+                self.bytes_sent as f64 / 1_000_000.0,
+                self.bytes_sent as f64 * 8.0 / 1000.0 / secs
+            ),
+            format!(
+                "  rtt avg {:.0} ms max {} ms, own-car update gap avg {:.0} ms max {} ms",
+                self.rtt.avg_ms(),
+                self.rtt.max_ms,
+                self.update_gap.avg_ms(),
+                self.update_gap.max_ms
+            ),
+            format!("  last {}", self.server_health()),
+        ];
+        if let Some(worst) = self.worst_tick_rate {
+            lines.push(format!(
+                "  worst server second: {:.1} tps, worst frame {:.1} ms",
+                worst, self.worst_frame_ms
+            ));
+        }
+        lines.join("\n")
+    }
+}
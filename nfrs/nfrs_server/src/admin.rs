@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+use clap::ValueEnum;
+use std::io::BufRead;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::bots::BotDifficulty;
+
+const HELP: &str = "Commands: bots add [count] [easy|medium|hard], bots clear, replay save, help";
+
+/// Operator command typed on the server console
+#[derive(Event, Debug, Clone, PartialEq)]
+pub enum AdminCommand {
+    AddBots {
+        count: usize,
+        difficulty: BotDifficulty,
+    },
+    ClearBots,
+    /// Finish the current replay recording and write it out
+    SaveReplay,
+}
+
+impl AdminCommand {
+    /// Parse one console line. `Ok(None)` means there was nothing to do.
+    pub fn parse(line: &str) -> Result<Option<Self>, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => Ok(None),
+            ["help"] => {
+                info!("{}", HELP);
+                Ok(None)
+            }
+            ["bots", "add", rest @ ..] => {
+                let mut count = 1;
+                let mut difficulty = BotDifficulty::Medium;
+                for word in rest {
+                    if let Ok(n) = word.parse() {
+                        count = n;
+                    } else {
+                        difficulty = BotDifficulty::from_str(word, true)
+                            .map_err(|_| format!("unknown difficulty '{}'", word))?;
+                    }
+                }
+                Ok(Some(AdminCommand::AddBots { count, difficulty }))
+            }
+            ["bots", "clear"] => Ok(Some(AdminCommand::ClearBots)),
+            ["replay", "save"] => Ok(Some(AdminCommand::SaveReplay)),
+            _ => Err(format!("unknown command '{}'. {}", line.trim(), HELP)),
+        }
+    }
+}
+
+/// Lines read from stdin by the console thread
+#[derive(Resource)]
+struct ConsoleLines(Mutex<Receiver<String>>);
+
+/// Reads admin commands from the server's stdin
+pub struct AdminPlugin;
+
+impl Plugin for AdminPlugin {
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = channel();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        app.add_event::<AdminCommand>();
+        app.insert_resource(ConsoleLines(Mutex::new(receiver)));
+        app.add_systems(Update, read_console);
+    }
+}
+
+fn read_console(lines: Res<ConsoleLines>, mut commands: EventWriter<AdminCommand>) {
+    let Ok(receiver) = lines.0.lock() else {
+        return;
+    };
+    for line in receiver.try_iter() {
+        match AdminCommand::parse(&line) {
+            Ok(Some(command)) => {
+                info!("Admin command: {:?}", command);
+                commands.write(command);
+            }
+            Ok(None) => {}
+            Err(error) => warn!("{}", error),
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use bevy::prelude::*;
+use lightyear::prelude::*;
+use nfrs_shared::{NoticeLevel, ServerChannel, ServerNotice};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Systems that consume client input run in this set, between the budget refill
+/// and the enforcement pass
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InputSet;
+
+/// Tunable limits for client input
+#[derive(Resource, Debug, Clone)]
+pub struct InputLimits {
+    /// Inputs a client earns per simulation tick
+    pub inputs_per_tick: u32,
+    /// Maximum number of inputs a client can bank (absorbs network jitter)
+    pub burst: u32,
+    /// Length of the flood detection window, in ticks
+    pub window_ticks: u32,
+    /// Dropped inputs within one window before the window counts as a flood
+    pub flood_threshold: u32,
+    /// Strikes before the client is warned
+    pub warn_strikes: u32,
+    /// Strikes before the client is kicked
+    pub kick_strikes: u32,
+    /// Clean windows needed to forgive one strike
+    pub forgive_windows: u32,
+    /// Ticks between the kick notice and dropping the connection,
+    /// so the notice has a chance to reach the client
+    pub kick_grace_ticks: u32,
+    pub max_username_len: usize,
+}
+
+impl Default for InputLimits {
+    fn default() -> Self {
+        Self {
+            inputs_per_tick: 1,
+            burst: 4,
+            window_ticks: 60,
+            flood_threshold: 30,
+            warn_strikes: 2,
+            kick_strikes: 5,
+            forgive_windows: 10,
+            kick_grace_ticks: 10,
+            max_username_len: 24,
+        }
+    }
+}
+
+/// Server-wide counters, logged periodically for operators
+#[derive(Resource, Debug, Default)]
+pub struct InputStats {
+    pub accepted: u64,
+    pub dropped: u64,
+    pub malformed: u64,
+    pub floods: u64,
+    pub warnings: u64,
+    pub kicks: u64,
+}
+
+#[derive(Resource)]
+struct StatsReportTimer(Timer);
+
+/// Per-client input budget, refilled once per simulation tick
+#[derive(Component, Debug)]
+pub struct InputBudget {
+    tokens: u32,
+    window_elapsed: u32,
+    dropped_in_window: u32,
+    clean_windows: u32,
+    strikes: u32,
+    warned_at: u32,
+    kick_in: Option<u32>,
+    /// Players sending inputs over this connection
+    seats: u32,
+}
+
+impl InputBudget {
+    pub fn new(limits: &InputLimits) -> Self {
+        Self {
+            tokens: limits.burst,
+            window_elapsed: 0,
+            dropped_in_window: 0,
+            clean_windows: 0,
+            strikes: 0,
+            warned_at: 0,
+            kick_in: None,
+            seats: 1,
+        }
+    }
+
+    /// Spend one input from the budget. Returns false if the input must be dropped.
+    pub fn try_spend(&mut self) -> bool {
+        if self.kick_in.is_some() {
+            return false;
+        }
+        if self.tokens == 0 {
+            self.dropped_in_window += 1;
+            return false;
+        }
+        self.tokens -= 1;
+        true
+    }
+
+    /// Record a protocol violation (malformed or out-of-place request)
+    pub fn strike(&mut self) {
+        self.strikes += 1;
+        self.clean_windows = 0;
+    }
+
+    /// Another split-screen player joined over the same connection
+    pub fn add_seat(&mut self) {
+        self.seats += 1;
+    }
+
+    fn refill(&mut self, limits: &InputLimits) {
+        self.tokens =
+            (self.tokens + limits.inputs_per_tick * self.seats).min(limits.burst * self.seats);
+    }
+}
+
+/// Check a requested username. Returns the reason it was rejected, if any.
+pub fn validate_username(username: &str, limits: &InputLimits) -> Result<(), &'static str> {
+    let trimmed = username.trim();
+    if trimmed.is_empty() {
+        return Err("empty username");
+    }
+    if trimmed.chars().count() > limits.max_username_len {
+        return Err("username too long");
+    }
+    if trimmed.chars().any(|c| c.is_control()) {
+        return Err("username contains control characters");
+    }
+    Ok(())
+}
+
+pub struct AntiCheatPlugin;
+
+impl Plugin for AntiCheatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputLimits>();
+        app.init_resource::<InputStats>();
+        app.insert_resource(StatsReportTimer(Timer::new(
+            Duration::from_secs(10),
+            TimerMode::Repeating,
+        )));
+        app.add_systems(
+            FixedUpdate,
+            (
+                refill_input_budgets.before(InputSet),
+                enforce_input_budgets.after(InputSet),
+            ),
+        );
+        app.add_systems(Update, report_input_stats);
+    }
+}
+
+fn refill_input_budgets(limits: Res<InputLimits>, mut budgets: Query<&mut InputBudget>) {
+    for mut budget in budgets.iter_mut() {
+        budget.refill(&limits);
+    }
+}
+
+/// Close flood detection windows and escalate: drop -> warn -> kick
+fn enforce_input_budgets(
+    mut commands: Commands,
+    limits: Res<InputLimits>,
+    mut stats: ResMut<InputStats>,
+    mut clients: Query<(
+        Entity,
+        &mut InputBudget,
+        Option<&mut MessageSender<ServerNotice>>,
+    )>,
+) {
+    for (client_entity, mut budget, mut notices) in clients.iter_mut() {
+        if let Some(ticks) = budget.kick_in {
+            if ticks == 0 {
+                info!("Kicking client {:?}", client_entity);
+                commands
+                    .entity(client_entity)
+                    .trigger(Unlink {
+                        reason: "Kicked by anti-cheat".to_string(),
+                    })
+                    .despawn();
+            } else {
+                budget.kick_in = Some(ticks - 1);
+            }
+            continue;
+        }
+
+        budget.window_elapsed += 1;
+        if budget.window_elapsed >= limits.window_ticks {
+            if budget.dropped_in_window > limits.flood_threshold {
+                warn!(
+                    "Client {:?} flooded inputs: {} dropped in {} ticks",
+                    client_entity, budget.dropped_in_window, limits.window_ticks
+                );
+                stats.floods += 1;
+                budget.strike();
+            } else {
+                budget.clean_windows += 1;
+                if budget.clean_windows >= limits.forgive_windows && budget.strikes > 0 {
+                    budget.strikes -= 1;
+                    budget.warned_at = budget.warned_at.min(budget.strikes);
+                    budget.clean_windows = 0;
+                }
+            }
+            budget.window_elapsed = 0;
+            budget.dropped_in_window = 0;
+        }
+
+        if budget.strikes >= limits.kick_strikes {
+            warn!(
+                "Client {:?} reached {} strikes, kicking",
+                client_entity, budget.strikes
+            );
+            stats.kicks += 1;
+            budget.kick_in = Some(limits.kick_grace_ticks);
+            if let Some(notices) = notices.as_mut() {
+                notices.send::<ServerChannel>(ServerNotice {
+                    level: NoticeLevel::Kicked,
+                    text: "Disconnected: too many invalid or excess inputs".to_string(),
+                });
+            }
+        } else if budget.strikes >= limits.warn_strikes && budget.strikes > budget.warned_at {
+            warn!(
+                "Warning client {:?} ({} strikes)",
+                client_entity, budget.strikes
+            );
+            stats.warnings += 1;
+            budget.warned_at = budget.strikes;
+            if let Some(notices) = notices.as_mut() {
+                notices.send::<ServerChannel>(ServerNotice {
+                    level: NoticeLevel::Warning,
+                    text: "You are sending too many inputs. Continued abuse will get you kicked."
+                        .to_string(),
+                });
+            }
+        }
+    }
+}
+
+fn report_input_stats(
+    time: Res<Time>,
+    mut timer: ResMut<StatsReportTimer>,
+    stats: Res<InputStats>,
+) {
+    if timer.0.tick(time.delta()).just_finished() {
+        info!(
+            "Input stats: accepted={} dropped={} malformed={} floods={} warnings={} kicks={}",
+            stats.accepted,
+            stats.dropped,
+            stats.malformed,
+            stats.floods,
+            stats.warnings,
+            stats.kicks
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use lightyear::prelude::*;
+use nfrs_shared::{Car, CarInput, Inventory, Nitro, Player, TrackLayout};
+use rand::Rng;
+use std::collections::HashSet;
+use tracing::info;
+
+use crate::admin::AdminCommand;
+use crate::car::{free_grid_slot, player_color, spawn_car, CarSet, DriverInput, GridSlot};
+use crate::track::CurrentTrack;
+
+// Bot ids live far above connection ids so they never collide
+const BOT_ID_BASE: u64 = 1 << 32;
+// Distance at which a waypoint counts as reached
+const WAYPOINT_RADIUS: f32 = 5.0;
+// Other cars closer than this, ahead of a bot, are steered around
+const AVOID_RANGE: f32 = 6.0;
+// Bots start slowing for a corner this far before its waypoint
+const BRAKE_DISTANCE: f32 = 14.0;
+// Below this speed while on the throttle, a bot is considered stuck
+const STUCK_SPEED: f32 = 1.0;
+const CRAWL_SPEED: f32 = 3.0;
+const STUCK_SECS: f32 = 1.5;
+const REVERSE_SECS: f32 = 1.0;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum BotDifficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+/// How a difficulty level drives
+struct BotProfile {
+    /// Fraction of the car's max speed the bot is willing to reach
+    top_speed: f32,
+    /// Max sideways error (m) when aiming at a waypoint
+    aim_error: f32,
+    /// Fraction of `top_speed` kept through a 45 degree bend
+    corner_speed: f32,
+    uses_nitro: bool,
+    /// Chance per second of using a held item
+    item_rate: f32,
+}
+
+impl BotDifficulty {
+    fn profile(self) -> BotProfile {
+        match self {
+            BotDifficulty::Easy => BotProfile {
+                top_speed: 0.7,
+                aim_error: 2.5,
+                corner_speed: 0.3,
+                uses_nitro: false,
+                item_rate: 0.2,
+            },
+            BotDifficulty::Medium => BotProfile {
+                top_speed: 0.85,
+                aim_error: 1.2,
+                corner_speed: 0.35,
+                uses_nitro: true,
+                item_rate: 0.5,
+            },
+            BotDifficulty::Hard => BotProfile {
+                top_speed: 1.0,
+                aim_error: 0.3,
+                corner_speed: 0.42,
+                uses_nitro: true,
+                item_rate: 2.0,
+            },
+        }
+    }
+}
+
+/// Bots to spawn at startup
+#[derive(Resource, Debug, Clone, Default)]
+pub struct BotConfig {
+    pub count: usize,
+    pub difficulty: BotDifficulty,
+}
+
+/// Server-side driver state of a bot car
+#[derive(Component, Debug)]
+pub struct Bot {
+    pub difficulty: BotDifficulty,
+    waypoint: usize,
+    /// Sideways offset from the racing line for the current waypoint
+    aim_offset: f32,
+    stuck_for: f32,
+    reversing_for: f32,
+}
+
+#[derive(Resource, Default)]
+struct BotRoster {
+    spawned: u64,
+}
+
+pub struct BotPlugin;
+
+impl Plugin for BotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BotConfig>();
+        app.init_resource::<BotRoster>();
+        // Also registered by the admin console, which is optional
+        app.add_event::<AdminCommand>();
+        app.add_systems(Startup, spawn_configured_bots);
+        app.add_systems(Update, handle_admin_commands);
+        app.add_systems(FixedUpdate, drive_bots.in_set(CarSet::Input));
+    }
+}
+
+fn spawn_configured_bots(
+    mut commands: Commands,
+    config: Res<BotConfig>,
+    track: Res<CurrentTrack>,
+    mut roster: ResMut<BotRoster>,
+) {
+    spawn_bots(
+        &mut commands,
+        &track.0,
+        &mut roster,
+        config.count,
+        config.difficulty,
+        HashSet::new(),
+        Vec::new(),
+    );
+}
+
+fn handle_admin_commands(
+    mut commands: Commands,
+    mut events: EventReader<AdminCommand>,
+    track: Res<CurrentTrack>,
+    mut roster: ResMut<BotRoster>,
+    cars: Query<&GridSlot, With<Car>>,
+    bots: Query<Entity, With<Bot>>,
+    clients: Query<Entity, With<ReplicationSender>>,
+) {
+    for event in events.read() {
+        match *event {
+            AdminCommand::AddBots { count, difficulty } => spawn_bots(
+                &mut commands,
+                &track.0,
+                &mut roster,
+                count,
+                difficulty,
+                cars.iter().map(|slot| slot.0).collect(),
+                clients.iter().collect(),
+            ),
+            AdminCommand::ClearBots => {
+                for bot in bots.iter() {
+                    commands.entity(bot).despawn();
+                }
+                info!("Removed {} bots", bots.iter().count());
+            }
+            AdminCommand::SaveReplay => {}
+        }
+    }
+}
+
+fn spawn_bots(
+    commands: &mut Commands,
+    track: &TrackLayout,
+    roster: &mut BotRoster,
+    count: usize,
+    difficulty: BotDifficulty,
+    mut taken: HashSet<usize>,
+    clients: Vec<Entity>,
+) {
+    for _ in 0..count {
+        roster.spawned += 1;
+        let client_id = BOT_ID_BASE + roster.spawned;
+        let player = Player {
+            client_id,
+            username: format!("Bot {}", roster.spawned),
+            color: player_color(client_id),
+            is_bot: true,
+        };
+        let slot = free_grid_slot(&taken);
+        taken.insert(slot);
+        let waypoint = first_waypoint(track, &track.grid_slot(slot));
+        let car = spawn_car(
+            commands,
+            player,
+            track,
+            slot,
+            Replicate::manual(clients.clone()),
+        );
+        commands.entity(car).insert(Bot {
+            difficulty,
+            waypoint,
+            aim_offset: 0.0,
+            stuck_for: 0.0,
+            reversing_for: 0.0,
+        });
+    }
+    if count > 0 {
+        info!("Spawned {} {:?} bots", count, difficulty);
+    }
+}
+
+/// Closest waypoint in front of the car
+fn first_waypoint(track: &TrackLayout, transform: &Transform) -> usize {
+    let position = transform.translation.truncate();
+    let forward = (transform.rotation * Vec3::Y).truncate();
+    track
+        .racing_line
+        .iter()
+        .enumerate()
+        .filter(|(_, point)| forward.dot(**point - position) > 0.0)
+        .min_by(|(_, a), (_, b)| {
+            a.distance_squared(position)
+                .total_cmp(&b.distance_squared(position))
+        })
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
+
+/// Steer bots along the racing line, producing the same inputs a player would
+fn drive_bots(
+    time: Res<Time>,
+    track: Res<CurrentTrack>,
+    mut bots: Query<(Entity, &Car, &Transform, &Velocity, &mut Bot)>,
+    mut drivers: Query<(&mut DriverInput, &Inventory, &Nitro)>,
+    others: Query<(Entity, &Transform), With<Car>>,
+) {
+    let line = &track.0.racing_line;
+    if line.is_empty() {
+        return;
+    }
+    let dt = time.delta_secs();
+    let mut rng = rand::thread_rng();
+
+    for (entity, car, transform, velocity, mut bot) in bots.iter_mut() {
+        let Ok((mut driver, inventory, nitro)) = drivers.get_mut(entity) else {
+            continue;
+        };
+        let profile = bot.difficulty.profile();
+        let position = transform.translation.truncate();
+        let forward = (transform.rotation * Vec3::Y).truncate();
+        let right = Vec2::new(forward.y, -forward.x);
+        let speed = velocity.linvel.length();
+
+        // Advance along the line, picking a new imprecise aim point each time
+        let waypoint = line[bot.waypoint % line.len()];
+        if waypoint.distance(position) < WAYPOINT_RADIUS {
+            bot.waypoint = (bot.waypoint + 1) % line.len();
+            bot.aim_offset = rng.gen_range(-1.0..=1.0) * profile.aim_error;
+        }
+        let previous = line[(bot.waypoint + line.len() - 1) % line.len()];
+        let waypoint = line[bot.waypoint % line.len()];
+        let next = line[(bot.waypoint + 1) % line.len()];
+        let line_side = (next - waypoint).normalize_or_zero().perp();
+        let mut target = waypoint + line_side * bot.aim_offset;
+
+        // Steer around cars close ahead
+        for (other, other_transform) in others.iter() {
+            if other == entity {
+                continue;
+            }
+            let offset = other_transform.translation.truncate() - position;
+            let distance = offset.length();
+            if distance == 0.0 || distance > AVOID_RANGE || forward.dot(offset / distance) < 0.6 {
+                continue;
+            }
+            let away = if right.dot(offset) > 0.0 { -1.0 } else { 1.0 };
+            target += right * away * (AVOID_RANGE - distance);
+        }
+
+        let to_target = target - position;
+        let heading_error = forward.angle_to(to_target);
+
+        let mut input = CarInput::default();
+
+        // Aim for a turn rate proportional to the heading error
+        let desired_angvel = (heading_error * 3.0).clamp(-car.steering_speed, car.steering_speed);
+        if velocity.angvel < desired_angvel - 0.2 {
+            input.left = true;
+        } else if velocity.angvel > desired_angvel + 0.2 {
+            input.right = true;
+        }
+
+        if bot.reversing_for > 0.0 {
+            // Back out of whatever we are stuck on, still turning towards the target
+            bot.reversing_for -= dt;
+            input.backward = true;
+        } else {
+            // Slow down ahead of sharp turns in the line, or while still turning into one
+            let top_speed = car.max_speed * profile.top_speed;
+            let mut turn = heading_error.abs();
+            if waypoint.distance(position) < BRAKE_DISTANCE {
+                turn = turn.max((waypoint - previous).angle_to(next - waypoint).abs());
+            }
+            let corner = turn > 0.2;
+            let target_speed = if heading_error.abs() > 1.2 {
+                // Facing the wrong way: crawl while turning around
+                CRAWL_SPEED
+            } else {
+                let sharpness = (turn / std::f32::consts::FRAC_PI_4).min(1.0);
+                top_speed * (1.0 - sharpness * (1.0 - profile.corner_speed))
+            };
+            if speed > target_speed + 1.0 {
+                input.backward = true;
+            } else {
+                input.forward = speed < target_speed;
+            }
+
+            bot.stuck_for = if speed < STUCK_SPEED {
+                bot.stuck_for + dt
+            } else {
+                0.0
+            };
+            if bot.stuck_for > STUCK_SECS {
+                bot.stuck_for = 0.0;
+                bot.reversing_for = REVERSE_SECS;
+            }
+
+            input.boost = profile.uses_nitro
+                && !corner
+                && heading_error.abs() < 0.15
+                && (nitro.boosting || nitro.meter > 0.5);
+        }
+
+        // Release the button after every use so the next press registers
+        input.use_item = inventory.item.is_some()
+            && !driver.input.use_item
+            && rng.gen_bool((profile.item_rate * dt).min(1.0) as f64);
+
+        driver.set(input);
+    }
+}
@@ -1,17 +1,53 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use lightyear::prelude::*;
-use nfrs_shared::{Car, CarInput, Player, PlayerPosition, SERVER_REPLICATION_INTERVAL};
-use tracing::{info, warn};
-
-// Resource to track which car entity belongs to which client entity
+use nfrs_shared::{
+    Car, CarDamage, CarInput, CarMotion, CarVelocity, ChatLine, ChatSend, GhostDownload,
+    GhostRequest, GhostUpload, ImpactEvent, Inventory, ItemEffects, JoinAccepted, Leaderboard,
+    LeaderboardRequest, Nitro, Player, PlayerPosition, RaceProgress, ServerChannel, ServerNotice,
+    ServerStatus, TrackLayout, CAR_ANGULAR_DAMPING, MAX_LOCAL_PLAYERS, SERVER_REPLICATION_INTERVAL,
+};
+use tracing::{info, trace, warn};
+
+use crate::anti_cheat::{validate_username, InputBudget, InputLimits, InputSet, InputStats};
+use crate::chat::{ChatBudget, ChatName, SystemMessage};
+use crate::collision::{PreStepVelocity, Wall};
+use crate::laps::LapTimer;
+use crate::stats::Odometer;
+use crate::track::{CurrentSurface, CurrentTrack};
+
+// Resource to track which car entity belongs to which client entity and seat
 #[derive(Resource, Default)]
 struct ClientCarMap {
-    client_to_car: HashMap<Entity, Entity>,
+    client_to_car: HashMap<(Entity, u8), Entity>,
+}
+
+/// Starting grid slot a car was spawned on, kept so later cars never spawn on top of it
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GridSlot(pub usize);
+
+/// Lowest grid slot not taken by any car
+pub fn free_grid_slot(taken: &HashSet<usize>) -> usize {
+    // One of the first `taken.len() + 1` slots is always free
+    (0..=taken.len())
+        .find(|slot| !taken.contains(slot))
+        .expect("more slots than cars")
+}
+
+// Split-screen players after the first get ids above those of connections and bots
+const SEAT_ID_STRIDE: u64 = 1 << 40;
+
+/// `Player::client_id` of the car driven from a seat of a client connection
+pub fn seat_client_id(client_entity: Entity, seat: u8) -> u64 {
+    client_entity.index() as u64 + seat as u64 * SEAT_ID_STRIDE
 }
 
+/// Marks a client connection that joined to watch; it never gets a car
+#[derive(Component)]
+pub struct Spectator;
+
 // Marker component for clients that need initial state sync
 // Includes a frame counter to delay sync until after Replicate components are updated
 #[derive(Component)]
@@ -19,13 +55,81 @@ struct NeedsInitialSync {
     frames_to_wait: u8,
 }
 
+// How many ticks an accepted input keeps driving the car.
+// Covers clients whose frame rate is below the simulation tick rate.
+const INPUT_HOLD_TICKS: u8 = 3;
+
+/// Latest accepted input for a car, applied at most once per simulation tick
+#[derive(Component, Default)]
+pub struct DriverInput {
+    pub input: CarInput,
+    pub ticks_left: u8,
+    /// Set when `use_item` goes from released to pressed; cleared by whoever consumes it
+    pub use_item_pressed: bool,
+}
+
+impl DriverInput {
+    /// Store a new input, tracking the press edge of one-shot actions
+    pub fn set(&mut self, input: CarInput) {
+        let was_held = self.ticks_left > 0 && self.input.use_item;
+        self.use_item_pressed |= input.use_item && !was_held;
+        self.input = input;
+        self.ticks_left = INPUT_HOLD_TICKS;
+    }
+}
+
+/// Multiplier on `Car::max_speed` for the current tick.
+/// Reset to 1.0 every tick, then scaled by the systems in [`CarSet::Modifiers`].
+#[derive(Component)]
+pub struct SpeedModifier(pub f32);
+
+impl Default for SpeedModifier {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// Per-tick stages of the car simulation, all inside [`InputSet`]
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CarSet {
+    /// Systems that write `DriverInput` (network clients and bots)
+    Input,
+    /// Systems that scale the `SpeedModifier` of cars
+    Modifiers,
+    /// Player input is turned into velocity
+    Drive,
+    /// Forces that act regardless of input (grip, boosts)
+    Forces,
+}
+
 pub struct CarPlugin;
 
 impl Plugin for CarPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<ClientCarMap>();
         app.add_systems(Startup, spawn_boundaries);
-        app.add_systems(FixedUpdate, apply_car_input);
+        app.configure_sets(
+            FixedUpdate,
+            (
+                CarSet::Input,
+                CarSet::Modifiers,
+                CarSet::Drive,
+                CarSet::Forces,
+            )
+                .chain()
+                .in_set(InputSet),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (
+                reset_speed_modifiers.before(CarSet::Modifiers),
+                receive_car_input.in_set(CarSet::Input),
+                apply_car_input.in_set(CarSet::Drive),
+                limit_speed.after(CarSet::Forces),
+                publish_velocity.after(limit_speed),
+            )
+                .in_set(InputSet),
+        );
         app.add_observer(handle_new_client);
         app.add_observer(handle_client_disconnect);
         // Add receiver for JoinRequest
@@ -46,24 +150,28 @@ fn spawn_boundaries(mut commands: Commands) {
     commands.spawn((
         Transform::from_xyz(0.0, hy, 0.0),
         Collider::cuboid(hx, thickness),
+        Wall,
     ));
 
     // Bottom Wall
     commands.spawn((
         Transform::from_xyz(0.0, -hy, 0.0),
         Collider::cuboid(hx, thickness),
+        Wall,
     ));
 
     // Left Wall
     commands.spawn((
         Transform::from_xyz(-33.0, 0.0, 0.0),
         Collider::cuboid(thickness, hy),
+        Wall,
     ));
 
     // Right Wall
     commands.spawn((
         Transform::from_xyz(33.0, 0.0, 0.0),
         Collider::cuboid(thickness, hy),
+        Wall,
     ));
 
     info!("Spawned map boundaries");
@@ -83,7 +191,7 @@ fn debug_cars(query: Query<Entity, (With<Car>, With<Replicate>)>, time: Res<Time
 fn sync_initial_state(
     mut commands: Commands,
     mut new_clients: Query<(Entity, &mut NeedsInitialSync)>,
-    mut cars: Query<(Entity, &mut Transform, Option<&mut Velocity>, &Player), With<Car>>,
+    mut replicated: Query<(Entity, &mut Transform, Option<&mut Velocity>), With<Replicate>>,
 ) {
     for (client, mut sync_marker) in new_clients.iter_mut() {
         if sync_marker.frames_to_wait > 0 {
@@ -96,11 +204,11 @@ fn sync_initial_state(
             continue;
         }
 
-        // Mark all car components as changed to force replication
-        for (car_entity, mut transform, velocity, player) in cars.iter_mut() {
+        // Mark all replicated components as changed to force replication
+        for (entity, mut transform, velocity) in replicated.iter_mut() {
             info!(
-                "Syncing car {:?} (client_id: {}) position: {:?}",
-                car_entity, player.client_id, transform.translation
+                "Syncing entity {:?} position: {:?}",
+                entity, transform.translation
             );
             transform.set_changed();
             if let Some(mut vel) = velocity {
@@ -120,8 +228,9 @@ fn handle_new_client(
     mut commands: Commands,
     // Query all existing client connections
     client_connections: Query<Entity, With<ReplicationSender>>,
-    // Query all existing cars to update their replication
-    mut existing_cars: Query<(Entity, &mut Replicate), With<Car>>,
+    // Query all replicated entities (cars, track) to update their replication
+    replicated: Query<Entity, With<Replicate>>,
+    limits: Res<InputLimits>,
 ) {
     let client_entity = trigger.target();
     info!("New client entity {:?} connected", client_entity);
@@ -150,19 +259,52 @@ fn handle_new_client(
         .entity(client_entity)
         .insert(MessageReceiver::<nfrs_shared::JoinRequest>::default());
 
+    // Add notice sender and input budget for anti-cheat
+    commands.entity(client_entity).insert((
+        MessageSender::<ServerNotice>::default(),
+        InputBudget::new(&limits),
+    ));
+
+    // Add impact event sender for client-side effects, and status reports
+    commands.entity(client_entity).insert((
+        MessageSender::<ImpactEvent>::default(),
+        MessageSender::<ServerStatus>::default(),
+    ));
+
+    // Join confirmation and ghost exchange
+    commands.entity(client_entity).insert((
+        MessageSender::<JoinAccepted>::default(),
+        MessageReceiver::<GhostUpload>::default(),
+        MessageReceiver::<GhostRequest>::default(),
+        MessageSender::<GhostDownload>::default(),
+    ));
+
+    // Leaderboard queries
+    commands.entity(client_entity).insert((
+        MessageReceiver::<LeaderboardRequest>::default(),
+        MessageSender::<Leaderboard>::default(),
+    ));
+
+    // Chat, once the client has joined under a name
+    commands.entity(client_entity).insert((
+        MessageReceiver::<ChatSend>::default(),
+        MessageSender::<ChatLine>::default(),
+        ChatBudget::default(),
+    ));
+
     // Get all client entities (existing + new one) for replication
     let mut all_clients: Vec<Entity> = client_connections.iter().collect();
     all_clients.push(client_entity);
 
-    // Update all existing cars to also replicate to this new client
-    for (existing_car, _) in existing_cars.iter_mut() {
+    // Update all replicated entities to also replicate to this new client
+    for existing in replicated.iter() {
         // Replace the Replicate component with a new one that includes all clients
         commands
-            .entity(existing_car)
+            .entity(existing)
             .insert(Replicate::manual(all_clients.clone()));
         info!(
-            "Updated car {:?} to replicate to new client {:?}",
-            existing_car, client_entity
+            "Updated entity {:?} to replicate to new client {:?}",
+            existing, client_entity
         );
     }
 
@@ -176,37 +318,102 @@ fn handle_new_client(
     info!("Client initialized, waiting for JoinRequest...");
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_join_request(
     mut commands: Commands,
-    mut message_receivers: Query<(Entity, &mut MessageReceiver<nfrs_shared::JoinRequest>)>,
+    mut message_receivers: Query<(
+        Entity,
+        &mut MessageReceiver<nfrs_shared::JoinRequest>,
+        &mut MessageSender<JoinAccepted>,
+        &mut InputBudget,
+    )>,
     mut car_map: ResMut<ClientCarMap>,
     client_connections: Query<Entity, With<ReplicationSender>>,
-    mut existing_cars: Query<(Entity, &mut Replicate), With<Car>>,
+    replicated: Query<Entity, With<Replicate>>,
+    limits: Res<InputLimits>,
+    mut stats: ResMut<InputStats>,
+    track: Res<CurrentTrack>,
+    cars: Query<&GridSlot, With<Car>>,
+    mut messages: EventWriter<SystemMessage>,
 ) {
-    for (client_entity, mut receiver) in message_receivers.iter_mut() {
-        if let Some(request) = receiver.receive().next() {
-            let client_id = client_entity.index() as u64;
+    // Cars spawned this frame are not visible to the query yet, so they are added as they go
+    let mut taken: HashSet<usize> = cars.iter().map(|slot| slot.0).collect();
+    for (client_entity, mut receiver, mut accepted, mut budget) in message_receivers.iter_mut() {
+        // One request per local player, in the same frame for split-screen clients
+        for request in receiver.receive() {
+            let seat = request.seat;
+            let client_id = seat_client_id(client_entity, seat);
             info!(
                 "Received JoinRequest from client {}: {:?}",
                 client_id, request
             );
 
+            if seat >= MAX_LOCAL_PLAYERS {
+                warn!(
+                    "Rejected JoinRequest from client {}: no seat {}",
+                    client_id, seat
+                );
+                stats.malformed += 1;
+                budget.strike();
+                continue;
+            }
+
             // Check if client already has a car
-            if car_map.client_to_car.contains_key(&client_entity) {
+            if car_map.client_to_car.contains_key(&(client_entity, seat)) {
                 warn!(
                     "Client {} already has a car, ignoring JoinRequest",
                     client_id
                 );
+                stats.malformed += 1;
+                budget.strike();
+                continue;
+            }
+
+            // Spectating is for the whole connection, so not while any of its players drive
+            if request.spectator
+                && car_map
+                    .client_to_car
+                    .keys()
+                    .any(|(client, _)| *client == client_entity)
+            {
+                warn!(
+                    "Client {} has a car, ignoring spectator JoinRequest",
+                    client_id
+                );
+                stats.malformed += 1;
+                budget.strike();
                 continue;
             }
 
-            // Generate unique color based on client_id to be deterministic/simple for now
-            // or modify to use Golden Ratio if needed.
-            // Simple HSL generation:
-            let hue = (client_id as f32 * 137.508) % 360.0; // Golden angle approximation
-            let color = Color::hsl(hue, 0.8, 0.5);
-            let color_rgba = color.to_srgba();
-            let color_array = [color_rgba.red, color_rgba.green, color_rgba.blue];
+            if let Err(reason) = validate_username(&request.username, &limits) {
+                warn!("Rejected JoinRequest from client {}: {}", client_id, reason);
+                stats.malformed += 1;
+                budget.strike();
+                continue;
+            }
+
+            let username = request.username.trim().to_string();
+            if seat == 0 {
+                commands
+                    .entity(client_entity)
+                    .insert(ChatName(username.clone()));
+            }
+
+            if request.spectator {
+                info!(
+                    "Client {} ('{}') joined as a spectator",
+                    client_id, username
+                );
+                commands.entity(client_entity).insert(Spectator);
+                messages.write(SystemMessage(format!("{} is spectating", username)));
+                accepted.send::<ServerChannel>(JoinAccepted {
+                    client_id,
+                    track: track.0.name.to_string(),
+                    seat,
+                });
+                continue;
+            }
+            commands.entity(client_entity).remove::<Spectator>();
 
             // Get all client entities for replication
             let all_clients: Vec<Entity> = client_connections.iter().collect();
@@ -215,36 +422,32 @@ fn handle_join_request(
             let replicate = Replicate::manual(all_clients.clone());
 
             // Spawn car
-            let car_entity = commands
-                .spawn((
-                    Car {
-                        max_speed: 20.0,
-                        acceleration: 10.0,
-                        steering_speed: 2.0,
-                    },
-                    Player {
-                        client_id,
-                        username: request.username.clone(),
-                        color: color_array,
-                    },
-                    PlayerPosition::default(),
-                    Transform::from_xyz(0.0, 0.0, 0.0),
-                    GlobalTransform::default(),
-                    RigidBody::Dynamic,
-                    Collider::cuboid(1.0, 2.0),
-                    Velocity::default(),
-                    GravityScale(0.0),
-                    Damping {
-                        linear_damping: 2.0,
-                        angular_damping: 2.0,
-                    },
-                    replicate,
-                    ReplicationGroup::default(),
-                ))
-                .id();
+            let player = Player {
+                client_id,
+                username: username.clone(),
+                color: player_color(client_id),
+                is_bot: false,
+            };
+            let slot = free_grid_slot(&taken);
+            taken.insert(slot);
+            let car_entity = spawn_car(&mut commands, player, &track.0, slot, replicate);
+            messages.write(SystemMessage(format!("{} joined the race", username)));
+
+            accepted.send::<ServerChannel>(JoinAccepted {
+                client_id,
+                track: track.0.name.to_string(),
+                seat,
+            });
+
+            // Every player on the connection sends inputs
+            if seat > 0 {
+                budget.add_seat();
+            }
 
             // Update map
-            car_map.client_to_car.insert(client_entity, car_entity);
+            car_map
+                .client_to_car
+                .insert((client_entity, seat), car_entity);
             info!(
                 "Spawned car {:?} for user '{}' (client {:?})",
                 car_entity, request.username, client_entity
@@ -267,37 +470,119 @@ fn handle_join_request(
             // But currently cars are only spawned when joined.
             // Let's stick to: Update existing cars to replicate to ALL connected clients.
 
-            for (existing_car, _) in existing_cars.iter_mut() {
+            for existing in replicated.iter() {
                 commands
-                    .entity(existing_car)
+                    .entity(existing)
                     .insert(Replicate::manual(all_clients.clone()));
             }
         }
     }
 }
 
+/// Unique color for a player, based on their id
+pub fn player_color(client_id: u64) -> [f32; 3] {
+    // Generate unique color based on client_id to be deterministic/simple for now
+    // or modify to use Golden Ratio if needed.
+    // Simple HSL generation:
+    let hue = (client_id as f32 * 137.508) % 360.0; // Golden angle approximation
+    let color = Color::hsl(hue, 0.8, 0.5);
+    let color_rgba = color.to_srgba();
+    [color_rgba.red, color_rgba.green, color_rgba.blue]
+}
+
+/// Spawn a car on a grid slot with the full server-side simulation state.
+/// Used for both human players and bots.
+pub fn spawn_car(
+    commands: &mut Commands,
+    player: Player,
+    track: &TrackLayout,
+    slot: usize,
+    replicate: Replicate,
+) -> Entity {
+    commands
+        .spawn((
+            Car {
+                max_speed: 20.0,
+                acceleration: 10.0,
+                steering_speed: 2.0,
+            },
+            player,
+            PlayerPosition::default(),
+            CarDamage::default(),
+            CarVelocity::default(),
+            track.grid_slot(slot),
+            GridSlot(slot),
+            GlobalTransform::default(),
+            (
+                RigidBody::Dynamic,
+                Collider::cuboid(1.0, 2.0),
+                Velocity::default(),
+                GravityScale(0.0),
+                Damping {
+                    linear_damping: 2.0,
+                    angular_damping: CAR_ANGULAR_DAMPING,
+                },
+                ActiveEvents::COLLISION_EVENTS,
+                CollidingEntities::default(),
+                PreStepVelocity::default(),
+            ),
+            (
+                DriverInput::default(),
+                SpeedModifier::default(),
+                CurrentSurface::default(),
+                Inventory::default(),
+                ItemEffects::default(),
+                Nitro::default(),
+                RaceProgress::default(),
+                LapTimer::default(),
+                Odometer::default(),
+            ),
+            replicate,
+            ReplicationGroup::default(),
+        ))
+        .id()
+}
+
 /// Handle client disconnections and cleanup their car
+#[allow(clippy::too_many_arguments)]
 fn handle_client_disconnect(
     trigger: Trigger<OnRemove, LinkOf>,
     mut car_map: ResMut<ClientCarMap>,
     mut commands: Commands,
     client_connections: Query<Entity, With<ReplicationSender>>,
-    remaining_cars: Query<Entity, With<Car>>,
+    replicated: Query<Entity, With<Replicate>>,
+    players: Query<&Player>,
+    names: Query<&ChatName>,
+    mut messages: EventWriter<SystemMessage>,
 ) {
     let client_entity = trigger.target();
     info!("Client entity {:?} disconnecting", client_entity);
 
-    // Look up the car entity for this client
-    let despawned_car = car_map.client_to_car.remove(&client_entity);
+    // Look up the cars of every player on this client
+    let mut despawned_cars = Vec::new();
+    car_map.client_to_car.retain(|(client, _), car| {
+        if *client == client_entity {
+            despawned_cars.push(*car);
+        }
+        *client != client_entity
+    });
 
-    if let Some(car_entity) = despawned_car {
+    if despawned_cars.is_empty() {
+        warn!("No car found for disconnecting client {:?}", client_entity);
+        // Spectators have no car to take their name from
+        if let Ok(name) = names.get(client_entity) {
+            messages.write(SystemMessage(format!("{} left", name.0)));
+        }
+    }
+    for car_entity in despawned_cars.iter() {
+        if let Ok(player) = players.get(*car_entity) {
+            messages.write(SystemMessage(format!("{} left", player.username)));
+        }
         info!(
             "Despawning car {:?} for disconnected client {:?}",
             car_entity, client_entity
         );
-        commands.entity(car_entity).despawn();
-    } else {
-        warn!("No car found for disconnecting client {:?}", client_entity);
+        commands.entity(*car_entity).despawn();
     }
 
     // Get remaining client connections (excluding the disconnected one)
@@ -311,71 +596,114 @@ fn handle_client_disconnect(
         remaining_clients.len()
     );
 
-    // Update all remaining cars to replicate only to remaining clients
-    // Skip the car we just despawned (since despawn is deferred)
-    for car in remaining_cars.iter() {
-        if Some(car) == despawned_car {
-            continue; // Skip the car we just despawned
+    // Update all remaining replicated entities to replicate only to remaining clients
+    // Skip the cars we just despawned (since despawn is deferred)
+    for entity in replicated.iter() {
+        if despawned_cars.contains(&entity) {
+            continue; // Skip the cars we just despawned
         }
         commands
-            .entity(car)
+            .entity(entity)
             .insert(Replicate::manual(remaining_clients.clone()));
     }
 }
 
-fn apply_car_input(
-    mut query: Query<(&Player, &Car, &mut Velocity, &Transform)>,
-    mut input_receivers: Query<(Entity, &mut MessageReceiver<CarInput>)>,
+/// Drain client inputs through their budget and keep only the latest accepted one for
+/// each of the client's players
+fn receive_car_input(
+    car_map: Res<ClientCarMap>,
+    mut stats: ResMut<InputStats>,
+    mut input_receivers: Query<(Entity, &mut MessageReceiver<CarInput>, &mut InputBudget)>,
+    mut drivers: Query<&mut DriverInput>,
 ) {
-    for (client_entity, mut input_receiver) in input_receivers.iter_mut() {
-        // Use client entity index as identifier
-        let client_id = client_entity.index() as u64;
-
-        // Get the latest input for this client
+    for (client_entity, mut input_receiver, mut budget) in input_receivers.iter_mut() {
+        let mut latest = HashMap::new();
         for input in input_receiver.receive() {
-            info!("Received input from client {}: {:?}", client_id, input);
-
-            // Find the player's car
-            for (player, car, mut velocity, transform) in query.iter_mut() {
-                if player.client_id == client_id {
-                    // Apply physics based on input
-                    let forward = transform.rotation * Vec3::Y;
-                    let forward_2d = Vec2::new(forward.x, forward.y);
-
-                    let mut linear_vel = velocity.linvel;
-                    let mut angular_vel = velocity.angvel;
-
-                    // Forward/backward
-                    if input.forward {
-                        linear_vel += forward_2d * car.acceleration * 0.016; // Assuming 60 FPS
-                    }
-                    if input.backward {
-                        linear_vel -= forward_2d * car.acceleration * 0.016;
-                    }
-
-                    // Steering
-                    if input.left {
-                        angular_vel += car.steering_speed * 0.016;
-                    }
-                    if input.right {
-                        angular_vel -= car.steering_speed * 0.016;
-                    }
-
-                    // Clamp speed
-                    let speed = linear_vel.length();
-                    if speed > car.max_speed {
-                        linear_vel = linear_vel.normalize() * car.max_speed;
-                    }
-
-                    velocity.linvel = linear_vel;
-                    velocity.angvel = angular_vel;
-
-                    info!(
-                        "Applied input to car {}: linvel={:?}, angvel={}, rotation={:?}",
-                        client_id, linear_vel, angular_vel, transform.rotation
-                    );
-                }
+            if budget.try_spend() {
+                stats.accepted += 1;
+                latest.insert(input.seat, input);
+            } else {
+                stats.dropped += 1;
             }
         }
+
+        for (seat, input) in latest {
+            trace!(
+                "Received input from client {:?} seat {}: {:?}",
+                client_entity,
+                seat,
+                input
+            );
+
+            // Inputs sent before the JoinRequest was handled have no car to drive
+            if let Some(mut driver) = car_map
+                .client_to_car
+                .get(&(client_entity, seat))
+                .and_then(|car| drivers.get_mut(*car).ok())
+            {
+                driver.set(input);
+            }
+        }
+    }
+}
+
+/// Copy the physics velocity into the replicated one
+fn publish_velocity(mut cars: Query<(&Velocity, &mut CarVelocity)>) {
+    for (velocity, mut published) in cars.iter_mut() {
+        published.0 = velocity.linvel;
+    }
+}
+
+fn reset_speed_modifiers(mut modifiers: Query<&mut SpeedModifier>) {
+    for mut modifier in modifiers.iter_mut() {
+        modifier.0 = 1.0;
+    }
+}
+
+fn apply_car_input(
+    mut query: Query<(
+        &Car,
+        &mut DriverInput,
+        &mut Velocity,
+        &Transform,
+        &SpeedModifier,
+    )>,
+) {
+    for (car, mut driver, mut velocity, transform, modifier) in query.iter_mut() {
+        if driver.ticks_left == 0 {
+            continue;
+        }
+        driver.ticks_left -= 1;
+        let input = driver.input;
+
+        // The same model replays step through, so keep it in the shared crate
+        let forward = (transform.rotation * Vec3::Y).truncate();
+        let mut motion = CarMotion {
+            linear: velocity.linvel,
+            angular: velocity.angvel,
+        };
+        car.drive(&mut motion, forward, &input, modifier.0);
+        velocity.linvel = motion.linear;
+        velocity.angvel = motion.angular;
+
+        trace!(
+            "Applied input {:?}: linvel={:?}, angvel={}, rotation={:?}",
+            input,
+            motion.linear,
+            motion.angular,
+            transform.rotation
+        );
+    }
+}
+
+/// Smoothly pull cars back under their current speed limit
+fn limit_speed(time: Res<Time>, mut cars: Query<(&Car, &SpeedModifier, &mut Velocity)>) {
+    for (car, modifier, mut velocity) in cars.iter_mut() {
+        let mut motion = CarMotion {
+            linear: velocity.linvel,
+            angular: velocity.angvel,
+        };
+        car.limit_speed(&mut motion, modifier.0, time.delta_secs());
+        velocity.linvel = motion.linear;
     }
 }
@@ -0,0 +1,149 @@
+use bevy::prelude::*;
+use lightyear::prelude::*;
+use nfrs_shared::{ChatLine, ChatSend, NoticeLevel, ServerChannel, ServerNotice, MAX_CHAT_LEN};
+use tracing::{info, warn};
+
+use crate::anti_cheat::{InputBudget, InputStats};
+
+// Lines a player can send in a row, and how quickly they earn another
+const CHAT_BURST: f32 = 5.0;
+const CHAT_LINES_PER_SECOND: f32 = 0.5;
+
+/// A line from the server itself (joins, leaves, race results), relayed to every client
+#[derive(Event, Debug, Clone)]
+pub struct SystemMessage(pub String);
+
+/// Name a client connection chats under: the username of its first player
+#[derive(Component, Debug, Clone)]
+pub struct ChatName(pub String);
+
+/// Per-client chat allowance, refilled over time
+#[derive(Component, Debug)]
+pub struct ChatBudget {
+    tokens: f32,
+    /// Last line relayed, to drop repeats
+    last: Option<String>,
+    /// Told about the rate limit since the budget last ran out
+    warned: bool,
+}
+
+impl Default for ChatBudget {
+    fn default() -> Self {
+        Self {
+            tokens: CHAT_BURST,
+            last: None,
+            warned: false,
+        }
+    }
+}
+
+/// Check a chat line. Returns the text to relay, or the reason it was rejected.
+pub fn validate_chat(text: &str) -> Result<&str, &'static str> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return Err("empty chat line");
+    }
+    if trimmed.chars().count() > MAX_CHAT_LEN {
+        return Err("chat line too long");
+    }
+    if trimmed.chars().any(|c| c.is_control()) {
+        return Err("chat line contains control characters");
+    }
+    Ok(trimmed)
+}
+
+pub struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SystemMessage>();
+        app.add_systems(Update, relay_chat);
+    }
+}
+
+/// Check the lines players sent and pass them on to everyone, with the server's own
+#[allow(clippy::type_complexity)]
+fn relay_chat(
+    time: Res<Time>,
+    mut system: EventReader<SystemMessage>,
+    mut stats: ResMut<InputStats>,
+    mut senders: Query<(
+        Entity,
+        &mut MessageReceiver<ChatSend>,
+        &mut ChatBudget,
+        &mut InputBudget,
+        Option<&ChatName>,
+        Option<&mut MessageSender<ServerNotice>>,
+    )>,
+    mut listeners: Query<&mut MessageSender<ChatLine>>,
+) {
+    let mut lines: Vec<ChatLine> = system
+        .read()
+        .map(|message| {
+            info!("[chat] {}", message.0);
+            ChatLine {
+                from: None,
+                text: message.0.clone(),
+            }
+        })
+        .collect();
+
+    let refill = CHAT_LINES_PER_SECOND * time.delta_secs();
+    for (client, mut receiver, mut chat, mut budget, name, mut notices) in senders.iter_mut() {
+        chat.tokens = (chat.tokens + refill).min(CHAT_BURST);
+        if chat.tokens >= 1.0 {
+            chat.warned = false;
+        }
+        for message in receiver.receive() {
+            // Only players who joined have a name to chat under
+            let Some(name) = name else {
+                warn!("Client {:?} chatted before joining", client);
+                stats.malformed += 1;
+                budget.strike();
+                continue;
+            };
+            let text = match validate_chat(&message.text) {
+                Ok(text) => text,
+                Err(reason) => {
+                    // The client never sends these, so they are not typos
+                    warn!("Rejected chat from {}: {}", name.0, reason);
+                    stats.malformed += 1;
+                    budget.strike();
+                    continue;
+                }
+            };
+            if chat.last.as_deref() == Some(text) {
+                continue;
+            }
+            if chat.tokens < 1.0 {
+                stats.dropped += 1;
+                if !chat.warned {
+                    chat.warned = true;
+                    if let Some(notices) = notices.as_mut() {
+                        notices.send::<ServerChannel>(ServerNotice {
+                            level: NoticeLevel::Warning,
+                            text: "You are sending chat messages too quickly".to_string(),
+                        });
+                    }
+                }
+                continue;
+            }
+            chat.tokens -= 1.0;
+            chat.last = Some(text.to_string());
+            info!("[chat] {}: {}", name.0, text);
+            lines.push(ChatLine {
+                from: Some(name.0.clone()),
+                text: text.to_string(),
+            });
+        }
+    }
+
+    if lines.is_empty() {
+        return;
+    }
+    for mut sender in listeners.iter_mut() {
+        for line in &lines {
+            sender.send::<ServerChannel>(line.clone());
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bevy_rapier2d::rapier::geometry::CollisionEventFlags;
+use lightyear::prelude::*;
+use nfrs_shared::{CarDamage, EffectsChannel, ImpactEvent, ImpactKind, Player};
+use tracing::debug;
+
+use crate::car::{CarSet, SpeedModifier};
+
+/// Marker for static track walls
+#[derive(Component)]
+pub struct Wall;
+
+/// Velocity of a car right before the physics step.
+/// Comparing it with the velocity after the step gives the impact strength.
+#[derive(Component, Default)]
+pub struct PreStepVelocity(Vec2);
+
+#[derive(Resource, Debug, Clone)]
+pub struct CollisionSettings {
+    /// Velocity change (m/s) below which a contact is not an impact
+    pub min_impact: f32,
+    pub damage_enabled: bool,
+    /// Damage per m/s of velocity change
+    pub damage_per_impact: f32,
+    /// Fraction of max speed lost at full damage
+    pub max_speed_penalty: f32,
+    /// Damage repaired per second
+    pub repair_rate: f32,
+}
+
+impl Default for CollisionSettings {
+    fn default() -> Self {
+        Self {
+            min_impact: 2.0,
+            damage_enabled: true,
+            damage_per_impact: 2.0,
+            max_speed_penalty: 0.5,
+            repair_rate: 1.0,
+        }
+    }
+}
+
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CollisionSettings>();
+        app.add_systems(
+            PostUpdate,
+            (
+                record_pre_step_velocity.before(PhysicsSet::SyncBackend),
+                detect_impacts.after(PhysicsSet::Writeback),
+            ),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (
+                repair_damage,
+                apply_damage_penalty.in_set(CarSet::Modifiers),
+            ),
+        );
+    }
+}
+
+fn record_pre_step_velocity(mut cars: Query<(&Velocity, &mut PreStepVelocity)>) {
+    for (velocity, mut pre_step) in cars.iter_mut() {
+        pre_step.0 = velocity.linvel;
+    }
+}
+
+/// A contact Rapier reported in the last physics step, not measured yet
+struct PendingImpact {
+    kind: ImpactKind,
+    entities: [Entity; 2],
+    /// Velocity change of each car (0 for walls) in the step that reported the contact
+    delta_v: [f32; 2],
+}
+
+/// Turn Rapier contact starts into impacts: damage the cars and tell every client.
+/// Rapier reports a contact while the bodies are still within its prediction distance and
+/// usually pushes them apart a step later, so each contact is measured over both steps.
+fn detect_impacts(
+    mut collisions: EventReader<CollisionEvent>,
+    mut pending: Local<Vec<PendingImpact>>,
+    settings: Res<CollisionSettings>,
+    mut cars: Query<(
+        &Player,
+        &Transform,
+        &Velocity,
+        &PreStepVelocity,
+        &mut CarDamage,
+    )>,
+    walls: Query<(), With<Wall>>,
+    mut impact_senders: Query<&mut MessageSender<ImpactEvent>>,
+) {
+    for impact in std::mem::take(&mut *pending) {
+        let mut strength: f32 = 0.0;
+        let mut position = Vec2::ZERO;
+        let mut client_ids = Vec::new();
+        for (entity, earlier) in impact.entities.into_iter().zip(impact.delta_v) {
+            let Ok((player, transform, velocity, pre_step, mut damage)) = cars.get_mut(entity)
+            else {
+                continue;
+            };
+            let delta_v = (pre_step.0 - velocity.linvel).length().max(earlier);
+            strength = strength.max(delta_v);
+            position += transform.translation.truncate();
+            client_ids.push(player.client_id);
+
+            if settings.damage_enabled && delta_v >= settings.min_impact {
+                damage.amount =
+                    (damage.amount + delta_v * settings.damage_per_impact).min(CarDamage::MAX);
+            }
+        }
+
+        // Cars can leave between the two steps
+        if client_ids.is_empty() || strength < settings.min_impact {
+            continue;
+        }
+        // Midpoint between the cars, or the car itself for wall hits
+        position /= client_ids.len() as f32;
+
+        debug!(
+            "{:?} impact at {:?}, strength {:.1}, cars {:?}",
+            impact.kind, position, strength, client_ids
+        );
+        let impact = ImpactEvent {
+            kind: impact.kind,
+            position,
+            strength,
+            client_ids,
+        };
+        for mut sender in impact_senders.iter_mut() {
+            sender.send::<EffectsChannel>(impact.clone());
+        }
+    }
+
+    for event in collisions.read() {
+        let CollisionEvent::Started(first, second, flags) = *event else {
+            continue;
+        };
+        if flags.contains(CollisionEventFlags::SENSOR) {
+            continue;
+        }
+
+        let kind = match (cars.contains(first), cars.contains(second)) {
+            (true, true) => ImpactKind::CarToCar,
+            (true, false) if walls.contains(second) => ImpactKind::CarToWall,
+            (false, true) if walls.contains(first) => ImpactKind::CarToWall,
+            _ => continue,
+        };
+        let delta_v = [first, second].map(|entity| {
+            cars.get(entity)
+                .map_or(0.0, |(_, _, velocity, pre_step, _)| {
+                    (pre_step.0 - velocity.linvel).length()
+                })
+        });
+        pending.push(PendingImpact {
+            kind,
+            entities: [first, second],
+            delta_v,
+        });
+    }
+}
+
+fn repair_damage(
+    time: Res<Time>,
+    settings: Res<CollisionSettings>,
+    mut damages: Query<&mut CarDamage>,
+) {
+    let repair = settings.repair_rate * time.delta_secs();
+    for mut damage in damages.iter_mut() {
+        // Avoid marking the component changed (and replicating it) when there is nothing to repair
+        if damage.amount > 0.0 {
+            damage.amount = (damage.amount - repair).max(0.0);
+        }
+    }
+}
+
+/// Damaged cars are slower
+fn apply_damage_penalty(
+    settings: Res<CollisionSettings>,
+    mut cars: Query<(&CarDamage, &mut SpeedModifier)>,
+) {
+    if !settings.damage_enabled {
+        return;
+    }
+    for (damage, mut modifier) in cars.iter_mut() {
+        modifier.0 *= 1.0 - settings.max_speed_penalty * damage.fraction();
+    }
+}
@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+use lightyear::prelude::*;
+use nfrs_shared::{
+    Ghost, GhostDownload, GhostRequest, GhostUpload, Player, RaceProgress, ServerChannel,
+    MAX_GHOST_SAMPLES,
+};
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use crate::anti_cheat::{InputBudget, InputStats};
+use crate::car::seat_client_id;
+use crate::track::CurrentTrack;
+
+// Client-side lap timing differs a little from the server's
+const LAP_TIME_TOLERANCE: f32 = 1.0;
+
+/// Fastest ghost uploaded for each track, with the name of its driver
+#[derive(Resource, Default)]
+pub struct GhostStore {
+    pub records: HashMap<String, (String, Ghost)>,
+}
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GhostStore>();
+        app.add_systems(Update, (receive_ghost_uploads, answer_ghost_requests));
+    }
+}
+
+/// Keep uploaded ghosts that match a lap the server actually timed and beat the record
+fn receive_ghost_uploads(
+    mut receivers: Query<(Entity, &mut MessageReceiver<GhostUpload>, &mut InputBudget)>,
+    cars: Query<(&Player, &RaceProgress)>,
+    track: Res<CurrentTrack>,
+    mut store: ResMut<GhostStore>,
+    mut stats: ResMut<InputStats>,
+) {
+    for (client, mut receiver, mut budget) in receivers.iter_mut() {
+        for upload in receiver.receive() {
+            // Ghosts are recorded by the first player on a client
+            let client_id = seat_client_id(client, 0);
+            let Some((player, progress)) = cars
+                .iter()
+                .find(|(player, _)| player.client_id == client_id)
+            else {
+                continue;
+            };
+            let mut ghost = upload.ghost;
+            if let Err(reason) = validate_ghost(&ghost, progress, track.0.name) {
+                warn!("Rejected ghost from {}: {}", player.username, reason);
+                stats.malformed += 1;
+                budget.strike();
+                continue;
+            }
+            // Use the server's timing, not the client's
+            ghost.lap_time = progress.best_lap.unwrap_or(ghost.lap_time);
+
+            let is_record = store
+                .records
+                .get(&ghost.track)
+                .is_none_or(|(_, record)| ghost.lap_time < record.lap_time);
+            if is_record {
+                info!(
+                    "New ghost record on '{}' by {}: {:.3} s",
+                    ghost.track, player.username, ghost.lap_time
+                );
+                store
+                    .records
+                    .insert(ghost.track.clone(), (player.username.clone(), ghost));
+            }
+        }
+    }
+}
+
+fn validate_ghost(ghost: &Ghost, progress: &RaceProgress, track: &str) -> Result<(), String> {
+    if ghost.track != track {
+        return Err(format!("ghost is for track '{}'", ghost.track));
+    }
+    if ghost.samples.is_empty() || ghost.samples.len() > MAX_GHOST_SAMPLES {
+        return Err(format!("{} samples", ghost.samples.len()));
+    }
+    let Some(best) = progress.best_lap else {
+        return Err("no lap completed".to_string());
+    };
+    let duration = ghost.samples.last().map_or(0.0, |sample| sample.time);
+    if (duration - best).abs() > LAP_TIME_TOLERANCE {
+        return Err(format!(
+            "ghost lasts {:.2} s but the best lap was {:.2} s",
+            duration, best
+        ));
+    }
+    Ok(())
+}
+
+fn answer_ghost_requests(
+    mut clients: Query<(
+        &mut MessageReceiver<GhostRequest>,
+        &mut MessageSender<GhostDownload>,
+    )>,
+    store: Res<GhostStore>,
+) {
+    for (mut receiver, mut sender) in clients.iter_mut() {
+        for request in receiver.receive() {
+            let record = store.records.get(&request.track).cloned();
+            sender.send::<ServerChannel>(GhostDownload {
+                track: request.track,
+                record,
+            });
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use lightyear::prelude::*;
+use nfrs_shared::{Hazard, HazardKind, Inventory, ItemBox, ItemEffects, ItemKind, Player};
+use rand::seq::SliceRandom;
+use tracing::{debug, info};
+
+use crate::car::{CarSet, DriverInput, SpeedModifier};
+use crate::track::CurrentTrack;
+
+// Hazards leaving this area are removed (the arena walls are at +/- 33 x +/- 19)
+const ARENA_HALF_EXTENTS: Vec2 = Vec2::new(34.0, 20.0);
+
+#[derive(Resource, Debug, Clone)]
+pub struct ItemSettings {
+    pub respawn_secs: f32,
+    pub boost_secs: f32,
+    pub boost_speed_factor: f32,
+    pub boost_acceleration: f32,
+    pub shield_secs: f32,
+    pub spinout_secs: f32,
+    /// Max speed multiplier while spinning out
+    pub spinout_speed_factor: f32,
+    /// Angular velocity forced on a spinning car, in rad/s
+    pub spinout_rate: f32,
+    pub oil_lifetime_secs: f32,
+    pub projectile_speed: f32,
+    pub projectile_lifetime_secs: f32,
+    /// Hazards can't hit anything until this long after being dropped.
+    /// Also gives clients time to receive the hazard before it can be removed.
+    pub hazard_arm_secs: f32,
+}
+
+impl Default for ItemSettings {
+    fn default() -> Self {
+        Self {
+            respawn_secs: 5.0,
+            boost_secs: 1.5,
+            boost_speed_factor: 1.4,
+            boost_acceleration: 30.0,
+            shield_secs: 6.0,
+            spinout_secs: 1.2,
+            spinout_speed_factor: 0.3,
+            spinout_rate: 10.0,
+            oil_lifetime_secs: 20.0,
+            projectile_speed: 35.0,
+            projectile_lifetime_secs: 3.0,
+            hazard_arm_secs: 0.3,
+        }
+    }
+}
+
+/// Seconds until an empty item box becomes active again (server only)
+#[derive(Component, Default)]
+struct Respawn(f32);
+
+/// Motion and remaining lifetime of a hazard (server only)
+#[derive(Component)]
+struct HazardMotion {
+    velocity: Vec2,
+    lifetime: f32,
+    armed_in: f32,
+}
+
+pub struct ItemPlugin;
+
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ItemSettings>();
+        app.add_systems(Startup, spawn_item_boxes);
+        app.add_systems(
+            FixedUpdate,
+            (
+                apply_item_speed.in_set(CarSet::Modifiers),
+                (
+                    collect_items,
+                    use_items,
+                    hit_hazards,
+                    apply_item_forces,
+                    tick_item_effects,
+                )
+                    .chain()
+                    .in_set(CarSet::Forces),
+                (respawn_item_boxes, move_hazards),
+            ),
+        );
+    }
+}
+
+fn spawn_item_boxes(mut commands: Commands, track: Res<CurrentTrack>) {
+    for position in &track.0.item_boxes {
+        commands.spawn((
+            ItemBox { active: true },
+            Respawn::default(),
+            Transform::from_translation(position.extend(0.0)),
+            Collider::ball(0.8),
+            Sensor,
+            // Replication targets are filled in as clients connect
+            Replicate::manual(Vec::new()),
+        ));
+    }
+    info!("Spawned {} item boxes", track.0.item_boxes.len());
+}
+
+fn respawn_item_boxes(time: Res<Time>, mut boxes: Query<(&mut ItemBox, &mut Respawn)>) {
+    for (mut item_box, mut respawn) in boxes.iter_mut() {
+        if item_box.active {
+            continue;
+        }
+        respawn.0 -= time.delta_secs();
+        if respawn.0 <= 0.0 {
+            item_box.active = true;
+        }
+    }
+}
+
+/// Cars with an empty inventory pick up a random item from active boxes they touch
+fn collect_items(
+    settings: Res<ItemSettings>,
+    mut cars: Query<(&Player, &CollidingEntities, &mut Inventory)>,
+    mut boxes: Query<(&mut ItemBox, &mut Respawn)>,
+) {
+    let mut rng = rand::thread_rng();
+    for (player, colliding, mut inventory) in cars.iter_mut() {
+        if inventory.item.is_some() {
+            continue;
+        }
+        for entity in colliding.iter() {
+            let Ok((mut item_box, mut respawn)) = boxes.get_mut(entity) else {
+                continue;
+            };
+            if !item_box.active {
+                continue;
+            }
+            item_box.active = false;
+            respawn.0 = settings.respawn_secs;
+            inventory.item = ItemKind::ALL.choose(&mut rng).copied();
+            debug!("Car {} picked up {:?}", player.client_id, inventory.item);
+            break;
+        }
+    }
+}
+
+fn use_items(
+    mut commands: Commands,
+    settings: Res<ItemSettings>,
+    clients: Query<Entity, With<ReplicationSender>>,
+    mut cars: Query<(
+        &Player,
+        &Transform,
+        &Velocity,
+        &mut DriverInput,
+        &mut Inventory,
+        &mut ItemEffects,
+    )>,
+) {
+    for (player, transform, velocity, mut driver, mut inventory, mut effects) in cars.iter_mut() {
+        if !driver.use_item_pressed {
+            continue;
+        }
+        driver.use_item_pressed = false;
+        let Some(item) = inventory.item.take() else {
+            continue;
+        };
+        debug!("Car {} used {:?}", player.client_id, item);
+
+        let forward = (transform.rotation * Vec3::Y).truncate();
+        let position = transform.translation.truncate();
+        let (kind, position, velocity, lifetime, radius) = match item {
+            ItemKind::Boost => {
+                effects.boost = settings.boost_secs;
+                continue;
+            }
+            ItemKind::Shield => {
+                effects.shield = settings.shield_secs;
+                continue;
+            }
+            ItemKind::OilSlick => (
+                HazardKind::OilSlick,
+                position - forward * 3.5,
+                Vec2::ZERO,
+                settings.oil_lifetime_secs,
+                1.5,
+            ),
+            ItemKind::Projectile => (
+                HazardKind::Projectile,
+                position + forward * 3.5,
+                velocity.linvel + forward * settings.projectile_speed,
+                settings.projectile_lifetime_secs,
+                0.5,
+            ),
+        };
+        commands.spawn((
+            Hazard {
+                kind,
+                owner: player.client_id,
+            },
+            HazardMotion {
+                velocity,
+                lifetime,
+                armed_in: settings.hazard_arm_secs,
+            },
+            Transform::from_translation(position.extend(0.0)),
+            Collider::ball(radius),
+            Sensor,
+            Replicate::manual(clients.iter().collect()),
+        ));
+    }
+}
+
+/// Oil and projectiles spin cars out unless they are shielded. Hazards are used up on contact.
+fn hit_hazards(
+    mut commands: Commands,
+    settings: Res<ItemSettings>,
+    mut cars: Query<(&Player, &CollidingEntities, &mut ItemEffects)>,
+    hazards: Query<(&Hazard, &HazardMotion)>,
+) {
+    for (player, colliding, mut effects) in cars.iter_mut() {
+        for entity in colliding.iter() {
+            let Ok((hazard, motion)) = hazards.get(entity) else {
+                continue;
+            };
+            if motion.armed_in > 0.0 {
+                continue;
+            }
+            // Projectiles can't hit the car that fired them
+            if hazard.kind == HazardKind::Projectile && hazard.owner == player.client_id {
+                continue;
+            }
+            if !effects.is_shielded() && !effects.is_spinning() {
+                debug!("Car {} hit {:?}", player.client_id, hazard.kind);
+                effects.spinout = settings.spinout_secs;
+            }
+            commands.entity(entity).try_despawn();
+        }
+    }
+}
+
+fn apply_item_speed(
+    settings: Res<ItemSettings>,
+    mut cars: Query<(&ItemEffects, &mut SpeedModifier)>,
+) {
+    for (effects, mut modifier) in cars.iter_mut() {
+        if effects.is_boosting() {
+            modifier.0 *= settings.boost_speed_factor;
+        }
+        if effects.is_spinning() {
+            modifier.0 *= settings.spinout_speed_factor;
+        }
+    }
+}
+
+fn apply_item_forces(
+    time: Res<Time>,
+    settings: Res<ItemSettings>,
+    mut cars: Query<(&ItemEffects, &Transform, &mut Velocity)>,
+) {
+    for (effects, transform, mut velocity) in cars.iter_mut() {
+        if effects.is_boosting() {
+            let forward = (transform.rotation * Vec3::Y).truncate();
+            velocity.linvel += forward * settings.boost_acceleration * time.delta_secs();
+        }
+        if effects.is_spinning() {
+            velocity.angvel = settings.spinout_rate;
+        }
+    }
+}
+
+fn tick_item_effects(time: Res<Time>, mut effects: Query<&mut ItemEffects>) {
+    let dt = time.delta_secs();
+    for mut effects in effects.iter_mut() {
+        // Only touch active timers so idle cars aren't re-replicated every tick
+        if effects.boost > 0.0 {
+            effects.boost = (effects.boost - dt).max(0.0);
+        }
+        if effects.shield > 0.0 {
+            effects.shield = (effects.shield - dt).max(0.0);
+        }
+        if effects.spinout > 0.0 {
+            effects.spinout = (effects.spinout - dt).max(0.0);
+        }
+    }
+}
+
+fn move_hazards(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut hazards: Query<(Entity, &mut HazardMotion, &mut Transform)>,
+) {
+    let dt = time.delta_secs();
+    for (entity, mut motion, mut transform) in hazards.iter_mut() {
+        motion.lifetime -= dt;
+        motion.armed_in -= dt;
+        if motion.velocity != Vec2::ZERO {
+            transform.translation += (motion.velocity * dt).extend(0.0);
+        }
+        let position = transform.translation.truncate();
+        if motion.lifetime <= 0.0 || position.abs().cmpgt(ARENA_HALF_EXTENTS).any() {
+            commands.entity(entity).try_despawn();
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use nfrs_shared::{Car, Player, RaceProgress};
+use tracing::info;
+
+use crate::car::CarSet;
+use crate::track::CurrentTrack;
+
+/// When the current lap of a car started, in seconds of simulation time (server only)
+#[derive(Component, Default)]
+pub struct LapTimer {
+    started: Option<f64>,
+}
+
+/// A car finished a lap
+#[derive(Event, Debug, Clone)]
+pub struct LapCompleted {
+    pub car: Entity,
+    pub player: Player,
+    pub lap: u32,
+    pub lap_time: f32,
+    pub personal_best: bool,
+}
+
+pub struct LapPlugin;
+
+impl Plugin for LapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LapCompleted>();
+        app.add_systems(FixedUpdate, track_laps.before(CarSet::Input));
+    }
+}
+
+/// Advance cars through the track's checkpoints, timing each full lap
+fn track_laps(
+    time: Res<Time>,
+    track: Res<CurrentTrack>,
+    mut cars: Query<
+        (
+            Entity,
+            &Player,
+            &Transform,
+            &mut RaceProgress,
+            &mut LapTimer,
+        ),
+        With<Car>,
+    >,
+    mut completed: EventWriter<LapCompleted>,
+) {
+    let checkpoints = &track.0.checkpoints;
+    if checkpoints.is_empty() {
+        return;
+    }
+    let now = time.elapsed_secs_f64();
+
+    for (entity, player, transform, mut progress, mut timer) in cars.iter_mut() {
+        let index = progress.next_checkpoint as usize % checkpoints.len();
+        let (center, half_extents) = checkpoints[index];
+        let offset = (transform.translation.truncate() - center).abs();
+        if offset.x > half_extents.x || offset.y > half_extents.y {
+            continue;
+        }
+
+        progress.next_checkpoint = ((index + 1) % checkpoints.len()) as u8;
+        if index != 0 {
+            continue;
+        }
+
+        // Crossed the start/finish line
+        if let Some(started) = timer.started {
+            let lap_time = (now - started) as f32;
+            let personal_best = progress.best_lap.is_none_or(|best| lap_time < best);
+            progress.last_lap = Some(lap_time);
+            if personal_best {
+                progress.best_lap = Some(lap_time);
+            }
+            info!(
+                "{} finished lap {} in {:.3} s{}",
+                player.username,
+                progress.lap,
+                lap_time,
+                if personal_best {
+                    " (personal best)"
+                } else {
+                    ""
+                }
+            );
+            completed.write(LapCompleted {
+                car: entity,
+                player: player.clone(),
+                lap: progress.lap,
+                lap_time,
+                personal_best,
+            });
+        }
+        progress.lap += 1;
+        timer.started = Some(now);
+    }
+}
@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use lightyear::prelude::server::*;
+use nfrs_shared::ProtocolPlugin;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::error;
+
+pub mod admin;
+pub mod anti_cheat;
+pub mod bots;
+pub mod car;
+pub mod chat;
+pub mod collision;
+pub mod ghosts;
+pub mod items;
+pub mod laps;
+pub mod nitro;
+pub mod replay;
+pub mod start;
+pub mod stats;
+pub mod status;
+pub mod track;
+pub mod transport;
+
+pub use bots::{BotConfig, BotDifficulty};
+pub use transport::ServerTransports;
+
+// Physics units used by every gameplay constant (speeds, track sizes)
+pub const PIXELS_PER_METER: f32 = 100.0;
+
+/// The full server simulation: networking, physics and gameplay.
+/// Does not add a runner or open any sockets; see [`ServerTransports`].
+pub struct ServerPlugin {
+    /// Length of one simulation tick
+    pub tick_duration: Duration,
+    /// Bots spawned at startup
+    pub bots: BotConfig,
+    /// Read admin commands from stdin
+    pub admin_console: bool,
+    /// Directory replays are saved to; `None` disables saving
+    pub replay_dir: Option<PathBuf>,
+    /// File leaderboards and player stats are kept in; `None` keeps them in memory
+    pub stats_file: Option<PathBuf>,
+    /// Seconds the cars wait on the grid, counted down, when a race starts; 0 starts
+    /// races at once
+    pub start_countdown_secs: u8,
+}
+
+impl Default for ServerPlugin {
+    fn default() -> Self {
+        Self {
+            tick_duration: Duration::from_secs_f64(1.0 / 60.0),
+            bots: BotConfig::default(),
+            admin_console: false,
+            replay_dir: None,
+            stats_file: None,
+            start_countdown_secs: 0,
+        }
+    }
+}
+
+impl Plugin for ServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(PIXELS_PER_METER),
+            ServerPlugins {
+                tick_duration: self.tick_duration,
+            },
+            ProtocolPlugin,
+        ));
+        app.add_plugins((
+            anti_cheat::AntiCheatPlugin,
+            bots::BotPlugin,
+            car::CarPlugin,
+            chat::ChatPlugin,
+            collision::CollisionPlugin,
+            ghosts::GhostPlugin,
+            items::ItemPlugin,
+            laps::LapPlugin,
+            nitro::NitroPlugin,
+            replay::ReplayPlugin,
+            start::StartPlugin,
+            stats::StatsPlugin,
+            status::StatusPlugin,
+            track::TrackPlugin,
+            transport::TransportPlugin,
+        ));
+        if self.admin_console {
+            app.add_plugins(admin::AdminPlugin);
+        }
+        app.insert_resource(self.bots.clone());
+        app.insert_resource(start::StartSettings {
+            countdown_secs: self.start_countdown_secs,
+        });
+        app.insert_resource(replay::ReplaySettings {
+            dir: self.replay_dir.clone(),
+            ..default()
+        });
+        if let Some(path) = &self.stats_file {
+            match stats::FileStore::open(path.clone()) {
+                Ok(store) => {
+                    app.insert_resource(stats::Stats(Box::new(store)));
+                }
+                Err(e) => error!(
+                    "Could not load player stats from {}, keeping them in memory: {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+    }
+}
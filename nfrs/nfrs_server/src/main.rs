@@ -1,73 +1,60 @@
 use bevy::app::ScheduleRunnerPlugin;
 use bevy::prelude::*;
-use bevy_rapier2d::prelude::*;
-use lightyear::prelude::server::*;
-use lightyear::prelude::*;
-use nfrs_shared::ProtocolPlugin;
-use std::net::{Ipv4Addr, SocketAddr};
+use clap::Parser;
+use nfrs_server::{transport, BotConfig, BotDifficulty, ServerPlugin, ServerTransports};
+use std::path::{Path, PathBuf};
 use tracing_subscriber::FmtSubscriber;
-use wtransport::Identity;
 
-mod car;
+#[derive(Parser, Debug)]
+#[command(about = "nfrs dedicated server")]
+struct Args {
+    /// Number of bot drivers to spawn at startup
+    #[arg(long, default_value_t = 0)]
+    bots: usize,
+    /// Difficulty of the startup bots
+    #[arg(long, value_enum, default_value_t = BotDifficulty::Medium)]
+    bot_difficulty: BotDifficulty,
+    /// Save a replay of every session into this directory
+    #[arg(long)]
+    replay_dir: Option<PathBuf>,
+    /// Keep leaderboards and player stats in this file
+    #[arg(long, default_value = "stats.ron")]
+    stats_file: PathBuf,
+    /// Count down this many seconds on the grid when the first driver joins
+    #[arg(long, default_value_t = 0)]
+    countdown: u8,
+}
 
 fn main() {
+    let args = Args::parse();
+
     // Setup logging
     let subscriber = FmtSubscriber::builder()
         .with_max_level(tracing::Level::INFO)
         .finish();
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
 
-    let mut app = App::new();
-
-    app.add_plugins((
-        MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(
-            std::time::Duration::from_secs_f64(1.0 / 60.0),
-        )),
-        RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0),
-        ServerPlugins::default(),
-        ProtocolPlugin,
-        car::CarPlugin,
-    ));
-
-    app.add_systems(Startup, start_server);
-
-    app.run();
-}
-
-fn start_server(mut commands: Commands) {
     // Load certificate
-    let cert_path = std::path::Path::new("cert.pem");
-    let key_path = std::path::Path::new("key.pem");
-
-    let rt = tokio::runtime::Runtime::new().unwrap();
-    let certificate = rt
-        .block_on(async { Identity::load_pemfiles(cert_path, key_path).await })
+    let certificate = transport::load_identity(Path::new("cert.pem"), Path::new("key.pem"))
         .expect("Failed to load certificate files. Make sure cert.pem and key.pem exist.");
 
-    let digest = certificate.certificate_chain().as_slice()[0].hash();
-    println!("Certificate Digest: {}", digest);
-
-    // UDP Server
-    let udp_addr = SocketAddr::new(Ipv4Addr::new(0, 0, 0, 0).into(), 5000);
-    let udp_server = commands
-        .spawn((
-            NetcodeServer::new(NetcodeConfig::default()),
-            LocalAddr(udp_addr),
-            ServerUdpIo::default(),
-        ))
-        .id();
-    commands.entity(udp_server).trigger(LinkStart);
-    commands.entity(udp_server).trigger(Start);
+    let server = ServerPlugin {
+        bots: BotConfig {
+            count: args.bots,
+            difficulty: args.bot_difficulty,
+        },
+        admin_console: true,
+        replay_dir: args.replay_dir,
+        stats_file: Some(args.stats_file),
+        start_countdown_secs: args.countdown,
+        ..default()
+    };
 
-    // WebTransport Server
-    let wt_addr = SocketAddr::new(Ipv4Addr::new(0, 0, 0, 0).into(), 5001);
-    let wt_server = commands
-        .spawn((
-            NetcodeServer::new(NetcodeConfig::default()),
-            LocalAddr(wt_addr),
-            WebTransportServerIo { certificate },
-        ))
-        .id();
-    commands.entity(wt_server).trigger(LinkStart);
-    commands.entity(wt_server).trigger(Start);
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(server.tick_duration)),
+        server,
+    ));
+    app.insert_resource(ServerTransports::dedicated(certificate));
+    app.run();
 }
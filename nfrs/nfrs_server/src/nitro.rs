@@ -0,0 +1,170 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use nfrs_shared::Nitro;
+
+use crate::car::{CarSet, DriverInput, SpeedModifier};
+
+#[derive(Resource, Debug, Clone)]
+pub struct NitroSettings {
+    /// Meter refilled per second, always
+    pub refill_rate: f32,
+    /// Extra meter per second while drifting
+    pub drift_refill_rate: f32,
+    /// Sideways speed (m/s) above which a car counts as drifting
+    pub drift_threshold: f32,
+    /// Meter used per second while boosting
+    pub drain_rate: f32,
+    /// Minimum meter needed to start a boost, so an empty meter can't be feathered
+    pub min_to_start: f32,
+    pub boost_speed_factor: f32,
+    pub boost_acceleration: f32,
+    /// How far behind another car the slipstream reaches
+    pub draft_range: f32,
+    /// Cosine of the slipstream half-angle
+    pub draft_cone: f32,
+    /// Both cars must be at least this fast for drafting to apply
+    pub draft_min_speed: f32,
+    pub draft_speed_factor: f32,
+    pub draft_acceleration: f32,
+}
+
+impl Default for NitroSettings {
+    fn default() -> Self {
+        Self {
+            refill_rate: 0.04,
+            drift_refill_rate: 0.25,
+            drift_threshold: 3.0,
+            drain_rate: 0.35,
+            min_to_start: 0.1,
+            boost_speed_factor: 1.3,
+            boost_acceleration: 20.0,
+            draft_range: 8.0,
+            draft_cone: 0.95,
+            draft_min_speed: 5.0,
+            draft_speed_factor: 1.1,
+            draft_acceleration: 5.0,
+        }
+    }
+}
+
+pub struct NitroPlugin;
+
+impl Plugin for NitroPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NitroSettings>();
+        app.add_systems(
+            FixedUpdate,
+            (
+                (update_nitro, detect_drafting, apply_nitro_speed)
+                    .chain()
+                    .in_set(CarSet::Modifiers),
+                apply_nitro_forces.in_set(CarSet::Forces),
+            ),
+        );
+    }
+}
+
+/// Refill the meter (faster while drifting) and drain it while the boost key is held
+fn update_nitro(
+    time: Res<Time>,
+    settings: Res<NitroSettings>,
+    mut cars: Query<(&DriverInput, &Transform, &Velocity, &mut Nitro)>,
+) {
+    let dt = time.delta_secs();
+    for (driver, transform, velocity, mut nitro) in cars.iter_mut() {
+        let wants_boost = driver.ticks_left > 0 && driver.input.boost;
+        let boosting = wants_boost
+            && nitro.meter > 0.0
+            && (nitro.boosting || nitro.meter >= settings.min_to_start);
+
+        let mut meter = nitro.meter;
+        if boosting {
+            meter -= settings.drain_rate * dt;
+        } else {
+            meter += settings.refill_rate * dt;
+            let right = (transform.rotation * Vec3::X).truncate();
+            if velocity.linvel.dot(right).abs() > settings.drift_threshold {
+                meter += settings.drift_refill_rate * dt;
+            }
+        }
+        let meter = meter.clamp(0.0, 1.0);
+
+        // Only write on change so a full, idle meter isn't replicated every tick
+        if nitro.meter != meter {
+            nitro.meter = meter;
+        }
+        if nitro.boosting != boosting {
+            nitro.boosting = boosting;
+        }
+    }
+}
+
+/// A car is drafting when another car is close in front of it, going roughly the same way
+fn detect_drafting(
+    settings: Res<NitroSettings>,
+    mut cars: Query<(Entity, &Transform, &Velocity, &mut Nitro)>,
+) {
+    let snapshot: Vec<(Entity, Vec2, Vec2, f32)> = cars
+        .iter()
+        .map(|(entity, transform, velocity, _)| {
+            (
+                entity,
+                transform.translation.truncate(),
+                (transform.rotation * Vec3::Y).truncate(),
+                velocity.linvel.length(),
+            )
+        })
+        .collect();
+
+    for (entity, transform, velocity, mut nitro) in cars.iter_mut() {
+        let position = transform.translation.truncate();
+        let forward = (transform.rotation * Vec3::Y).truncate();
+        let speed = velocity.linvel.length();
+
+        let drafting = speed >= settings.draft_min_speed
+            && snapshot
+                .iter()
+                .any(|&(other, other_position, other_forward, other_speed)| {
+                    if other == entity || other_speed < settings.draft_min_speed {
+                        return false;
+                    }
+                    let offset = other_position - position;
+                    let distance = offset.length();
+                    distance > 0.0
+                        && distance <= settings.draft_range
+                        && forward.dot(offset / distance) >= settings.draft_cone
+                        && forward.dot(other_forward) > 0.0
+                });
+        if nitro.drafting != drafting {
+            nitro.drafting = drafting;
+        }
+    }
+}
+
+fn apply_nitro_speed(settings: Res<NitroSettings>, mut cars: Query<(&Nitro, &mut SpeedModifier)>) {
+    for (nitro, mut modifier) in cars.iter_mut() {
+        if nitro.boosting {
+            modifier.0 *= settings.boost_speed_factor;
+        }
+        if nitro.drafting {
+            modifier.0 *= settings.draft_speed_factor;
+        }
+    }
+}
+
+fn apply_nitro_forces(
+    time: Res<Time>,
+    settings: Res<NitroSettings>,
+    mut cars: Query<(&Nitro, &Transform, &mut Velocity)>,
+) {
+    let dt = time.delta_secs();
+    for (nitro, transform, mut velocity) in cars.iter_mut() {
+        let forward = (transform.rotation * Vec3::Y).truncate();
+        if nitro.boosting {
+            velocity.linvel += forward * settings.boost_acceleration * dt;
+        }
+        if nitro.drafting {
+            velocity.linvel += forward * settings.draft_acceleration * dt;
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use nfrs_shared::{
+    Car, CarInput, CarState, InputChange, Keyframe, Player, Replay, ReplayCar, REPLAY_EXTENSION,
+};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use crate::admin::AdminCommand;
+use crate::car::{CarSet, DriverInput};
+use crate::start::hold_cars;
+use crate::track::CurrentTrack;
+
+#[derive(Resource, Debug, Clone)]
+pub struct ReplaySettings {
+    /// Where finished replays are written; `None` keeps them in memory only
+    pub dir: Option<PathBuf>,
+    /// Ticks between car state keyframes
+    pub keyframe_interval: u32,
+    /// Long sessions are split into files of at most this many ticks
+    pub max_ticks: u32,
+}
+
+impl Default for ReplaySettings {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            keyframe_interval: 6,
+            max_ticks: 60 * 60 * 30,
+        }
+    }
+}
+
+/// Records the session from the first car spawning until the last one leaves
+#[derive(Resource, Default)]
+pub struct ReplayRecorder {
+    replay: Option<Replay>,
+    tick: u32,
+    car_ids: HashMap<Entity, u16>,
+    last_inputs: HashMap<u16, CarInput>,
+}
+
+impl ReplayRecorder {
+    /// Replay recorded so far, if a session is in progress
+    pub fn replay(&self) -> Option<&Replay> {
+        self.replay.as_ref()
+    }
+
+    /// Close the current recording, returning it
+    fn finish(&mut self) -> Option<Replay> {
+        let mut replay = self.replay.take()?;
+        for car in replay.cars.iter_mut() {
+            car.last_tick.get_or_insert(self.tick);
+        }
+        self.tick = 0;
+        self.car_ids.clear();
+        self.last_inputs.clear();
+        Some(replay)
+    }
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplaySettings>();
+        app.init_resource::<ReplayRecorder>();
+        app.add_event::<AdminCommand>();
+        app.add_systems(Update, handle_admin_commands);
+        app.add_systems(
+            FixedUpdate,
+            (
+                record_cars.before(CarSet::Input),
+                record_inputs.after(hold_cars).before(CarSet::Drive),
+            ),
+        );
+    }
+}
+
+/// Track cars joining and leaving and take keyframes of their state
+fn record_cars(
+    mut recorder: ResMut<ReplayRecorder>,
+    settings: Res<ReplaySettings>,
+    track: Res<CurrentTrack>,
+    fixed_time: Res<Time<Fixed>>,
+    cars: Query<(Entity, &Car, &Player, &Transform, &Velocity)>,
+) {
+    let recorder = &mut *recorder;
+
+    if recorder.replay.is_none() {
+        if cars.is_empty() {
+            return;
+        }
+        let tick_rate = 1.0 / fixed_time.timestep().as_secs_f32();
+        recorder.replay = Some(Replay::new(track.0.name, tick_rate));
+        info!("Started recording a replay");
+    }
+    let Some(replay) = recorder.replay.as_mut() else {
+        return;
+    };
+
+    // Cars that left since the last tick
+    let tick = recorder.tick;
+    recorder.car_ids.retain(|entity, id| {
+        let present = cars.contains(*entity);
+        if !present {
+            replay.cars[*id as usize].last_tick = Some(tick);
+        }
+        present
+    });
+    if cars.is_empty() {
+        if let Some(replay) = recorder.finish() {
+            save(replay, &settings);
+        }
+        return;
+    }
+
+    for (entity, car, player, _, _) in cars.iter() {
+        recorder.car_ids.entry(entity).or_insert_with(|| {
+            let id = replay.cars.len() as u16;
+            replay.cars.push(ReplayCar {
+                id,
+                player: player.clone(),
+                car: car.clone(),
+                first_tick: tick,
+                last_tick: None,
+            });
+            id
+        });
+    }
+
+    if tick.is_multiple_of(settings.keyframe_interval) {
+        let states = cars
+            .iter()
+            .map(|(entity, _, _, transform, velocity)| CarState {
+                car: recorder.car_ids[&entity],
+                position: transform.translation.truncate(),
+                rotation: transform.rotation.to_euler(EulerRot::ZYX).0,
+                velocity: velocity.linvel,
+                angular_velocity: velocity.angvel,
+            })
+            .collect();
+        replay.keyframes.push(Keyframe { tick, cars: states });
+    }
+
+    recorder.tick += 1;
+    if recorder.tick >= settings.max_ticks {
+        // Cars still on track are picked up again by the next recording
+        if let Some(replay) = recorder.finish() {
+            save(replay, &settings);
+        }
+    }
+}
+
+/// Store each car's input whenever it changes
+fn record_inputs(mut recorder: ResMut<ReplayRecorder>, drivers: Query<(Entity, &DriverInput)>) {
+    let recorder = &mut *recorder;
+    let Some(replay) = recorder.replay.as_mut() else {
+        return;
+    };
+    // Tick was already advanced by `record_cars`
+    let tick = recorder.tick.saturating_sub(1);
+    for (entity, driver) in drivers.iter() {
+        let Some(&car) = recorder.car_ids.get(&entity) else {
+            continue;
+        };
+        let input = if driver.ticks_left > 0 {
+            driver.input
+        } else {
+            CarInput::default()
+        };
+        let last = recorder.last_inputs.entry(car).or_default();
+        if *last != input {
+            *last = input;
+            replay.inputs.push(InputChange { tick, car, input });
+        }
+    }
+}
+
+fn handle_admin_commands(
+    mut events: EventReader<AdminCommand>,
+    mut recorder: ResMut<ReplayRecorder>,
+    settings: Res<ReplaySettings>,
+) {
+    for event in events.read() {
+        if *event == AdminCommand::SaveReplay {
+            match recorder.finish() {
+                Some(replay) => save(replay, &settings),
+                None => info!("No replay is being recorded"),
+            }
+        }
+    }
+}
+
+fn save(replay: Replay, settings: &ReplaySettings) {
+    let Some(dir) = &settings.dir else {
+        info!(
+            "Finished replay of {:.0} s (not saved, no replay directory set)",
+            replay.duration_secs()
+        );
+        return;
+    };
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let path = dir.join(format!(
+        "{}-{}.{}",
+        replay.track, timestamp, REPLAY_EXTENSION
+    ));
+    let result = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&path, replay.encode()));
+    match result {
+        Ok(()) => info!(
+            "Saved replay of {:.0} s to {}",
+            replay.duration_secs(),
+            path.display()
+        ),
+        Err(e) => warn!("Failed to save replay to {}: {}", path.display(), e),
+    }
+}
@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use nfrs_shared::{Car, Player, StartCountdown};
+
+use crate::car::{CarSet, DriverInput};
+
+#[derive(Resource, Debug, Clone, Default)]
+pub struct StartSettings {
+    /// Seconds counted down on the grid when a race starts; 0 starts races at once
+    pub countdown_secs: u8,
+}
+
+/// Where the current race is. A race starts when the first driver joins, and ends when
+/// the last car leaves, like the standings in [`crate::stats`].
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Default)]
+pub enum RaceStart {
+    /// No driver has joined yet; any bots wait on the grid
+    #[default]
+    Waiting,
+    /// Seconds left before every car on the grid is let go
+    CountingDown(f32),
+    Racing,
+}
+
+pub struct StartPlugin;
+
+impl Plugin for StartPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StartSettings>();
+        app.init_resource::<RaceStart>();
+        app.add_systems(
+            FixedUpdate,
+            (
+                start_race.before(CarSet::Input),
+                hold_cars.after(CarSet::Input).before(CarSet::Modifiers),
+            ),
+        );
+    }
+}
+
+/// Count the race in once the first driver is on the grid, and show every car waiting
+/// for it how long is left. Cars joining once the race is on never count down.
+fn start_race(
+    time: Res<Time>,
+    settings: Res<StartSettings>,
+    mut race: ResMut<RaceStart>,
+    mut commands: Commands,
+    mut cars: Query<(Entity, &Player, Option<&mut StartCountdown>), With<Car>>,
+) {
+    let next = match *race {
+        _ if settings.countdown_secs == 0 => RaceStart::Racing,
+        _ if cars.is_empty() => RaceStart::Waiting,
+        RaceStart::Waiting if cars.iter().any(|(_, player, _)| !player.is_bot) => {
+            RaceStart::CountingDown(settings.countdown_secs as f32)
+        }
+        RaceStart::CountingDown(left) if left > time.delta_secs() => {
+            RaceStart::CountingDown(left - time.delta_secs())
+        }
+        RaceStart::CountingDown(_) => RaceStart::Racing,
+        current => current,
+    };
+    race.set_if_neq(next);
+
+    let seconds = match next {
+        RaceStart::CountingDown(left) => left.ceil() as u8,
+        _ => 0,
+    };
+    for (entity, _, countdown) in cars.iter_mut() {
+        match countdown {
+            // Only touch the replicated value when the shown second changes
+            Some(mut countdown) => {
+                countdown.set_if_neq(StartCountdown(seconds));
+            }
+            None if seconds > 0 => {
+                commands.entity(entity).insert(StartCountdown(seconds));
+            }
+            None => {}
+        }
+    }
+}
+
+/// Throw away the input of every car until the race is on, so nothing reads it this tick
+pub fn hold_cars(race: Res<RaceStart>, mut drivers: Query<&mut DriverInput>) {
+    if *race == RaceStart::Racing {
+        return;
+    }
+    for mut driver in drivers.iter_mut() {
+        driver.ticks_left = 0;
+        driver.use_item_pressed = false;
+    }
+}
@@ -0,0 +1,360 @@
+use bevy::prelude::*;
+use lightyear::prelude::*;
+use nfrs_shared::{
+    Car, LapRecord, Leaderboard, LeaderboardRequest, Player, PlayerStats, ServerChannel, RACE_LAPS,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use crate::car::CarSet;
+use crate::chat::SystemMessage;
+use crate::laps::LapCompleted;
+use crate::track::CurrentTrack;
+
+/// Entries in each leaderboard sent to clients
+pub const LEADERBOARD_SIZE: usize = 10;
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+// Movement larger than this in one tick is a respawn, not driving
+const MAX_TICK_DISTANCE: f32 = 5.0;
+
+/// A car finishing a race of [`RACE_LAPS`] laps
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RaceResult {
+    pub track: String,
+    pub player: String,
+    /// 1 for the winner
+    pub position: u32,
+    /// Seconds from the start line to the finish
+    pub race_time: f32,
+    /// Unix time of the finish
+    pub finished_at: u64,
+}
+
+/// Where leaderboards and player statistics are kept. Players are identified by username;
+/// bots are never recorded.
+pub trait StatsStore: Send + Sync {
+    fn record_lap(&mut self, track: &str, player: &str, lap_time: f32);
+    fn record_race(&mut self, result: RaceResult);
+    fn add_distance(&mut self, player: &str, distance: f32);
+    fn leaderboard(&self, track: &str) -> Leaderboard;
+    /// Persist everything recorded so far
+    fn save(&mut self) -> Result<(), String>;
+}
+
+/// Keeps statistics until the server stops
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct MemoryStore {
+    /// Best lap of each player, per track
+    best_laps: HashMap<String, HashMap<String, f32>>,
+    players: HashMap<String, PlayerStats>,
+    results: Vec<RaceResult>,
+}
+
+impl MemoryStore {
+    fn player(&mut self, player: &str) -> &mut PlayerStats {
+        self.players
+            .entry(player.to_string())
+            .or_insert_with(|| PlayerStats {
+                player: player.to_string(),
+                ..default()
+            })
+    }
+}
+
+impl StatsStore for MemoryStore {
+    fn record_lap(&mut self, track: &str, player: &str, lap_time: f32) {
+        let best = self
+            .best_laps
+            .entry(track.to_string())
+            .or_default()
+            .entry(player.to_string())
+            .or_insert(lap_time);
+        *best = best.min(lap_time);
+    }
+
+    fn record_race(&mut self, result: RaceResult) {
+        let stats = self.player(&result.player);
+        stats.races += 1;
+        if result.position == 1 {
+            stats.wins += 1;
+        }
+        self.results.push(result);
+    }
+
+    fn add_distance(&mut self, player: &str, distance: f32) {
+        self.player(player).distance += distance;
+    }
+
+    fn leaderboard(&self, track: &str) -> Leaderboard {
+        let mut best_laps: Vec<LapRecord> = self
+            .best_laps
+            .get(track)
+            .into_iter()
+            .flatten()
+            .map(|(player, lap_time)| LapRecord {
+                player: player.clone(),
+                lap_time: *lap_time,
+            })
+            .collect();
+        best_laps.sort_by(|a, b| a.lap_time.total_cmp(&b.lap_time));
+        best_laps.truncate(LEADERBOARD_SIZE);
+
+        let mut players: Vec<PlayerStats> = self.players.values().cloned().collect();
+        players.sort_by(|a, b| {
+            b.wins
+                .cmp(&a.wins)
+                .then(b.races.cmp(&a.races))
+                .then(b.distance.total_cmp(&a.distance))
+        });
+        players.truncate(LEADERBOARD_SIZE);
+
+        Leaderboard {
+            track: track.to_string(),
+            best_laps,
+            players,
+        }
+    }
+
+    fn save(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Statistics kept in a RON file, rewritten whenever they are saved
+pub struct FileStore {
+    path: PathBuf,
+    data: MemoryStore,
+    dirty: bool,
+}
+
+impl FileStore {
+    /// Load `path`, starting empty if it does not exist yet
+    pub fn open(path: PathBuf) -> Result<Self, String> {
+        let data = match std::fs::read_to_string(&path) {
+            Ok(text) => ron::from_str(&text).map_err(|e| e.to_string())?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => MemoryStore::default(),
+            Err(e) => return Err(e.to_string()),
+        };
+        Ok(Self {
+            path,
+            data,
+            dirty: false,
+        })
+    }
+}
+
+impl StatsStore for FileStore {
+    fn record_lap(&mut self, track: &str, player: &str, lap_time: f32) {
+        self.data.record_lap(track, player, lap_time);
+        self.dirty = true;
+    }
+
+    fn record_race(&mut self, result: RaceResult) {
+        self.data.record_race(result);
+        self.dirty = true;
+    }
+
+    fn add_distance(&mut self, player: &str, distance: f32) {
+        self.data.add_distance(player, distance);
+        self.dirty = true;
+    }
+
+    fn leaderboard(&self, track: &str) -> Leaderboard {
+        self.data.leaderboard(track)
+    }
+
+    fn save(&mut self) -> Result<(), String> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let text = ron::ser::to_string_pretty(&self.data, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        // Write a copy first so a crash never leaves a half-written file
+        let temp = self.path.with_extension("tmp");
+        std::fs::write(&temp, text)
+            .and_then(|_| std::fs::rename(&temp, &self.path))
+            .map_err(|e| e.to_string())?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+/// The server's statistics store
+#[derive(Resource)]
+pub struct Stats(pub Box<dyn StatsStore>);
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self(Box::new(MemoryStore::default()))
+    }
+}
+
+/// Distance driven by a car that has not been added to the store yet (server only)
+#[derive(Component, Default)]
+pub struct Odometer {
+    last: Option<Vec2>,
+    pending: f32,
+}
+
+/// Cars finished and time driven in the current race, which lasts until every car has left
+#[derive(Resource, Default)]
+struct RaceStandings {
+    finished: u32,
+    race_times: HashMap<Entity, f32>,
+}
+
+#[derive(Resource)]
+struct SaveTimer(Timer);
+
+impl Default for SaveTimer {
+    fn default() -> Self {
+        Self(Timer::new(SAVE_INTERVAL, TimerMode::Repeating))
+    }
+}
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Stats>();
+        app.init_resource::<RaceStandings>();
+        app.init_resource::<SaveTimer>();
+        // Laps are timed before the car systems run; their results and the distance driven
+        // are recorded once the car systems are done with the tick
+        app.add_systems(
+            FixedUpdate,
+            (record_laps, measure_distance).after(CarSet::Forces),
+        );
+        app.add_systems(Update, (answer_leaderboard_requests, save_stats));
+        app.add_observer(flush_odometer);
+    }
+}
+
+fn record_laps(
+    mut completed: EventReader<LapCompleted>,
+    track: Res<CurrentTrack>,
+    cars: Query<(), With<Car>>,
+    mut standings: ResMut<RaceStandings>,
+    mut stats: ResMut<Stats>,
+    mut messages: EventWriter<SystemMessage>,
+) {
+    if cars.is_empty() {
+        *standings = RaceStandings::default();
+    }
+    for lap in completed.read() {
+        if !lap.player.is_bot {
+            stats
+                .0
+                .record_lap(track.0.name, &lap.player.username, lap.lap_time);
+        }
+        let race_time = standings.race_times.entry(lap.car).or_default();
+        *race_time += lap.lap_time;
+        let race_time = *race_time;
+        if lap.lap != RACE_LAPS {
+            continue;
+        }
+
+        standings.finished += 1;
+        info!(
+            "{} finished the race in position {} ({:.3} s)",
+            lap.player.username, standings.finished, race_time
+        );
+        messages.write(SystemMessage(format!(
+            "{} finished {} ({:.3} s)",
+            lap.player.username,
+            ordinal(standings.finished),
+            race_time
+        )));
+        if !lap.player.is_bot {
+            stats.0.record_race(RaceResult {
+                track: track.0.name.to_string(),
+                player: lap.player.username.clone(),
+                position: standings.finished,
+                race_time,
+                finished_at: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_secs()),
+            });
+        }
+    }
+}
+
+/// "1st", "2nd", "11th"...
+fn ordinal(position: u32) -> String {
+    let suffix = match (position % 10, position % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", position, suffix)
+}
+
+fn measure_distance(mut cars: Query<(&Transform, &mut Odometer)>) {
+    for (transform, mut odometer) in cars.iter_mut() {
+        let position = transform.translation.truncate();
+        if let Some(last) = odometer.last {
+            let moved = position.distance(last);
+            if moved < MAX_TICK_DISTANCE {
+                odometer.pending += moved;
+            }
+        }
+        odometer.last = Some(position);
+    }
+}
+
+/// Keep the distance driven by cars that are leaving
+fn flush_odometer(
+    trigger: Trigger<OnRemove, Odometer>,
+    cars: Query<(&Player, &Odometer)>,
+    mut stats: ResMut<Stats>,
+) {
+    if let Ok((player, odometer)) = cars.get(trigger.target()) {
+        if !player.is_bot && odometer.pending > 0.0 {
+            stats.0.add_distance(&player.username, odometer.pending);
+        }
+    }
+}
+
+fn save_stats(
+    time: Res<Time<Real>>,
+    mut timer: ResMut<SaveTimer>,
+    exits: EventReader<AppExit>,
+    mut cars: Query<(&Player, &mut Odometer)>,
+    mut stats: ResMut<Stats>,
+) {
+    // Also save when the server is shutting down
+    if !timer.0.tick(time.delta()).just_finished() && exits.is_empty() {
+        return;
+    }
+    for (player, mut odometer) in cars.iter_mut() {
+        let pending = std::mem::take(&mut odometer.pending);
+        if !player.is_bot && pending > 0.0 {
+            stats.0.add_distance(&player.username, pending);
+        }
+    }
+    if let Err(e) = stats.0.save() {
+        warn!("Failed to save player stats: {}", e);
+    }
+}
+
+fn answer_leaderboard_requests(
+    mut clients: Query<(
+        &mut MessageReceiver<LeaderboardRequest>,
+        &mut MessageSender<Leaderboard>,
+    )>,
+    stats: Res<Stats>,
+) {
+    for (mut receiver, mut sender) in clients.iter_mut() {
+        for request in receiver.receive() {
+            sender.send::<ServerChannel>(stats.0.leaderboard(&request.track));
+        }
+    }
+}
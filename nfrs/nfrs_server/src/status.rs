@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+use lightyear::prelude::*;
+use nfrs_shared::{Car, ServerChannel, ServerStatus};
+use std::time::Duration;
+use tracing::warn;
+
+/// Frame and tick counters for the current reporting period
+#[derive(Resource)]
+struct TickHealth {
+    timer: Timer,
+    ticks: u32,
+    frames: u32,
+    frame_ms_total: f32,
+    frame_ms_max: f32,
+}
+
+impl Default for TickHealth {
+    fn default() -> Self {
+        Self {
+            timer: Timer::new(Duration::from_secs(1), TimerMode::Repeating),
+            ticks: 0,
+            frames: 0,
+            frame_ms_total: 0.0,
+            frame_ms_max: 0.0,
+        }
+    }
+}
+
+/// Measures how well the server keeps up with its tick rate and reports it to clients
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TickHealth>();
+        app.add_systems(FixedUpdate, count_ticks);
+        app.add_systems(Update, send_status);
+    }
+}
+
+fn count_ticks(mut health: ResMut<TickHealth>) {
+    health.ticks += 1;
+}
+
+fn send_status(
+    real_time: Res<Time<Real>>,
+    fixed_time: Res<Time<Fixed>>,
+    mut health: ResMut<TickHealth>,
+    mut senders: Query<&mut MessageSender<ServerStatus>>,
+    cars: Query<(), With<Car>>,
+) {
+    let frame_ms = real_time.delta_secs() * 1000.0;
+    health.frames += 1;
+    health.frame_ms_total += frame_ms;
+    health.frame_ms_max = health.frame_ms_max.max(frame_ms);
+    if !health.timer.tick(real_time.delta()).just_finished() {
+        return;
+    }
+
+    let status = ServerStatus {
+        tick_rate: health.ticks as f32 / health.timer.duration().as_secs_f32(),
+        target_tick_rate: 1.0 / fixed_time.timestep().as_secs_f32(),
+        frame_ms_avg: health.frame_ms_total / health.frames.max(1) as f32,
+        frame_ms_max: health.frame_ms_max,
+        clients: senders.iter().count() as u32,
+        cars: cars.iter().count() as u32,
+    };
+    // Dropping more than 10% of ticks means the simulation visibly slows down
+    if status.tick_rate < status.target_tick_rate * 0.9 {
+        warn!(
+            "Server falling behind: {:.1}/{:.0} ticks per second, frames avg {:.1} ms, max {:.1} ms",
+            status.tick_rate, status.target_tick_rate, status.frame_ms_avg, status.frame_ms_max
+        );
+    }
+    for mut sender in senders.iter_mut() {
+        sender.send::<ServerChannel>(status.clone());
+    }
+
+    health.ticks = 0;
+    health.frames = 0;
+    health.frame_ms_total = 0.0;
+    health.frame_ms_max = 0.0;
+}
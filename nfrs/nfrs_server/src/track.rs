@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use lightyear::prelude::*;
+use nfrs_shared::{CarMotion, SurfaceKind, SurfaceRegion, TrackLayout};
+use tracing::info;
+
+use crate::car::{CarSet, DriverInput, SpeedModifier};
+
+/// The track being raced on
+#[derive(Resource)]
+pub struct CurrentTrack(pub TrackLayout);
+
+impl Default for CurrentTrack {
+    fn default() -> Self {
+        Self(TrackLayout::oval())
+    }
+}
+
+/// Surface under a car, updated from its sensor contacts
+#[derive(Component, Debug)]
+pub struct CurrentSurface(pub SurfaceKind);
+
+impl Default for CurrentSurface {
+    fn default() -> Self {
+        Self(SurfaceKind::Grass)
+    }
+}
+
+pub struct TrackPlugin;
+
+impl Plugin for TrackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentTrack>();
+        app.add_systems(Startup, spawn_surfaces);
+        app.add_systems(
+            FixedUpdate,
+            (
+                (detect_car_surface, apply_surface_speed)
+                    .chain()
+                    .in_set(CarSet::Modifiers),
+                apply_surface_forces.in_set(CarSet::Forces),
+            ),
+        );
+    }
+}
+
+/// Spawn the track's surface regions as replicated sensor colliders
+fn spawn_surfaces(mut commands: Commands, track: Res<CurrentTrack>) {
+    for (center, region) in &track.0.surfaces {
+        commands.spawn((
+            region.clone(),
+            Transform::from_translation(center.extend(0.0)),
+            Collider::cuboid(region.half_extents.x, region.half_extents.y),
+            Sensor,
+            // Replication targets are filled in as clients connect
+            Replicate::manual(Vec::new()),
+        ));
+    }
+    info!(
+        "Spawned {} surface regions for track '{}'",
+        track.0.surfaces.len(),
+        track.0.name
+    );
+}
+
+/// Pick the highest priority surface among the regions a car overlaps
+fn detect_car_surface(
+    mut cars: Query<(&CollidingEntities, &mut CurrentSurface)>,
+    regions: Query<&SurfaceRegion>,
+) {
+    for (colliding, mut surface) in cars.iter_mut() {
+        let kind = colliding
+            .iter()
+            .filter_map(|entity| regions.get(entity).ok())
+            .map(|region| region.kind)
+            .max_by_key(|kind| kind.priority())
+            .unwrap_or(SurfaceKind::Grass);
+        if surface.0 != kind {
+            surface.0 = kind;
+        }
+    }
+}
+
+fn apply_surface_speed(mut cars: Query<(&CurrentSurface, &mut SpeedModifier)>) {
+    for (surface, mut modifier) in cars.iter_mut() {
+        modifier.0 *= surface.0.properties().max_speed_factor;
+    }
+}
+
+/// Grip, drag and boost pads act every tick, whether or not the driver gives input
+fn apply_surface_forces(
+    time: Res<Time>,
+    mut cars: Query<(
+        &CurrentSurface,
+        &Transform,
+        &mut Velocity,
+        &mut Damping,
+        Option<&DriverInput>,
+    )>,
+) {
+    let dt = time.delta_secs();
+    for (surface, transform, mut velocity, mut damping, driver) in cars.iter_mut() {
+        let properties = surface.0.properties();
+        let handbrake =
+            driver.is_some_and(|driver| driver.ticks_left > 0 && driver.input.handbrake);
+        let forward = (transform.rotation * Vec3::Y).truncate();
+        let mut motion = CarMotion {
+            linear: velocity.linvel,
+            angular: velocity.angvel,
+        };
+        properties.push(&mut motion, forward, handbrake, dt);
+        velocity.linvel = motion.linear;
+
+        if damping.linear_damping != properties.drag {
+            damping.linear_damping = properties.drag;
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use lightyear::prelude::server::*;
+use lightyear::prelude::*;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::Path;
+use tracing::info;
+use wtransport::Identity;
+
+/// Default ports of the dedicated server
+pub const UDP_PORT: u16 = 5000;
+pub const WEBTRANSPORT_PORT: u16 = 5001;
+
+/// Sockets the server listens on, opened at startup.
+/// Insert as a resource before running the app; with none inserted the server
+/// only accepts links spawned by hand (tests, in-memory clients).
+#[derive(Resource, Default)]
+pub struct ServerTransports {
+    udp: Option<SocketAddr>,
+    webtransport: Option<(SocketAddr, Identity)>,
+}
+
+impl ServerTransports {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Listen for UDP clients (native)
+    pub fn udp(mut self, addr: SocketAddr) -> Self {
+        self.udp = Some(addr);
+        self
+    }
+
+    /// Listen for WebTransport clients (browser), using the given certificate
+    pub fn webtransport(mut self, addr: SocketAddr, identity: Identity) -> Self {
+        self.webtransport = Some((addr, identity));
+        self
+    }
+
+    /// UDP and WebTransport on the default ports of all interfaces
+    pub fn dedicated(identity: Identity) -> Self {
+        let any = Ipv4Addr::UNSPECIFIED.into();
+        Self::new()
+            .udp(SocketAddr::new(any, UDP_PORT))
+            .webtransport(SocketAddr::new(any, WEBTRANSPORT_PORT), identity)
+    }
+}
+
+/// Load a certificate and private key, as used by WebTransport
+pub fn load_identity(cert_path: &Path, key_path: &Path) -> Result<Identity, String> {
+    let rt = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+    rt.block_on(Identity::load_pemfiles(cert_path, key_path))
+        .map_err(|e| e.to_string())
+}
+
+pub(crate) struct TransportPlugin;
+
+impl Plugin for TransportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, start_transports);
+    }
+}
+
+fn start_transports(mut commands: Commands, transports: Option<ResMut<ServerTransports>>) {
+    let Some(mut transports) = transports else {
+        return;
+    };
+    let transports = std::mem::take(&mut *transports);
+
+    if let Some(addr) = transports.udp {
+        let server = commands
+            .spawn((
+                NetcodeServer::new(NetcodeConfig::default()),
+                LocalAddr(addr),
+                ServerUdpIo::default(),
+            ))
+            .id();
+        commands.entity(server).trigger(LinkStart);
+        commands.entity(server).trigger(Start);
+        info!("Listening for UDP clients on {}", addr);
+    }
+
+    if let Some((addr, certificate)) = transports.webtransport {
+        let digest = certificate.certificate_chain().as_slice()[0].hash();
+        println!("Certificate Digest: {}", digest);
+        let server = commands
+            .spawn((
+                NetcodeServer::new(NetcodeConfig::default()),
+                LocalAddr(addr),
+                WebTransportServerIo { certificate },
+            ))
+            .id();
+        commands.entity(server).trigger(LinkStart);
+        commands.entity(server).trigger(Start);
+        info!("Listening for WebTransport clients on {}", addr);
+    }
+}
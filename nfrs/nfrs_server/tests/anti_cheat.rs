@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use nfrs_server::anti_cheat::InputStats;
+use nfrs_shared::{Car, CarInput, NoticeLevel, Player, ServerNotice};
+
+mod harness;
+
+use harness::Stepper;
+
+// Far more inputs per frame than the one per tick a client earns
+const FLOOD: usize = 50;
+
+fn throttle() -> CarInput {
+    CarInput {
+        forward: true,
+        ..default()
+    }
+}
+
+fn car_x(stepper: &mut Stepper, username: &str) -> f32 {
+    stepper
+        .server_query::<(&Player, &Transform)>()
+        .into_iter()
+        .find(|(player, _)| player.username == username)
+        .map(|(_, transform)| transform.translation.x)
+        .unwrap()
+}
+
+#[test]
+fn flooded_inputs_drive_no_faster_than_one_per_tick() {
+    let mut stepper = Stepper::new();
+    let honest = stepper.connect_client();
+    stepper.send_join(honest, "alice");
+    let flooder = stepper.connect_client();
+    stepper.send_join(flooder, "mallory");
+    stepper.run_until(120, |stepper| stepper.server_query::<&Car>().len() == 2);
+
+    let honest_start = car_x(&mut stepper, "alice");
+    let flooder_start = car_x(&mut stepper, "mallory");
+    for _ in 0..60 {
+        stepper.send_input(honest, throttle());
+        for _ in 0..FLOOD {
+            stepper.send_input(flooder, throttle());
+        }
+        stepper.frame();
+    }
+
+    // The grid faces +X
+    let honest_distance = car_x(&mut stepper, "alice") - honest_start;
+    let flooder_distance = car_x(&mut stepper, "mallory") - flooder_start;
+    assert!(
+        honest_distance > 1.0,
+        "the honest car drove {honest_distance}"
+    );
+    assert!(
+        flooder_distance < honest_distance * 1.05,
+        "flooding drove {flooder_distance}, one input per tick {honest_distance}"
+    );
+    assert!(stepper.server.world().resource::<InputStats>().dropped > 0);
+}
+
+#[test]
+fn input_floods_are_warned_then_kicked() {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    stepper.collect::<ServerNotice>(client);
+    stepper.send_join(client, "mallory");
+    stepper.run_until(120, |stepper| stepper.server_query::<&Car>().len() == 1);
+    let link = stepper.clients[client].link;
+
+    let mut levels = Vec::new();
+    stepper.run_until(600, |stepper| {
+        for _ in 0..FLOOD {
+            stepper.send_input(client, throttle());
+        }
+        levels.extend(
+            stepper
+                .received::<ServerNotice>(client)
+                .into_iter()
+                .map(|notice| notice.level),
+        );
+        stepper.server.world().get_entity(link).is_err()
+    });
+
+    // A warning for every strike short of a kick, then the kick
+    let (kick, warnings) = levels.split_last().unwrap();
+    assert_eq!(*kick, NoticeLevel::Kicked);
+    assert!(!warnings.is_empty() && warnings.iter().all(|level| *level == NoticeLevel::Warning));
+    assert!(
+        stepper.server_query::<&Car>().is_empty(),
+        "the car left too"
+    );
+    let stats = stepper.server.world().resource::<InputStats>();
+    assert_eq!((stats.warnings, stats.kicks), (warnings.len() as u64, 1));
+}
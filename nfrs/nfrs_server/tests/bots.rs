@@ -0,0 +1,69 @@
+use nfrs_server::admin::AdminCommand;
+use nfrs_server::bots::Bot;
+use nfrs_server::car::GridSlot;
+use nfrs_server::{BotConfig, BotDifficulty, ServerPlugin};
+use nfrs_shared::{Player, RaceProgress};
+
+mod harness;
+
+use harness::Stepper;
+
+fn with_bots(count: usize) -> Stepper {
+    Stepper::with_server(ServerPlugin {
+        bots: BotConfig {
+            count,
+            difficulty: BotDifficulty::Hard,
+        },
+        ..Default::default()
+    })
+}
+
+#[test]
+fn bots_and_players_share_the_grid_without_overlapping() {
+    let mut stepper = with_bots(2);
+    let client = stepper.connect_client();
+    stepper.send_join(client, "alice");
+    stepper.run_until(120, |stepper| {
+        stepper.server_query::<&GridSlot>().len() == 3
+    });
+
+    // Bots added mid-race go around the cars already on the grid
+    stepper
+        .server
+        .world_mut()
+        .send_event(AdminCommand::AddBots {
+            count: 2,
+            difficulty: BotDifficulty::Easy,
+        });
+    stepper.run_until(10, |stepper| stepper.server_query::<&GridSlot>().len() == 5);
+
+    let mut slots: Vec<usize> = stepper
+        .server_query::<&GridSlot>()
+        .into_iter()
+        .map(|slot| slot.0)
+        .collect();
+    slots.sort();
+    assert_eq!(slots, [0, 1, 2, 3, 4]);
+    let alice = stepper
+        .server_query::<(&Player, &GridSlot)>()
+        .into_iter()
+        .find(|(player, _)| player.username == "alice")
+        .map(|(_, slot)| slot.0);
+    assert_eq!(alice, Some(2), "alice lines up behind the startup bots");
+    assert_eq!(stepper.server_query::<&Bot>().len(), 4);
+}
+
+#[test]
+fn a_bot_drives_laps_on_its_own() {
+    let mut stepper = with_bots(1);
+
+    // Lap 1 starts at the line just ahead of the grid, so lap 2 takes a full lap
+    stepper.run_until(60 * 60, |stepper| {
+        stepper
+            .server_query::<(&Bot, &RaceProgress)>()
+            .iter()
+            .all(|(_, progress)| progress.lap >= 2)
+    });
+    let (_, progress) = stepper.server_query::<(&Bot, &RaceProgress)>()[0];
+    assert!(progress.last_lap.is_some(), "the lap was timed");
+}
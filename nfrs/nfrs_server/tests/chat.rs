@@ -0,0 +1,81 @@
+use nfrs_server::anti_cheat::InputStats;
+use nfrs_shared::{ChatLine, ServerNotice, MAX_CHAT_LEN};
+
+mod harness;
+
+use harness::Stepper;
+
+fn line(from: Option<&str>, text: &str) -> ChatLine {
+    ChatLine {
+        from: from.map(str::to_string),
+        text: text.to_string(),
+    }
+}
+
+/// Step until a client has received `expected`, returning everything it got
+fn wait_for_line(stepper: &mut Stepper, index: usize, expected: &ChatLine) -> Vec<ChatLine> {
+    let mut lines = Vec::new();
+    stepper.run_until(120, |stepper| {
+        lines.extend(stepper.received::<ChatLine>(index));
+        lines.contains(expected)
+    });
+    lines
+}
+
+#[test]
+fn chat_is_relayed_with_join_and_leave_messages() {
+    let mut stepper = Stepper::new();
+    let first = stepper.connect_client();
+    let second = stepper.connect_client();
+    stepper.collect::<ChatLine>(first);
+    stepper.collect::<ChatLine>(second);
+    stepper.send_join(first, "alice");
+    stepper.send_spectate(second, "bob");
+    let lines = wait_for_line(&mut stepper, second, &line(None, "bob is spectating"));
+    assert!(lines.contains(&line(None, "alice joined the race")));
+
+    stepper.send_chat(first, "  hello everyone ");
+    wait_for_line(&mut stepper, second, &line(Some("alice"), "hello everyone"));
+    // The sender sees their own line too
+    wait_for_line(&mut stepper, first, &line(Some("alice"), "hello everyone"));
+
+    stepper.disconnect(first);
+    let second = second - 1;
+    wait_for_line(&mut stepper, second, &line(None, "alice left"));
+}
+
+#[test]
+fn bad_chat_is_dropped() {
+    let mut stepper = Stepper::new();
+    let first = stepper.connect_client();
+    let second = stepper.connect_client();
+    stepper.collect::<ChatLine>(second);
+    stepper.collect::<ServerNotice>(first);
+    // Not joined yet, so it has no name to chat under
+    stepper.send_chat(first, "too early");
+    stepper.send_join(first, "alice");
+    stepper.send_join(second, "bob");
+    stepper.frames(10);
+
+    stepper.send_chat(first, "ring\u{7}ring");
+    stepper.send_chat(first, &"a".repeat(MAX_CHAT_LEN + 1));
+    stepper.send_chat(first, "   ");
+    // Five lines in a row are allowed, and repeats are skipped without using any
+    for text in ["1", "2", "2", "3", "4", "5", "6", "7"] {
+        stepper.send_chat(first, text);
+    }
+    stepper.frames(30);
+
+    let relayed: Vec<String> = stepper
+        .received::<ChatLine>(second)
+        .into_iter()
+        .filter(|line| line.from.is_some())
+        .map(|line| line.text)
+        .collect();
+    assert_eq!(relayed, ["1", "2", "3", "4", "5"]);
+    assert!(stepper
+        .received::<ServerNotice>(first)
+        .iter()
+        .any(|notice| notice.text.contains("too quickly")));
+    assert_eq!(stepper.server.world().resource::<InputStats>().malformed, 4);
+}
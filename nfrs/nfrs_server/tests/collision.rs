@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+use nfrs_server::car::SpeedModifier;
+use nfrs_server::collision::CollisionSettings;
+use nfrs_shared::{CarDamage, CarInput, ImpactEvent, ImpactKind, Player};
+
+mod harness;
+
+use harness::Stepper;
+
+fn damage_of(stepper: &mut Stepper, username: &str) -> (f32, f32) {
+    stepper
+        .server_query::<(&Player, &CarDamage, &SpeedModifier)>()
+        .into_iter()
+        .find(|(player, ..)| player.username == username)
+        .map(|(_, damage, modifier)| (damage.amount, modifier.0))
+        .unwrap()
+}
+
+#[test]
+fn car_crashes_are_sent_to_clients_and_damage_the_cars() {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    stepper.collect::<ImpactEvent>(client);
+    // Split-screen seats fill the grid in order: carol lines up behind alice
+    stepper.send_join(client, "alice");
+    stepper.send_join_seat(client, "bob", 1);
+    stepper.send_join_seat(client, "carol", 2);
+    stepper.run_until(120, |stepper| {
+        stepper.server_query::<&CarDamage>().len() == 3
+    });
+
+    // Give carol a run-up, then drive the two into each other
+    let world = stepper.server.world_mut();
+    let mut cars = world.query::<(&Player, &mut Transform)>();
+    for (player, mut transform) in cars.iter_mut(world) {
+        if player.username == "carol" {
+            transform.translation.x -= 10.0;
+        }
+    }
+    let mut impacts = Vec::new();
+    stepper.run_until(180, |stepper| {
+        stepper.send_input(
+            client,
+            CarInput {
+                backward: true,
+                ..default()
+            },
+        );
+        stepper.send_input(
+            client,
+            CarInput {
+                forward: true,
+                seat: 2,
+                ..default()
+            },
+        );
+        impacts.extend(stepper.received::<ImpactEvent>(client));
+        !impacts.is_empty()
+    });
+    assert_eq!(impacts[0].kind, ImpactKind::CarToCar);
+    assert_eq!(impacts[0].client_ids.len(), 2);
+
+    // Both cars took damage, which lowers their speed limit
+    let penalty = stepper
+        .server
+        .world()
+        .resource::<CollisionSettings>()
+        .max_speed_penalty;
+    for username in ["alice", "carol"] {
+        let (damage, modifier) = damage_of(&mut stepper, username);
+        assert!(damage > 0.0, "{username} was not damaged");
+        assert!(
+            modifier <= 1.0 - penalty * damage / CarDamage::MAX + 1e-4,
+            "{username} has {damage} damage but a speed modifier of {modifier}"
+        );
+    }
+    assert_eq!(damage_of(&mut stepper, "bob"), (0.0, 1.0));
+
+    // Damage is repaired over time
+    let (crashed, _) = damage_of(&mut stepper, "alice");
+    stepper.frames(120);
+    let (repaired, _) = damage_of(&mut stepper, "alice");
+    assert!(
+        repaired < crashed - 1.0,
+        "damage went from {crashed} to {repaired} in two seconds"
+    );
+}
@@ -0,0 +1,265 @@
+// Each test file uses a different part of the harness
+#![allow(dead_code)]
+
+use bevy::ecs::query::ReadOnlyQueryData;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use lightyear::crossbeam::CrossbeamIo;
+use lightyear::netcode::Key;
+use lightyear::prelude::client::{self, ClientPlugins, NetcodeClient};
+use lightyear::prelude::server::{NetcodeConfig, NetcodeServer, Start};
+use lightyear::prelude::*;
+use nfrs_server::ServerPlugin;
+use nfrs_shared::{
+    CarInput, ChatLine, ChatSend, ImpactEvent, InputChannel, JoinRequest, Leaderboard,
+    LeaderboardRequest, ProtocolPlugin, ServerNotice, ServerStatus,
+};
+use std::time::Duration;
+
+// Both sides advance by exactly one simulation tick per frame
+const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// A server and any number of clients in one process, linked by in-memory channels.
+/// Every app is stepped by hand, so tests run at full speed and deterministically.
+pub struct Stepper {
+    pub server: App,
+    server_entity: Entity,
+    pub clients: Vec<TestClient>,
+    /// Netcode ids are never reused, even after a client disconnects
+    next_client_id: u64,
+}
+
+/// Messages received by a client since the last [`Stepper::received`]. Receivers are
+/// cleared at the end of every frame, so a system has to collect them.
+#[derive(Resource)]
+struct Received<M>(Vec<M>);
+
+pub struct TestClient {
+    pub app: App,
+    /// `Client` entity in the client app
+    pub entity: Entity,
+    /// Link entity for this client in the server app
+    pub link: Entity,
+}
+
+impl Stepper {
+    pub fn new() -> Self {
+        Self::with_server(ServerPlugin::default())
+    }
+
+    pub fn with_server(plugin: ServerPlugin) -> Self {
+        let mut server = App::new();
+        server.add_plugins((MinimalPlugins, plugin));
+        server.insert_resource(TimeUpdateStrategy::ManualDuration(FRAME));
+        server.finish();
+        server.cleanup();
+
+        let server_entity = server
+            .world_mut()
+            .spawn(NetcodeServer::new(NetcodeConfig::default()))
+            .id();
+        server.world_mut().entity_mut(server_entity).trigger(Start);
+        server.update();
+
+        Self {
+            server,
+            server_entity,
+            clients: Vec::new(),
+            next_client_id: 1,
+        }
+    }
+
+    /// Link a new client to the server and start its netcode handshake.
+    /// Returns the index of the client in `clients`.
+    pub fn add_client(&mut self) -> usize {
+        let (client_io, server_io) = CrossbeamIo::new_pair();
+        let client_id = self.next_client_id;
+        self.next_client_id += 1;
+
+        let link = self
+            .server
+            .world_mut()
+            .spawn((
+                LinkOf {
+                    server: self.server_entity,
+                },
+                Link::new(None),
+                server_io,
+            ))
+            .id();
+        self.server.world_mut().entity_mut(link).trigger(LinkStart);
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, ClientPlugins::default(), ProtocolPlugin));
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(FRAME));
+        app.finish();
+        app.cleanup();
+
+        let auth = Authentication::Manual {
+            server_addr: "127.0.0.1:0".parse().unwrap(),
+            client_id,
+            private_key: Key::default(),
+            protocol_id: 0,
+        };
+        let entity = app
+            .world_mut()
+            .spawn((
+                Client::default(),
+                ReplicationReceiver::default(),
+                NetcodeClient::new(auth, client::NetcodeConfig::default()).unwrap(),
+                client_io,
+                (
+                    MessageSender::<JoinRequest>::default(),
+                    MessageSender::<CarInput>::default(),
+                    MessageReceiver::<ServerNotice>::default(),
+                    MessageReceiver::<ImpactEvent>::default(),
+                    MessageReceiver::<ServerStatus>::default(),
+                    MessageSender::<LeaderboardRequest>::default(),
+                    MessageReceiver::<Leaderboard>::default(),
+                    MessageSender::<ChatSend>::default(),
+                    MessageReceiver::<ChatLine>::default(),
+                ),
+            ))
+            .id();
+        app.world_mut().entity_mut(entity).trigger(LinkStart);
+        app.world_mut().entity_mut(entity).trigger(Connect);
+
+        self.clients.push(TestClient { app, entity, link });
+        self.clients.len() - 1
+    }
+
+    /// Advance the server, then every client, by one frame. Clients the server has
+    /// unlinked (kicked) are no longer stepped, as their channel is closed.
+    pub fn frame(&mut self) {
+        self.server.update();
+        for client in &mut self.clients {
+            if self.server.world().get_entity(client.link).is_ok() {
+                client.app.update();
+            }
+        }
+    }
+
+    pub fn frames(&mut self, count: usize) {
+        for _ in 0..count {
+            self.frame();
+        }
+    }
+
+    /// Step until `condition` holds, panicking after `max_frames`
+    pub fn run_until(&mut self, max_frames: usize, mut condition: impl FnMut(&mut Self) -> bool) {
+        for _ in 0..max_frames {
+            if condition(self) {
+                return;
+            }
+            self.frame();
+        }
+        panic!("condition not met after {max_frames} frames");
+    }
+
+    /// Add a client and wait for its connection to be established
+    pub fn connect_client(&mut self) -> usize {
+        let index = self.add_client();
+        self.run_until(120, |stepper| stepper.is_connected(index));
+        index
+    }
+
+    pub fn is_connected(&mut self, index: usize) -> bool {
+        let client = &mut self.clients[index];
+        client
+            .app
+            .world()
+            .entity(client.entity)
+            .contains::<Connected>()
+    }
+
+    pub fn send_join(&mut self, index: usize, username: &str) {
+        self.send_join_request(index, username, false, 0);
+    }
+
+    pub fn send_spectate(&mut self, index: usize, username: &str) {
+        self.send_join_request(index, username, true, 0);
+    }
+
+    /// Join another split-screen player over the same connection
+    pub fn send_join_seat(&mut self, index: usize, username: &str, seat: u8) {
+        self.send_join_request(index, username, false, seat);
+    }
+
+    pub fn send_join_request(&mut self, index: usize, username: &str, spectator: bool, seat: u8) {
+        let client = &mut self.clients[index];
+        client
+            .app
+            .world_mut()
+            .get_mut::<MessageSender<JoinRequest>>(client.entity)
+            .unwrap()
+            .send::<InputChannel>(JoinRequest {
+                username: username.to_string(),
+                spectator,
+                seat,
+            });
+    }
+
+    pub fn send_input(&mut self, index: usize, input: CarInput) {
+        let client = &mut self.clients[index];
+        client
+            .app
+            .world_mut()
+            .get_mut::<MessageSender<CarInput>>(client.entity)
+            .unwrap()
+            .send::<InputChannel>(input);
+    }
+
+    pub fn send_chat(&mut self, index: usize, text: &str) {
+        let client = &mut self.clients[index];
+        client
+            .app
+            .world_mut()
+            .get_mut::<MessageSender<ChatSend>>(client.entity)
+            .unwrap()
+            .send::<InputChannel>(ChatSend {
+                text: text.to_string(),
+            });
+    }
+
+    /// Drop the server side of a client's link, as a timeout or kick would
+    pub fn disconnect(&mut self, index: usize) {
+        let client = self.clients.remove(index);
+        self.server.world_mut().despawn(client.link);
+    }
+
+    /// Query the server world
+    pub fn server_query<D: ReadOnlyQueryData>(&mut self) -> Vec<D::Item<'_>> {
+        let world = self.server.world_mut();
+        let mut query = world.query::<D>();
+        query.iter(world).collect()
+    }
+
+    /// Query a client's world
+    pub fn client_query<D: ReadOnlyQueryData>(&mut self, index: usize) -> Vec<D::Item<'_>> {
+        let world = self.clients[index].app.world_mut();
+        let mut query = world.query::<D>();
+        query.iter(world).collect()
+    }
+
+    /// Start collecting messages of type `M` received by a client
+    pub fn collect<M: Message>(&mut self, index: usize) {
+        let app = &mut self.clients[index].app;
+        app.insert_resource(Received::<M>(Vec::new()));
+        app.add_systems(Update, collect_messages::<M>);
+    }
+
+    /// Messages collected since the last call
+    pub fn received<M: Message>(&mut self, index: usize) -> Vec<M> {
+        let world = self.clients[index].app.world_mut();
+        std::mem::take(&mut world.resource_mut::<Received<M>>().0)
+    }
+}
+
+fn collect_messages<M: Message>(
+    mut receivers: Query<&mut MessageReceiver<M>>,
+    mut received: ResMut<Received<M>>,
+) {
+    for mut receiver in receivers.iter_mut() {
+        received.0.extend(receiver.receive());
+    }
+}
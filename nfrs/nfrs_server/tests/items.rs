@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use nfrs_shared::{
+    CarInput, Hazard, HazardKind, Inventory, ItemBox, ItemEffects, ItemKind, Player,
+};
+
+mod harness;
+
+use harness::Stepper;
+
+fn inventory_of(stepper: &mut Stepper, username: &str) -> Option<ItemKind> {
+    stepper
+        .server_query::<(&Player, &Inventory)>()
+        .into_iter()
+        .find(|(player, _)| player.username == username)
+        .and_then(|(_, inventory)| inventory.item)
+}
+
+fn effects_of(stepper: &mut Stepper, username: &str) -> ItemEffects {
+    stepper
+        .server_query::<(&Player, &ItemEffects)>()
+        .into_iter()
+        .find(|(player, _)| player.username == username)
+        .map(|(_, effects)| effects.clone())
+        .unwrap()
+}
+
+#[test]
+fn cars_pick_up_items_from_boxes_and_use_them() {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    stepper.send_join(client, "alice");
+    stepper.run_until(120, |stepper| {
+        stepper.server_query::<&Inventory>().len() == 1
+    });
+
+    // The first row of boxes is straight down the start straight from the pole slot
+    stepper.run_until(600, |stepper| {
+        stepper.send_input(
+            client,
+            CarInput {
+                forward: true,
+                ..default()
+            },
+        );
+        stepper
+            .client_query::<&Inventory>(client)
+            .iter()
+            .any(|inventory| inventory.item.is_some())
+    });
+    let item = inventory_of(&mut stepper, "alice").unwrap();
+    let emptied = |boxes: Vec<&ItemBox>| boxes.iter().filter(|item_box| !item_box.active).count();
+    assert_eq!(emptied(stepper.server_query::<&ItemBox>()), 1);
+    assert_eq!(emptied(stepper.client_query::<&ItemBox>(client)), 1);
+
+    stepper.run_until(10, |stepper| {
+        stepper.send_input(
+            client,
+            CarInput {
+                use_item: true,
+                ..default()
+            },
+        );
+        inventory_of(stepper, "alice").is_none()
+    });
+    stepper.frames(5);
+    let effects = effects_of(&mut stepper, "alice");
+    let hazards: Vec<HazardKind> = stepper
+        .client_query::<&Hazard>(client)
+        .into_iter()
+        .map(|hazard| hazard.kind)
+        .collect();
+    match item {
+        ItemKind::Boost => assert!(effects.is_boosting()),
+        ItemKind::Shield => assert!(effects.is_shielded()),
+        ItemKind::OilSlick => assert_eq!(hazards, [HazardKind::OilSlick]),
+        ItemKind::Projectile => assert_eq!(hazards, [HazardKind::Projectile]),
+    }
+}
+
+#[test]
+fn projectiles_spin_out_the_car_they_hit() {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    stepper.send_join(client, "alice");
+    stepper.send_join_seat(client, "bob", 1);
+    stepper.run_until(120, |stepper| {
+        stepper.server_query::<&Inventory>().len() == 2
+    });
+
+    // Park bob down the straight in alice's line of fire and arm her
+    let world = stepper.server.world_mut();
+    let mut cars = world.query::<(&Player, &mut Transform, &mut Inventory)>();
+    let mut aim = Vec3::ZERO;
+    for (player, transform, mut inventory) in cars.iter_mut(world) {
+        if player.username == "alice" {
+            inventory.item = Some(ItemKind::Projectile);
+            aim = transform.translation + transform.rotation * Vec3::Y * 20.0;
+        }
+    }
+    for (player, mut transform, _) in cars.iter_mut(world) {
+        if player.username == "bob" {
+            transform.translation = aim;
+        }
+    }
+
+    stepper.run_until(120, |stepper| {
+        stepper.send_input(
+            client,
+            CarInput {
+                use_item: true,
+                ..default()
+            },
+        );
+        effects_of(stepper, "bob").is_spinning()
+    });
+    assert!(!effects_of(&mut stepper, "alice").is_spinning());
+    stepper.frames(5);
+    assert!(
+        stepper.server_query::<&Hazard>().is_empty(),
+        "the projectile was used up"
+    );
+}
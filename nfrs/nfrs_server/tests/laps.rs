@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use lightyear::prelude::*;
+use nfrs_server::track::CurrentTrack;
+use nfrs_shared::{Car, InputChannel, Leaderboard, LeaderboardRequest, RaceProgress};
+
+mod harness;
+
+use harness::Stepper;
+
+fn progress(stepper: &mut Stepper) -> RaceProgress {
+    stepper.server_query::<&RaceProgress>()[0].clone()
+}
+
+/// Move the only car onto a checkpoint and give the server a few ticks to notice
+fn drive_through(stepper: &mut Stepper, checkpoint: usize) {
+    let center = stepper
+        .server
+        .world()
+        .resource::<CurrentTrack>()
+        .0
+        .checkpoints[checkpoint]
+        .0;
+    let world = stepper.server.world_mut();
+    let mut cars = world.query_filtered::<&mut Transform, With<Car>>();
+    cars.single_mut(world).unwrap().translation = center.extend(0.0);
+    stepper.frames(5);
+}
+
+#[test]
+fn laps_are_timed_through_every_checkpoint() {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    stepper.send_join(client, "alice");
+    stepper.run_until(120, |stepper| !stepper.server_query::<&Car>().is_empty());
+
+    drive_through(&mut stepper, 0);
+    assert_eq!(progress(&mut stepper).lap, 1);
+
+    // Skipping a checkpoint does not count
+    drive_through(&mut stepper, 2);
+    drive_through(&mut stepper, 0);
+    assert_eq!(progress(&mut stepper).next_checkpoint, 1);
+
+    for checkpoint in [1, 2, 3, 0] {
+        drive_through(&mut stepper, checkpoint);
+    }
+    let lap = progress(&mut stepper);
+    assert_eq!(lap.lap, 2);
+    assert!(lap.last_lap.unwrap() > 0.0);
+    assert_eq!(lap.best_lap, lap.last_lap);
+
+    // The client sees its progress
+    stepper.run_until(60, |stepper| {
+        stepper
+            .client_query::<&RaceProgress>(client)
+            .iter()
+            .any(|progress| progress.lap == 2)
+    });
+}
+
+#[test]
+fn finished_laps_reach_the_leaderboard() {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    stepper.send_join(client, "alice");
+    stepper.run_until(120, |stepper| !stepper.server_query::<&Car>().is_empty());
+    for checkpoint in [0, 1, 2, 3, 0] {
+        drive_through(&mut stepper, checkpoint);
+    }
+
+    stepper.collect::<Leaderboard>(client);
+    let entity = stepper.clients[client].entity;
+    stepper.clients[client]
+        .app
+        .world_mut()
+        .get_mut::<MessageSender<LeaderboardRequest>>(entity)
+        .unwrap()
+        .send::<InputChannel>(LeaderboardRequest {
+            track: "oval".to_string(),
+        });
+    let mut leaderboards = Vec::new();
+    stepper.run_until(60, |stepper| {
+        leaderboards.extend(stepper.received::<Leaderboard>(client));
+        !leaderboards.is_empty()
+    });
+
+    let leaderboard = &leaderboards[0];
+    assert_eq!(leaderboard.best_laps.len(), 1);
+    assert_eq!(leaderboard.best_laps[0].player, "alice");
+    assert_eq!(
+        Some(leaderboard.best_laps[0].lap_time),
+        progress(&mut stepper).best_lap
+    );
+}
@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::Velocity;
+use nfrs_server::nitro::NitroSettings;
+use nfrs_shared::{CarInput, Nitro, Player};
+
+mod harness;
+
+use harness::Stepper;
+
+fn nitro_of(stepper: &mut Stepper, username: &str) -> Nitro {
+    stepper
+        .server_query::<(&Player, &Nitro)>()
+        .into_iter()
+        .find(|(player, _)| player.username == username)
+        .map(|(_, nitro)| nitro.clone())
+        .unwrap()
+}
+
+fn speed_of(stepper: &mut Stepper, username: &str) -> f32 {
+    stepper
+        .server_query::<(&Player, &Velocity)>()
+        .into_iter()
+        .find(|(player, _)| player.username == username)
+        .map(|(_, velocity)| velocity.linvel.length())
+        .unwrap()
+}
+
+#[test]
+fn nitro_drains_while_boosting_and_refills_after() {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    stepper.send_join(client, "alice");
+    stepper.run_until(120, |stepper| {
+        stepper
+            .client_query::<&Nitro>(client)
+            .iter()
+            .any(|nitro| nitro.meter == 1.0)
+    });
+    let min_to_start = stepper
+        .server
+        .world()
+        .resource::<NitroSettings>()
+        .min_to_start;
+    let boost = CarInput {
+        boost: true,
+        ..default()
+    };
+
+    stepper.run_until(30, |stepper| {
+        stepper.send_input(client, boost);
+        stepper
+            .client_query::<&Nitro>(client)
+            .iter()
+            .any(|nitro| nitro.boosting)
+    });
+
+    // Holding the key runs the meter dry, and an empty meter can't start another boost
+    stepper.run_until(600, |stepper| {
+        stepper.send_input(client, boost);
+        !nitro_of(stepper, "alice").boosting
+    });
+    let empty = nitro_of(&mut stepper, "alice");
+    assert!(empty.meter < min_to_start, "stopped at {}", empty.meter);
+    for _ in 0..30 {
+        stepper.send_input(client, boost);
+        stepper.frame();
+        assert!(!nitro_of(&mut stepper, "alice").boosting);
+    }
+
+    let before = nitro_of(&mut stepper, "alice").meter;
+    stepper.frames(120);
+    let after = nitro_of(&mut stepper, "alice").meter;
+    assert!(after > before, "the meter went from {before} to {after}");
+}
+
+#[test]
+fn cars_close_behind_another_draft_it() {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    stepper.send_join(client, "alice");
+    stepper.send_join_seat(client, "carol", 1);
+    stepper.run_until(120, |stepper| stepper.server_query::<&Nitro>().len() == 2);
+
+    // Line carol up 5 m behind alice and send both down the straight faster than drafting needs
+    let world = stepper.server.world_mut();
+    let mut cars = world.query::<(&Player, &mut Transform, &mut Velocity)>();
+    let mut behind_alice = Vec3::ZERO;
+    for (player, transform, mut velocity) in cars.iter_mut(world) {
+        let forward = transform.rotation * Vec3::Y;
+        velocity.linvel = forward.truncate() * 10.0;
+        if player.username == "alice" {
+            behind_alice = transform.translation - forward * 5.0;
+        }
+    }
+    for (player, mut transform, _) in cars.iter_mut(world) {
+        if player.username == "carol" {
+            transform.translation = behind_alice;
+        }
+    }
+    stepper.run_until(30, |stepper| {
+        stepper
+            .client_query::<(&Player, &Nitro)>(client)
+            .iter()
+            .any(|(player, nitro)| player.username == "carol" && nitro.drafting)
+    });
+    assert!(!nitro_of(&mut stepper, "alice").drafting);
+
+    // The slipstream pulls carol up to alice
+    for _ in 0..30 {
+        for seat in [0, 1] {
+            stepper.send_input(
+                client,
+                CarInput {
+                    forward: true,
+                    seat,
+                    ..default()
+                },
+            );
+        }
+        stepper.frame();
+    }
+    let leader = speed_of(&mut stepper, "alice");
+    let follower = speed_of(&mut stepper, "carol");
+    assert!(
+        follower > leader,
+        "carol drafted at {follower} behind alice at {leader}"
+    );
+}
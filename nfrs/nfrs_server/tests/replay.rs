@@ -0,0 +1,101 @@
+use nfrs_server::replay::ReplayRecorder;
+use nfrs_shared::{CarInput, Replay, TrackLayout};
+
+mod harness;
+
+use harness::Stepper;
+
+fn recorded(stepper: &Stepper) -> Option<Replay> {
+    stepper
+        .server
+        .world()
+        .resource::<ReplayRecorder>()
+        .replay()
+        .cloned()
+}
+
+#[test]
+fn records_cars_inputs_and_keyframes() {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    assert!(recorded(&stepper).is_none());
+
+    stepper.send_join(client, "alice");
+    stepper.run_until(120, |stepper| recorded(stepper).is_some());
+    let throttle = CarInput {
+        forward: true,
+        ..Default::default()
+    };
+    for _ in 0..60 {
+        stepper.send_input(client, throttle);
+        stepper.frame();
+    }
+
+    let replay = recorded(&stepper).unwrap();
+    assert_eq!(replay.track, "oval");
+    assert_eq!(replay.cars.len(), 1);
+    assert_eq!(replay.cars[0].player.username, "alice");
+    assert!(replay.keyframes.len() >= 10);
+    assert!(replay.inputs.iter().any(|change| change.input == throttle));
+
+    // The car moved, and sampling between keyframes lands between them
+    let first = replay.keyframes[0].cars[0].position;
+    let last = replay.keyframes.last().unwrap().cars[0].position;
+    assert!(first.distance(last) > 1.0);
+    let a = &replay.keyframes[5];
+    let b = &replay.keyframes[6];
+    let surfaces = TrackLayout::oval().surfaces;
+    let middle = replay.sample(&surfaces, (a.tick + b.tick) as f32 / 2.0)[0].position;
+    assert!(middle.x > a.cars[0].position.x && middle.x < b.cars[0].position.x);
+
+    assert_eq!(Replay::decode(&replay.encode()).unwrap(), replay);
+}
+
+#[test]
+fn recording_ends_when_the_last_car_leaves() {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    stepper.send_join(client, "alice");
+    stepper.run_until(120, |stepper| recorded(stepper).is_some());
+
+    stepper.disconnect(client);
+    stepper.frames(5);
+    assert!(recorded(&stepper).is_none());
+}
+
+#[test]
+fn playback_steps_the_car_model_between_keyframes() {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    stepper.send_join(client, "alice");
+    stepper.run_until(120, |stepper| recorded(stepper).is_some());
+    // Accelerate, then turn hard while braking with the handbrake
+    for frame in 0..120 {
+        stepper.send_input(
+            client,
+            CarInput {
+                forward: frame < 60,
+                right: frame >= 60,
+                handbrake: frame >= 90,
+                ..Default::default()
+            },
+        );
+        stepper.frame();
+    }
+
+    // Keep every fifth keyframe and check the ones dropped can be worked out again
+    let replay = recorded(&stepper).unwrap();
+    let mut sparse = replay.clone();
+    sparse.keyframes = replay.keyframes.iter().step_by(5).cloned().collect();
+    let surfaces = TrackLayout::oval().surfaces;
+    for keyframe in replay.keyframes.iter().take(sparse.keyframes.len() * 5 - 4) {
+        let expected = keyframe.cars[0];
+        let sampled = sparse.sample(&surfaces, keyframe.tick as f32)[0];
+        assert!(sampled.position.distance(expected.position) < 0.01);
+        assert!((sampled.rotation - expected.rotation).abs() < 0.01);
+        assert!(sampled.velocity.distance(expected.velocity) < 0.05);
+    }
+    let turned =
+        replay.keyframes.last().unwrap().cars[0].rotation - replay.keyframes[0].cars[0].rotation;
+    assert!(turned.abs() > 0.5, "the car turned");
+}
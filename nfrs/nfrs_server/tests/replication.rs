@@ -0,0 +1,301 @@
+use bevy::prelude::*;
+use nfrs_server::anti_cheat::InputStats;
+use nfrs_server::car::{GridSlot, Spectator};
+use nfrs_server::ServerPlugin;
+use nfrs_shared::{Car, CarInput, Player, StartCountdown, SurfaceRegion};
+
+mod harness;
+
+use harness::Stepper;
+
+fn client_cars(stepper: &mut Stepper, index: usize) -> Vec<String> {
+    stepper
+        .client_query::<&Player>(index)
+        .into_iter()
+        .map(|player| player.username.clone())
+        .collect()
+}
+
+#[test]
+fn join_spawns_replicated_car() {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    stepper.send_join(client, "alice");
+
+    stepper.run_until(120, |stepper| client_cars(stepper, client).len() == 1);
+    assert_eq!(client_cars(&mut stepper, client), vec!["alice"]);
+    assert_eq!(stepper.server_query::<&Car>().len(), 1);
+    // The track is replicated as well
+    assert!(!stepper.client_query::<&SurfaceRegion>(client).is_empty());
+}
+
+#[test]
+fn cars_replicate_to_every_client() {
+    let mut stepper = Stepper::new();
+    let first = stepper.connect_client();
+    stepper.send_join(first, "alice");
+    let second = stepper.connect_client();
+    stepper.send_join(second, "bob");
+
+    stepper.run_until(120, |stepper| {
+        client_cars(stepper, first).len() == 2 && client_cars(stepper, second).len() == 2
+    });
+}
+
+#[test]
+fn invalid_username_is_rejected() {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    stepper.send_join(client, "   ");
+    stepper.frames(60);
+
+    assert!(stepper.server_query::<&Car>().is_empty());
+    assert!(client_cars(&mut stepper, client).is_empty());
+}
+
+#[test]
+fn spectators_watch_without_a_car() {
+    let mut stepper = Stepper::new();
+    let driver = stepper.connect_client();
+    stepper.send_join(driver, "alice");
+    let spectator = stepper.connect_client();
+    stepper.send_spectate(spectator, "screen");
+
+    stepper.run_until(120, |stepper| client_cars(stepper, spectator).len() == 1);
+    stepper.frames(30);
+    assert_eq!(client_cars(&mut stepper, spectator), vec!["alice"]);
+    assert_eq!(stepper.server_query::<&Car>().len(), 1);
+}
+
+#[test]
+fn drivers_cannot_also_spectate() {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    stepper.send_join(client, "alice");
+    stepper.run_until(120, |stepper| client_cars(stepper, client).len() == 1);
+
+    stepper.send_join_request(client, "bob", true, 1);
+    stepper.frames(30);
+    assert_eq!(client_cars(&mut stepper, client), vec!["alice"]);
+    assert!(stepper.server_query::<&Spectator>().is_empty());
+    assert_eq!(stepper.server.world().resource::<InputStats>().malformed, 1);
+}
+
+#[test]
+fn input_moves_car() {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    stepper.send_join(client, "alice");
+    stepper.run_until(120, |stepper| client_cars(stepper, client).len() == 1);
+
+    let start = stepper.client_query::<(&Transform, &Car)>(client)[0]
+        .0
+        .translation;
+    for _ in 0..60 {
+        stepper.send_input(
+            client,
+            CarInput {
+                forward: true,
+                ..default()
+            },
+        );
+        stepper.frame();
+    }
+    stepper.frames(10);
+
+    // The grid faces +X
+    let end = stepper.client_query::<(&Transform, &Car)>(client)[0]
+        .0
+        .translation;
+    assert!(
+        end.x - start.x > 2.0,
+        "car moved from {start} to {end} under throttle"
+    );
+}
+
+/// How far a lone car gets along the grid in one second of `input`
+fn distance_driven(input: CarInput) -> f32 {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    stepper.send_join(client, "alice");
+    stepper.run_until(120, |stepper| client_cars(stepper, client).len() == 1);
+
+    let start = stepper.client_query::<(&Transform, &Car)>(client)[0]
+        .0
+        .translation;
+    for _ in 0..60 {
+        stepper.send_input(client, input);
+        stepper.frame();
+    }
+    stepper.frames(10);
+    let end = stepper.client_query::<(&Transform, &Car)>(client)[0]
+        .0
+        .translation;
+    end.x - start.x
+}
+
+#[test]
+fn analog_throttle_is_proportional() {
+    let full = distance_driven(CarInput {
+        forward: true,
+        ..default()
+    });
+    let half = distance_driven(CarInput {
+        throttle: 0.5,
+        ..default()
+    });
+    assert!(
+        half > 0.5 && half < full * 0.75,
+        "half throttle drove {half}, full throttle {full}"
+    );
+}
+
+#[test]
+fn disconnect_removes_only_that_car() {
+    let mut stepper = Stepper::new();
+    let first = stepper.connect_client();
+    stepper.send_join(first, "alice");
+    let second = stepper.connect_client();
+    stepper.send_join(second, "bob");
+    stepper.run_until(120, |stepper| client_cars(stepper, second).len() == 2);
+
+    stepper.disconnect(first);
+    let remaining = second - 1;
+    stepper.run_until(120, |stepper| client_cars(stepper, remaining).len() == 1);
+
+    assert_eq!(client_cars(&mut stepper, remaining), vec!["bob"]);
+    let server_players: Vec<String> = stepper
+        .server_query::<&Player>()
+        .into_iter()
+        .map(|player| player.username.clone())
+        .collect();
+    assert_eq!(server_players, vec!["bob"]);
+}
+
+#[test]
+fn new_cars_take_a_free_grid_slot() {
+    let mut stepper = Stepper::new();
+    let first = stepper.connect_client();
+    stepper.send_join(first, "alice");
+    let second = stepper.connect_client();
+    stepper.send_join(second, "bob");
+    stepper.run_until(120, |stepper| client_cars(stepper, second).len() == 2);
+    let freed = stepper
+        .server_query::<(&Player, &GridSlot)>()
+        .into_iter()
+        .find(|(player, _)| player.username == "alice")
+        .map(|(_, slot)| *slot)
+        .unwrap();
+
+    stepper.disconnect(first);
+    let second = second - 1;
+    stepper.run_until(120, |stepper| client_cars(stepper, second).len() == 1);
+    let third = stepper.connect_client();
+    stepper.send_join(third, "carol");
+    stepper.run_until(120, |stepper| client_cars(stepper, second).len() == 2);
+
+    let mut slots: Vec<(String, GridSlot)> = stepper
+        .server_query::<(&Player, &GridSlot)>()
+        .into_iter()
+        .map(|(player, slot)| (player.username.clone(), *slot))
+        .collect();
+    slots.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_ne!(slots[0].1, slots[1].1, "no two cars share a slot");
+    assert_eq!(slots[1], ("carol".to_string(), freed));
+}
+
+#[test]
+fn split_screen_players_drive_their_own_cars() {
+    let mut stepper = Stepper::new();
+    let client = stepper.connect_client();
+    stepper.send_join(client, "alice");
+    stepper.send_join_seat(client, "bob", 1);
+    stepper.run_until(120, |stepper| client_cars(stepper, client).len() == 2);
+
+    let positions = |stepper: &mut Stepper| -> Vec<(String, Vec3)> {
+        let mut cars: Vec<_> = stepper
+            .client_query::<(&Player, &Transform)>(client)
+            .into_iter()
+            .map(|(player, transform)| (player.username.clone(), transform.translation))
+            .collect();
+        cars.sort_by(|a, b| a.0.cmp(&b.0));
+        cars
+    };
+    let start = positions(&mut stepper);
+    for _ in 0..60 {
+        stepper.send_input(
+            client,
+            CarInput {
+                forward: true,
+                seat: 1,
+                ..default()
+            },
+        );
+        stepper.frame();
+    }
+    stepper.frames(10);
+    let end = positions(&mut stepper);
+
+    assert!(end[0].1.distance(start[0].1) < 0.1, "alice stayed put");
+    assert!(end[1].1.x - start[1].1.x > 2.0, "bob drove off");
+}
+
+#[test]
+fn cars_wait_for_the_start_countdown_only_at_the_race_start() {
+    let mut stepper = Stepper::with_server(ServerPlugin {
+        start_countdown_secs: 1,
+        ..default()
+    });
+    let first = stepper.connect_client();
+    stepper.send_join(first, "alice");
+    stepper.run_until(120, |stepper| client_cars(stepper, first).len() == 1);
+    assert_eq!(
+        stepper.client_query::<&StartCountdown>(first),
+        vec![&StartCountdown(1)]
+    );
+
+    let position = |stepper: &mut Stepper, username: &str| {
+        stepper
+            .server_query::<(&Player, &Transform)>()
+            .into_iter()
+            .find(|(player, _)| player.username == username)
+            .map(|(_, transform)| transform.translation)
+            .unwrap()
+    };
+    let drive = |stepper: &mut Stepper, client: usize, frames: usize| {
+        for _ in 0..frames {
+            stepper.send_input(
+                client,
+                CarInput {
+                    forward: true,
+                    ..default()
+                },
+            );
+            stepper.frame();
+        }
+    };
+    let start = position(&mut stepper, "alice");
+    drive(&mut stepper, first, 20);
+    assert!(
+        position(&mut stepper, "alice").distance(start) < 0.01,
+        "held on the grid"
+    );
+
+    stepper.run_until(120, |stepper| {
+        stepper.client_query::<&StartCountdown>(first) == vec![&StartCountdown(0)]
+    });
+    drive(&mut stepper, first, 60);
+    assert!(
+        position(&mut stepper, "alice").distance(start) > 1.0,
+        "drove off after the countdown"
+    );
+
+    // Joining a race that is already on means driving straight away
+    let second = stepper.connect_client();
+    stepper.send_join(second, "bob");
+    stepper.run_until(120, |stepper| client_cars(stepper, second).len() == 2);
+    let start = position(&mut stepper, "bob");
+    drive(&mut stepper, second, 60);
+    assert!(position(&mut stepper, "bob").distance(start) > 1.0);
+    assert_eq!(stepper.server_query::<&StartCountdown>().len(), 1);
+}
@@ -0,0 +1,33 @@
+use nfrs_server::stats::{FileStore, RaceResult, StatsStore};
+
+#[test]
+fn file_store_keeps_stats_between_runs() {
+    let path = std::env::temp_dir().join(format!("nfrs-stats-{}.ron", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut store = FileStore::open(path.clone()).unwrap();
+    store.record_lap("oval", "alice", 12.5);
+    store.record_lap("oval", "alice", 11.0);
+    store.record_lap("oval", "bob", 11.5);
+    store.record_race(RaceResult {
+        track: "oval".to_string(),
+        player: "bob".to_string(),
+        position: 1,
+        race_time: 35.0,
+        finished_at: 0,
+    });
+    store.add_distance("bob", 250.0);
+    store.save().unwrap();
+
+    let leaderboard = FileStore::open(path.clone()).unwrap().leaderboard("oval");
+    std::fs::remove_file(&path).unwrap();
+    let laps: Vec<_> = leaderboard
+        .best_laps
+        .iter()
+        .map(|record| (record.player.as_str(), record.lap_time))
+        .collect();
+    assert_eq!(laps, [("alice", 11.0), ("bob", 11.5)]);
+    assert_eq!(leaderboard.players[0].player, "bob");
+    assert_eq!(leaderboard.players[0].wins, 1);
+    assert_eq!(leaderboard.players[0].distance, 250.0);
+}
@@ -0,0 +1,19 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Longest chat line the server relays, in characters
+pub const MAX_CHAT_LEN: usize = 200;
+
+/// Client -> server: a line typed into the chat
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default, Reflect)]
+pub struct ChatSend {
+    pub text: String,
+}
+
+/// Server -> client: a chat line from a player, or from the server itself
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default, Reflect)]
+pub struct ChatLine {
+    /// Username of the sender; `None` for server messages (joins, leaves, results)
+    pub from: Option<String>,
+    pub text: String,
+}
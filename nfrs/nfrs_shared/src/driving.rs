@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+
+use crate::{Car, CarInput, SurfaceProperties};
+
+// Input is applied as if every tick lasted this long
+const INPUT_DT: f32 = 0.016;
+// Share of forward speed the handbrake removes per second
+const HANDBRAKE_DECELERATION: f32 = 1.5;
+// Sideways velocity removed per second at full grip
+const GRIP_RATE: f32 = 8.0;
+// Share of the surface's grip left while the handbrake is held, so the car slides
+const HANDBRAKE_GRIP: f32 = 0.25;
+// How quickly speed above the current limit bleeds off (per second)
+const OVERSPEED_BLEED: f32 = 3.0;
+/// Angular damping of every car body; linear damping comes from the surface
+pub const CAR_ANGULAR_DAMPING: f32 = 2.0;
+
+/// How a car's body is moving: the part of its state the car model changes
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CarMotion {
+    pub linear: Vec2,
+    /// Radians per second, counter-clockwise
+    pub angular: f32,
+}
+
+impl CarMotion {
+    /// Move a body with no contacts for `dt` seconds, the way the physics engine does:
+    /// damp the velocities, then move by them
+    pub fn integrate(&mut self, position: &mut Vec2, rotation: &mut f32, drag: f32, dt: f32) {
+        self.linear *= 1.0 / (1.0 + dt * drag);
+        self.angular *= 1.0 / (1.0 + dt * CAR_ANGULAR_DAMPING);
+        *position += self.linear * dt;
+        *rotation += self.angular * dt;
+    }
+}
+
+/// Direction a car with the given heading faces (0 = facing +Y)
+pub fn heading_forward(rotation: f32) -> Vec2 {
+    Vec2::new(-rotation.sin(), rotation.cos())
+}
+
+impl Car {
+    /// One tick of driver input: throttle, steering and handbrake. Input can't push the
+    /// car past its speed limit (scaled by `speed_modifier`), but speed already above it
+    /// (from boosts) is left for [`Car::limit_speed`] to bleed off.
+    pub fn drive(
+        &self,
+        motion: &mut CarMotion,
+        forward: Vec2,
+        input: &CarInput,
+        speed_modifier: f32,
+    ) {
+        let max_speed = (self.max_speed * speed_modifier).max(motion.linear.length());
+
+        // Forward/backward
+        let mut linear =
+            motion.linear + forward * self.acceleration * input.throttle_axis() * INPUT_DT;
+
+        // Steering
+        motion.angular -= self.steering_speed * input.steering_axis() * INPUT_DT;
+
+        if input.handbrake {
+            let forward_speed = linear.dot(forward);
+            let braked = forward_speed * (1.0 - HANDBRAKE_DECELERATION * INPUT_DT).max(0.0);
+            linear += forward * (braked - forward_speed);
+        }
+
+        let speed = linear.length();
+        if speed > max_speed {
+            linear = linear.normalize() * max_speed;
+        }
+        motion.linear = linear;
+    }
+
+    /// Smoothly pull the car back under its current speed limit
+    pub fn limit_speed(&self, motion: &mut CarMotion, speed_modifier: f32, dt: f32) {
+        let max_speed = self.max_speed * speed_modifier;
+        let speed = motion.linear.length();
+        if speed > max_speed {
+            let bleed = (-OVERSPEED_BLEED * dt).exp();
+            let target = max_speed + (speed - max_speed) * bleed;
+            motion.linear *= target / speed;
+        }
+    }
+}
+
+impl SurfaceProperties {
+    /// Grip and boost pads, acting every tick whether or not the driver gives input
+    pub fn push(&self, motion: &mut CarMotion, forward: Vec2, handbrake: bool, dt: f32) {
+        let grip = if handbrake {
+            self.grip * HANDBRAKE_GRIP
+        } else {
+            self.grip
+        };
+
+        // Grip: cancel part of the sideways slide
+        let forward_speed = motion.linear.dot(forward);
+        let lateral = motion.linear - forward * forward_speed;
+        let keep = 1.0 - (grip * GRIP_RATE * dt).min(1.0);
+        motion.linear = forward * forward_speed + lateral * keep;
+
+        // Boost pads push the car along its heading
+        motion.linear += forward * self.boost * dt;
+    }
+}
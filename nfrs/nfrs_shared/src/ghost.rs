@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Ghosts longer than this are rejected by the server
+pub const MAX_GHOST_SAMPLES: usize = 3000;
+
+/// Path of a car over one lap, replayed as a translucent car
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default, Reflect)]
+pub struct Ghost {
+    pub track: String,
+    pub lap_time: f32,
+    pub samples: Vec<GhostSample>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default, Reflect)]
+pub struct GhostSample {
+    /// Seconds since the lap started
+    pub time: f32,
+    pub position: Vec2,
+    /// Heading in radians (0 = facing +Y)
+    pub rotation: f32,
+}
+
+impl Ghost {
+    pub fn encode(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("ghosts always serialize")
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        bincode::deserialize(bytes).map_err(|e| e.to_string())
+    }
+
+    /// Position and heading `time` seconds into the lap; `None` once the lap is over
+    pub fn sample(&self, time: f32) -> Option<(Vec2, f32)> {
+        let next = self.samples.partition_point(|sample| sample.time < time);
+        let b = self.samples.get(next)?;
+        let Some(a) = next.checked_sub(1).map(|index| self.samples[index]) else {
+            return Some((b.position, b.rotation));
+        };
+        let t = ((time - a.time) / (b.time - a.time).max(f32::EPSILON)).clamp(0.0, 1.0);
+        let turn = (b.rotation - a.rotation + std::f32::consts::PI)
+            .rem_euclid(std::f32::consts::TAU)
+            - std::f32::consts::PI;
+        Some((a.position.lerp(b.position, t), a.rotation + turn * t))
+    }
+}
+
+/// Client -> server: a new personal best lap
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default, Reflect)]
+pub struct GhostUpload {
+    pub ghost: Ghost,
+}
+
+/// Client -> server: ask for the fastest ghost on a track
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default, Reflect)]
+pub struct GhostRequest {
+    pub track: String,
+}
+
+/// Server -> client: answer to a [`GhostRequest`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default, Reflect)]
+pub struct GhostDownload {
+    pub track: String,
+    /// Who drove the lap, with the ghost itself; `None` if nobody has set one yet
+    pub record: Option<(String, Ghost)>,
+}
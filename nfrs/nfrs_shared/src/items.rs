@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub enum ItemKind {
+    /// Short burst of extra speed
+    Boost,
+    /// Drops a slick behind the car that spins out whoever drives over it
+    OilSlick,
+    /// Protects against oil and projectiles for a few seconds
+    Shield,
+    /// Fires a shot straight ahead
+    Projectile,
+}
+
+impl ItemKind {
+    pub const ALL: [ItemKind; 4] = [
+        ItemKind::Boost,
+        ItemKind::OilSlick,
+        ItemKind::Shield,
+        ItemKind::Projectile,
+    ];
+}
+
+/// A pickup location on the track. Inactive boxes are waiting to respawn.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct ItemBox {
+    pub active: bool,
+}
+
+/// The item a car is carrying, if any
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct Inventory {
+    pub item: Option<ItemKind>,
+}
+
+/// Remaining duration (in seconds) of timed item effects on a car
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct ItemEffects {
+    pub boost: f32,
+    pub shield: f32,
+    pub spinout: f32,
+}
+
+impl ItemEffects {
+    pub fn is_boosting(&self) -> bool {
+        self.boost > 0.0
+    }
+
+    pub fn is_shielded(&self) -> bool {
+        self.shield > 0.0
+    }
+
+    pub fn is_spinning(&self) -> bool {
+        self.spinout > 0.0
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default, Reflect)]
+pub enum HazardKind {
+    #[default]
+    OilSlick,
+    Projectile,
+}
+
+/// Something dropped or fired by an item
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct Hazard {
+    pub kind: HazardKind,
+    /// `Player::client_id` of the car that used the item
+    pub owner: u64,
+}
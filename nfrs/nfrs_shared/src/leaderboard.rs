@@ -0,0 +1,40 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Laps needed to finish a race
+pub const RACE_LAPS: u32 = 3;
+
+/// A player's fastest lap on a track
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default, Reflect)]
+pub struct LapRecord {
+    pub player: String,
+    /// Seconds
+    pub lap_time: f32,
+}
+
+/// Career totals of a player, across every track
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default, Reflect)]
+pub struct PlayerStats {
+    pub player: String,
+    /// Races finished, and how many of them were won
+    pub races: u32,
+    pub wins: u32,
+    /// Distance driven, in track units (a car is 4 long)
+    pub distance: f32,
+}
+
+/// Client -> server: ask for the leaderboards of a track
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default, Reflect)]
+pub struct LeaderboardRequest {
+    pub track: String,
+}
+
+/// Server -> client: answer to a [`LeaderboardRequest`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default, Reflect)]
+pub struct Leaderboard {
+    pub track: String,
+    /// Fastest laps on the track, best first
+    pub best_laps: Vec<LapRecord>,
+    /// Players with the most wins
+    pub players: Vec<PlayerStats>,
+}
@@ -4,8 +4,28 @@ use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tracing::info;
 
+mod chat;
+mod driving;
+mod ghost;
+mod items;
+mod leaderboard;
+mod replay;
+mod track;
+
+pub use chat::{ChatLine, ChatSend, MAX_CHAT_LEN};
+pub use driving::{heading_forward, CarMotion, CAR_ANGULAR_DAMPING};
+pub use ghost::{Ghost, GhostDownload, GhostRequest, GhostSample, GhostUpload, MAX_GHOST_SAMPLES};
+pub use items::{Hazard, HazardKind, Inventory, ItemBox, ItemEffects, ItemKind};
+pub use leaderboard::{LapRecord, Leaderboard, LeaderboardRequest, PlayerStats, RACE_LAPS};
+pub use replay::{
+    CarState, InputChange, Keyframe, Replay, ReplayCar, REPLAY_EXTENSION, REPLAY_VERSION,
+};
+pub use track::{SurfaceKind, SurfaceProperties, SurfaceRegion, TrackLayout};
+
 pub const FIXED_TIMESTEP_HZ: f64 = 60.0;
 pub const SERVER_REPLICATION_INTERVAL: Duration = Duration::from_millis(100);
+/// Players that can share one client, in split-screen
+pub const MAX_LOCAL_PLAYERS: u8 = 4;
 
 #[derive(Clone)]
 pub struct ProtocolPlugin;
@@ -19,10 +39,31 @@ impl Plugin for ProtocolPlugin {
         app.register_component::<Car>();
         app.register_component::<PlayerPosition>();
         app.register_component::<Transform>();
+        app.register_component::<CarDamage>();
+        app.register_component::<Nitro>();
+        app.register_component::<SurfaceRegion>();
+        app.register_component::<ItemBox>();
+        app.register_component::<Inventory>();
+        app.register_component::<ItemEffects>();
+        app.register_component::<Hazard>();
+        app.register_component::<RaceProgress>();
+        app.register_component::<CarVelocity>();
+        app.register_component::<StartCountdown>();
 
         // Register the message protocol
         app.add_message::<CarInput>();
         app.add_message::<JoinRequest>();
+        app.add_message::<ServerNotice>();
+        app.add_message::<ImpactEvent>();
+        app.add_message::<ServerStatus>();
+        app.add_message::<JoinAccepted>();
+        app.add_message::<GhostUpload>();
+        app.add_message::<GhostRequest>();
+        app.add_message::<GhostDownload>();
+        app.add_message::<LeaderboardRequest>();
+        app.add_message::<Leaderboard>();
+        app.add_message::<ChatSend>();
+        app.add_message::<ChatLine>();
 
         // Register the input channel
         app.add_channel::<InputChannel>(ChannelSettings {
@@ -30,6 +71,20 @@ impl Plugin for ProtocolPlugin {
             ..default()
         })
         .add_direction(NetworkDirection::ClientToServer);
+
+        // Register the channel for server -> client notices
+        app.add_channel::<ServerChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            ..default()
+        })
+        .add_direction(NetworkDirection::ServerToClient);
+
+        // Register the channel for cosmetic events; losing one is harmless
+        app.add_channel::<EffectsChannel>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            ..default()
+        })
+        .add_direction(NetworkDirection::ServerToClient);
     }
 }
 
@@ -39,6 +94,8 @@ pub struct Player {
     pub username: String,
     // Store color as RGB [r, g, b]
     pub color: [f32; 3],
+    /// Driven by the server AI rather than a connected client
+    pub is_bot: bool,
 }
 
 #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
@@ -54,19 +111,191 @@ pub struct Car {
     pub steering_speed: f32,
 }
 
+/// Linear velocity of a car in m/s, replicated for cameras and effects
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub struct CarVelocity(pub Vec2);
+
+/// Whole seconds a car still has to wait on the grid before it may drive; 0 once it
+/// is racing. Only cars that have counted down have it.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub struct StartCountdown(pub u8);
+
+/// Accumulated collision damage, from 0 to [`CarDamage::MAX`]
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct CarDamage {
+    pub amount: f32,
+}
+
+impl CarDamage {
+    pub const MAX: f32 = 100.0;
+
+    /// Damage as a fraction in 0..=1
+    pub fn fraction(&self) -> f32 {
+        (self.amount / Self::MAX).clamp(0.0, 1.0)
+    }
+}
+
+/// Nitro meter and slipstream state of a car
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Nitro {
+    /// Fill level in 0..=1
+    pub meter: f32,
+    pub boosting: bool,
+    /// Following closely behind another car
+    pub drafting: bool,
+}
+
+impl Default for Nitro {
+    fn default() -> Self {
+        Self {
+            meter: 1.0,
+            boosting: false,
+            drafting: false,
+        }
+    }
+}
+
+/// Lap counting and timing of a car. Lap 0 means the car has not crossed the start line yet.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct RaceProgress {
+    pub lap: u32,
+    /// Index into `TrackLayout::checkpoints` of the next checkpoint to pass
+    pub next_checkpoint: u8,
+    /// Seconds taken by the last completed lap, and by the fastest one
+    pub last_lap: Option<f32>,
+    pub best_lap: Option<f32>,
+}
+
+impl RaceProgress {
+    /// Checkpoints passed since the car first crossed the start line, for ordering cars
+    pub fn checkpoints_passed(&self, checkpoints: usize) -> u32 {
+        if self.lap == 0 || checkpoints == 0 {
+            return 0;
+        }
+        let count = checkpoints as u32;
+        let this_lap = (self.next_checkpoint as u32 + count - 1) % count + 1;
+        (self.lap - 1) * count + this_lap
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default, Reflect)]
 pub struct CarInput {
     pub forward: bool,
     pub backward: bool,
     pub left: bool,
     pub right: bool,
+    pub use_item: bool,
+    pub boost: bool,
+    /// Locks the rear wheels: brakes hard and lets the car slide
+    pub handbrake: bool,
+    /// Analog throttle from a trigger or stick, -1 (full brake) to 1. Used instead of
+    /// `forward`/`backward` when non-zero.
+    pub throttle: f32,
+    /// Analog steering, -1 (full left) to 1. Used instead of `left`/`right` when non-zero.
+    pub steering: f32,
+    /// Which of the client's local players this is for, 0 for the first
+    pub seat: u8,
+}
+
+impl CarInput {
+    /// Throttle from -1 to 1, whether it came from keys or an analog input
+    pub fn throttle_axis(&self) -> f32 {
+        analog_or(self.throttle, self.forward, self.backward)
+    }
+
+    /// Steering from -1 (left) to 1 (right), whether it came from keys or an analog input
+    pub fn steering_axis(&self) -> f32 {
+        analog_or(self.steering, self.right, self.left)
+    }
+}
+
+/// Analog value clamped to -1..=1, or the digital pair if there is none.
+/// Garbage from the network (NaN, infinities) counts as no analog input.
+fn analog_or(analog: f32, positive: bool, negative: bool) -> f32 {
+    if analog.is_finite() && analog != 0.0 {
+        return analog.clamp(-1.0, 1.0);
+    }
+    positive as i8 as f32 - negative as i8 as f32
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default, Reflect)]
 pub struct JoinRequest {
     pub username: String,
+    /// Watch the race without driving; no car is spawned
+    pub spectator: bool,
+    /// Local player joining, below [`MAX_LOCAL_PLAYERS`]; split-screen clients send one
+    /// request per player over the same connection
+    pub seat: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default, Reflect)]
+pub enum NoticeLevel {
+    #[default]
+    Info,
+    Warning,
+    /// The server is about to drop the connection
+    Kicked,
+}
+
+/// Sent to a client once it has joined, after its car (if it drives) has been spawned
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default, Reflect)]
+pub struct JoinAccepted {
+    /// `Player::client_id` of the client's car (spectators have none)
+    pub client_id: u64,
+    /// `TrackLayout::name` of the track being raced
+    pub track: String,
+    /// `JoinRequest::seat` this answers
+    pub seat: u8,
+}
+
+/// Out-of-band message from the server to a single client (e.g. anti-cheat warnings)
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default, Reflect)]
+pub struct ServerNotice {
+    pub level: NoticeLevel,
+    pub text: String,
+}
+
+/// Periodic server health report, sent to every client once per second
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default, Reflect)]
+pub struct ServerStatus {
+    /// Simulation ticks run during the last second
+    pub tick_rate: f32,
+    /// Simulation ticks the server aims to run per second
+    pub target_tick_rate: f32,
+    /// Average and worst frame time during the last second, in milliseconds
+    pub frame_ms_avg: f32,
+    pub frame_ms_max: f32,
+    pub clients: u32,
+    pub cars: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default, Reflect)]
+pub enum ImpactKind {
+    #[default]
+    CarToCar,
+    CarToWall,
+}
+
+/// A collision involving at least one car, sent to clients for effects
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default, Reflect)]
+pub struct ImpactEvent {
+    pub kind: ImpactKind,
+    /// World position of the impact
+    pub position: Vec2,
+    /// Velocity change of the hardest hit car, in m/s
+    pub strength: f32,
+    /// `Player::client_id` of the cars involved
+    pub client_ids: Vec<u64>,
 }
 
 // Channel for sending car inputs
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct InputChannel;
+
+// Channel for server -> client messages
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ServerChannel;
+
+// Channel for server -> client cosmetic events (impacts, effects)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct EffectsChannel;
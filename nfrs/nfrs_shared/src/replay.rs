@@ -0,0 +1,212 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{heading_forward, Car, CarInput, CarMotion, Player, SurfaceKind, SurfaceRegion};
+
+/// Bumped whenever the file layout changes
+pub const REPLAY_VERSION: u32 = 4;
+pub const REPLAY_EXTENSION: &str = "nfrsreplay";
+
+/// A recorded session: who drove, what they pressed each tick, and where every car was.
+/// Inputs are stored only when they change; car states at a fixed keyframe interval.
+/// Playback steps the shared car model from each keyframe with the recorded inputs.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+    pub version: u32,
+    pub track: String,
+    pub tick_rate: f32,
+    pub cars: Vec<ReplayCar>,
+    pub keyframes: Vec<Keyframe>,
+    pub inputs: Vec<InputChange>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReplayCar {
+    /// Index into `Replay::cars`, used by keyframes and inputs
+    pub id: u16,
+    pub player: Player,
+    /// Handling the car was driven with
+    pub car: Car,
+    pub first_tick: u32,
+    /// Last tick the car existed; `None` while it is still driving
+    pub last_tick: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Keyframe {
+    pub tick: u32,
+    pub cars: Vec<CarState>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct CarState {
+    pub car: u16,
+    pub position: Vec2,
+    /// Heading in radians (0 = facing +Y)
+    pub rotation: f32,
+    pub velocity: Vec2,
+    /// Radians per second, counter-clockwise
+    pub angular_velocity: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct InputChange {
+    pub tick: u32,
+    pub car: u16,
+    pub input: CarInput,
+}
+
+impl Replay {
+    pub fn new(track: &str, tick_rate: f32) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            track: track.to_string(),
+            tick_rate,
+            ..default()
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("replays always serialize")
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let replay: Self = bincode::deserialize(bytes).map_err(|e| e.to_string())?;
+        if replay.version != REPLAY_VERSION {
+            return Err(format!(
+                "replay version {} is not supported (expected {})",
+                replay.version, REPLAY_VERSION
+            ));
+        }
+        Ok(replay)
+    }
+
+    /// Tick of the last keyframe
+    pub fn last_tick(&self) -> u32 {
+        self.keyframes.last().map_or(0, |frame| frame.tick)
+    }
+
+    pub fn duration_secs(&self) -> f32 {
+        self.last_tick() as f32 / self.tick_rate
+    }
+
+    /// Input a car was holding at `tick`
+    pub fn input_at(&self, car: u16, tick: u32) -> CarInput {
+        self.inputs_from(car, tick).next().unwrap_or_default()
+    }
+
+    /// Input a car was holding on each tick from `start` on
+    fn inputs_from(&self, car: u16, start: u32) -> impl Iterator<Item = CarInput> + '_ {
+        let next = self.inputs.partition_point(|change| change.tick <= start);
+        let mut held = self.inputs[..next]
+            .iter()
+            .rev()
+            .find(|change| change.car == car)
+            .map(|change| change.input)
+            .unwrap_or_default();
+        let mut changes = self.inputs[next..]
+            .iter()
+            .filter(move |change| change.car == car)
+            .peekable();
+        (start..).map(move |tick| {
+            while let Some(change) = changes.next_if(|change| change.tick <= tick) {
+                held = change.input;
+            }
+            held
+        })
+    }
+
+    /// State of a car on every tick from a keyframe, `ticks` long, driven by its recorded
+    /// inputs through the shared car model. Collisions, items and nitro are not simulated.
+    fn simulate(
+        &self,
+        surfaces: &[(Vec2, SurfaceRegion)],
+        from: &CarState,
+        start: u32,
+        ticks: u32,
+    ) -> Vec<CarState> {
+        let car = self
+            .cars
+            .get(from.car as usize)
+            .map(|recorded| recorded.car.clone())
+            .unwrap_or_default();
+        let dt = 1.0 / self.tick_rate;
+        let mut state = *from;
+        let mut states = vec![state];
+        for input in self.inputs_from(from.car, start).take(ticks as usize) {
+            let properties = SurfaceKind::at(surfaces, state.position).properties();
+            let forward = heading_forward(state.rotation);
+            let mut motion = CarMotion {
+                linear: state.velocity,
+                angular: state.angular_velocity,
+            };
+            car.drive(&mut motion, forward, &input, properties.max_speed_factor);
+            properties.push(&mut motion, forward, input.handbrake, dt);
+            car.limit_speed(&mut motion, properties.max_speed_factor, dt);
+            motion.integrate(
+                &mut state.position,
+                &mut state.rotation,
+                properties.drag,
+                dt,
+            );
+            state.velocity = motion.linear;
+            state.angular_velocity = motion.angular;
+            states.push(state);
+        }
+        states
+    }
+
+    /// Car states at a (fractional) tick on a track with the given `surfaces`. Each car is
+    /// stepped from the keyframe before `tick` with its recorded inputs. Whatever the model
+    /// misses (collisions, items) shows up as a gap to the next keyframe, which is spread
+    /// over the span so playback never jumps.
+    pub fn sample(&self, surfaces: &[(Vec2, SurfaceRegion)], tick: f32) -> Vec<CarState> {
+        let next = self
+            .keyframes
+            .partition_point(|frame| (frame.tick as f32) < tick);
+        let (Some(a), Some(b)) = (
+            self.keyframes.get(next.saturating_sub(1)),
+            self.keyframes
+                .get(next.min(self.keyframes.len().saturating_sub(1))),
+        ) else {
+            return Vec::new();
+        };
+        if a.tick == b.tick {
+            return b.cars.clone();
+        }
+
+        let ticks = b.tick - a.tick;
+        let offset = (tick - a.tick as f32).clamp(0.0, ticks as f32);
+        let t = offset / ticks as f32;
+        // Between the two simulated ticks around `tick`
+        let step = (offset.floor() as usize).min(ticks as usize - 1);
+        let blend = offset - step as f32;
+        a.cars
+            .iter()
+            .filter_map(|from| {
+                let to = b.cars.iter().find(|to| to.car == from.car)?;
+                let states = self.simulate(surfaces, from, a.tick, ticks);
+                let end = states.last()?;
+                let (before, after) = (&states[step], &states[step + 1]);
+                Some(CarState {
+                    car: from.car,
+                    position: before.position.lerp(after.position, blend)
+                        + (to.position - end.position) * t,
+                    rotation: before.rotation
+                        + wrap_angle(after.rotation - before.rotation) * blend
+                        + wrap_angle(to.rotation - end.rotation) * t,
+                    velocity: before.velocity.lerp(after.velocity, blend)
+                        + (to.velocity - end.velocity) * t,
+                    angular_velocity: before.angular_velocity
+                        + (after.angular_velocity - before.angular_velocity) * blend
+                        + (to.angular_velocity - end.angular_velocity) * t,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Shortest signed difference between two angles
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI
+}
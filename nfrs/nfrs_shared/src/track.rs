@@ -0,0 +1,227 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Kind of ground a car can drive on
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Reflect)]
+pub enum SurfaceKind {
+    #[default]
+    Asphalt,
+    Grass,
+    Gravel,
+    Ice,
+    BoostPad,
+}
+
+/// How a surface affects the car controller
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SurfaceProperties {
+    /// Share of sideways velocity removed per second (1.0 = full grip)
+    pub grip: f32,
+    /// Multiplier applied to the car's max speed
+    pub max_speed_factor: f32,
+    /// Linear damping while on this surface
+    pub drag: f32,
+    /// Extra forward acceleration applied every tick, regardless of input
+    pub boost: f32,
+}
+
+impl SurfaceKind {
+    pub fn properties(self) -> SurfaceProperties {
+        match self {
+            SurfaceKind::Asphalt => SurfaceProperties {
+                grip: 1.0,
+                max_speed_factor: 1.0,
+                drag: 2.0,
+                boost: 0.0,
+            },
+            SurfaceKind::Grass => SurfaceProperties {
+                grip: 0.6,
+                max_speed_factor: 0.55,
+                drag: 3.5,
+                boost: 0.0,
+            },
+            SurfaceKind::Gravel => SurfaceProperties {
+                grip: 0.4,
+                max_speed_factor: 0.4,
+                drag: 5.0,
+                boost: 0.0,
+            },
+            SurfaceKind::Ice => SurfaceProperties {
+                grip: 0.05,
+                max_speed_factor: 0.9,
+                drag: 0.3,
+                boost: 0.0,
+            },
+            SurfaceKind::BoostPad => SurfaceProperties {
+                grip: 1.0,
+                max_speed_factor: 1.5,
+                drag: 1.0,
+                boost: 25.0,
+            },
+        }
+    }
+
+    /// When regions overlap, the one with the highest priority decides the surface
+    pub fn priority(self) -> u8 {
+        match self {
+            SurfaceKind::Grass => 0,
+            SurfaceKind::Asphalt => 1,
+            SurfaceKind::Gravel => 2,
+            SurfaceKind::Ice => 3,
+            SurfaceKind::BoostPad => 4,
+        }
+    }
+
+    /// Surface at a point of a track: the highest priority region covering it, or grass
+    pub fn at(surfaces: &[(Vec2, SurfaceRegion)], position: Vec2) -> Self {
+        surfaces
+            .iter()
+            .filter(|(center, region)| {
+                let offset = (position - *center).abs();
+                offset.x <= region.half_extents.x && offset.y <= region.half_extents.y
+            })
+            .map(|(_, region)| region.kind)
+            .max_by_key(|kind| kind.priority())
+            .unwrap_or(SurfaceKind::Grass)
+    }
+}
+
+/// A rectangular patch of ground, replicated so clients can draw it
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct SurfaceRegion {
+    pub kind: SurfaceKind,
+    pub half_extents: Vec2,
+}
+
+/// Static description of a track
+#[derive(Clone, Debug)]
+pub struct TrackLayout {
+    pub name: &'static str,
+    /// (center, region) pairs
+    pub surfaces: Vec<(Vec2, SurfaceRegion)>,
+    pub item_boxes: Vec<Vec2>,
+    /// Closed loop of waypoints in driving order, used by bots
+    pub racing_line: Vec<Vec2>,
+    /// (center, half extents) of the areas a lap must pass through, in order.
+    /// The first one is the start/finish line.
+    pub checkpoints: Vec<(Vec2, Vec2)>,
+    /// First grid slot; further slots are laid out behind it
+    pub grid_origin: Vec2,
+    /// Heading of the grid, in radians (0 = facing +Y)
+    pub grid_heading: f32,
+}
+
+impl TrackLayout {
+    /// The default oval, built inside the +/- 32 x +/- 18 arena
+    pub fn oval() -> Self {
+        let region = |kind, hx, hy| SurfaceRegion {
+            kind,
+            half_extents: Vec2::new(hx, hy),
+        };
+        Self {
+            name: "oval",
+            surfaces: vec![
+                // Ground everywhere else
+                (Vec2::ZERO, region(SurfaceKind::Grass, 32.0, 18.0)),
+                // Straights
+                (
+                    Vec2::new(0.0, -12.0),
+                    region(SurfaceKind::Asphalt, 30.0, 4.0),
+                ),
+                (
+                    Vec2::new(0.0, 12.0),
+                    region(SurfaceKind::Asphalt, 30.0, 4.0),
+                ),
+                (
+                    Vec2::new(-26.0, 0.0),
+                    region(SurfaceKind::Asphalt, 4.0, 16.0),
+                ),
+                (
+                    Vec2::new(26.0, 0.0),
+                    region(SurfaceKind::Asphalt, 4.0, 16.0),
+                ),
+                // Gravel traps on the inside of each corner
+                (
+                    Vec2::new(-19.0, -5.0),
+                    region(SurfaceKind::Gravel, 3.0, 3.0),
+                ),
+                (Vec2::new(19.0, -5.0), region(SurfaceKind::Gravel, 3.0, 3.0)),
+                (Vec2::new(-19.0, 5.0), region(SurfaceKind::Gravel, 3.0, 3.0)),
+                (Vec2::new(19.0, 5.0), region(SurfaceKind::Gravel, 3.0, 3.0)),
+                // Ice patch on the back straight
+                (Vec2::new(-26.0, 0.0), region(SurfaceKind::Ice, 4.0, 3.0)),
+                // Boost pads
+                (
+                    Vec2::new(8.0, -12.0),
+                    region(SurfaceKind::BoostPad, 2.5, 1.5),
+                ),
+                (
+                    Vec2::new(-8.0, 12.0),
+                    region(SurfaceKind::BoostPad, 2.5, 1.5),
+                ),
+            ],
+            // A row of three boxes across each long straight
+            item_boxes: vec![
+                Vec2::new(16.0, -14.5),
+                Vec2::new(16.0, -12.0),
+                Vec2::new(16.0, -9.5),
+                Vec2::new(-16.0, 14.5),
+                Vec2::new(-16.0, 12.0),
+                Vec2::new(-16.0, 9.5),
+            ],
+            // Anticlockwise, cutting the corners wide of the gravel traps
+            racing_line: vec![
+                Vec2::new(-10.0, -12.0),
+                Vec2::new(10.0, -12.0),
+                Vec2::new(22.0, -11.5),
+                Vec2::new(26.0, -7.0),
+                Vec2::new(26.0, 7.0),
+                Vec2::new(22.0, 11.5),
+                Vec2::new(10.0, 12.0),
+                Vec2::new(-10.0, 12.0),
+                Vec2::new(-22.0, 11.5),
+                Vec2::new(-26.0, 7.0),
+                Vec2::new(-26.0, -7.0),
+                Vec2::new(-22.0, -11.5),
+            ],
+            // Start/finish line, then one gate on each other straight
+            checkpoints: vec![
+                (Vec2::new(0.0, -12.0), Vec2::new(1.0, 4.0)),
+                (Vec2::new(26.0, 0.0), Vec2::new(4.0, 1.0)),
+                (Vec2::new(0.0, 12.0), Vec2::new(1.0, 4.0)),
+                (Vec2::new(-26.0, 0.0), Vec2::new(4.0, 1.0)),
+            ],
+            grid_origin: Vec2::new(-4.0, -12.0),
+            grid_heading: -std::f32::consts::FRAC_PI_2,
+        }
+    }
+
+    /// Look up a built-in track by its `name`
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "oval" => Some(Self::oval()),
+            _ => None,
+        }
+    }
+
+    /// Smallest rectangle covering every surface
+    pub fn bounds(&self) -> Rect {
+        self.surfaces
+            .iter()
+            .map(|(center, region)| Rect::from_center_half_size(*center, region.half_extents))
+            .reduce(|a, b| a.union(b))
+            .unwrap_or_default()
+    }
+
+    /// Starting transform for the given grid slot (two cars per row)
+    pub fn grid_slot(&self, slot: usize) -> Transform {
+        let row = (slot / 2) as f32;
+        let column = if slot.is_multiple_of(2) { 1.0 } else { -1.0 };
+        let rotation = Quat::from_rotation_z(self.grid_heading);
+        // Rows go backwards along the heading, columns sideways
+        let forward = (rotation * Vec3::Y).truncate();
+        let side = Vec2::new(forward.y, -forward.x);
+        let position = self.grid_origin - forward * (row * 5.0) + side * (column * 2.0);
+        Transform::from_translation(position.extend(0.0)).with_rotation(rotation)
+    }
+}